@@ -9,30 +9,21 @@ fn main() -> Result<(), VmError> {
 
     let mut vm = VirtualMachine::new();
 
-    // Create a simple RISC-V program:
-    // 1. Load 10 into x1
-    // 2. Load 32 into x2  
-    // 3. Add x1 + x2 and store in x3
-    // 4. Exit with the result in x10
-    
-    let program = vec![
-        0x00a00093u32, // addi x1, x0, 10
-        0x02000113u32, // addi x2, x0, 32
-        0x002081b3u32, // add x3, x1, x2
-        0x00018513u32, // addi x10, x3, 0 (move x3 to x10 for exit code)
-        0x05d00893u32, // addi x17, x0, 93 (exit syscall number)
-        0x00000073u32, // ecall
-    ];
-
-    println!("Loading program into memory...");
-    
-    // Load program into memory starting at address 0x1000
-    for (i, &instruction) in program.iter().enumerate() {
-        vm.memory_mut().store_word(0x1000 + (i as u32) * 4, instruction)?;
-    }
-
-    // Set the program counter to start of program
-    vm.set_pc(0x1000);
+    // Add 10 and 32 and exit with the result in a0
+    let program = "
+        _start:
+            li x1, 10
+            li x2, 32
+            add x3, x1, x2
+            mv a0, x3          # exit code
+            li a7, 93          # exit syscall number
+            ecall
+    ";
+
+    println!("Assembling program into memory...");
+
+    // Assemble at 0x1000; the PC is set to `_start`
+    vm.load_asm(0x1000, program)?;
 
     println!("Initial state:");
     print_vm_state(&vm);
@@ -10,7 +10,7 @@ pub mod loader;
 pub mod utils;
 
 pub use utils::error::VmError;
-pub use vm::VirtualMachine;
+pub use vm::{ExitReason, VirtualMachine};
 pub use risc_v::Instruction;
 pub use zk::Proof;
 
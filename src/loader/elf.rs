@@ -1,32 +1,153 @@
 //! ELF file parsing
 
+use crate::risc_v::isa::Xlen;
 use crate::utils::VmError;
+use crate::vm::memory::Permissions;
+use goblin::elf::header::{EI_CLASS, EI_DATA, ELFCLASS32, ELFCLASS64, ELFDATA2LSB, EM_RISCV, ET_EXEC};
+use goblin::elf::program_header::{PF_R, PF_W, PF_X, PT_LOAD};
+use goblin::elf::sym::{STT_FUNC, STT_NOTYPE, STT_OBJECT};
+use goblin::elf::Elf;
+use std::collections::BTreeMap;
 use std::path::Path;
 
 /// ELF file loader
-pub struct ElfLoader {
-    // TODO: Implement ELF loading
-}
+pub struct ElfLoader;
 
 impl ElfLoader {
     pub fn new() -> Self {
-        Self {}
+        Self
+    }
+
+    /// Read and parse an ELF executable from disk
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<ElfFile, VmError> {
+        let bytes = std::fs::read(path)?;
+        self.parse(&bytes)
     }
 
-    pub fn load<P: AsRef<Path>>(&self, _path: P) -> Result<ElfFile, VmError> {
-        // TODO: Implement ELF loading using goblin
-        Err(VmError::ElfError("ELF loading not implemented".to_string()))
+    /// Parse an in-memory RV32 (ELFCLASS32) or RV64 (ELFCLASS64) little-endian
+    /// ELF executable; RV64 images must be linked below 4 GiB
+    pub fn parse(&self, bytes: &[u8]) -> Result<ElfFile, VmError> {
+        let elf = Elf::parse(bytes).map_err(|e| VmError::ElfError(e.to_string()))?;
+        let header = &elf.header;
+
+        let xlen = match header.e_ident[EI_CLASS] {
+            ELFCLASS32 => Xlen::Rv32,
+            ELFCLASS64 => Xlen::Rv64,
+            class => return Err(VmError::ElfError(format!("Unsupported ELF class: {}", class))),
+        };
+        if header.e_ident[EI_DATA] != ELFDATA2LSB {
+            return Err(VmError::ElfError("Not a little-endian ELF file".to_string()));
+        }
+        if header.e_machine != EM_RISCV {
+            return Err(VmError::ElfError(format!(
+                "Unsupported machine type: {}",
+                header.e_machine
+            )));
+        }
+        if header.e_type != ET_EXEC {
+            return Err(VmError::ElfError(format!(
+                "Not an executable ELF file (type {})",
+                header.e_type
+            )));
+        }
+
+        let entry_point = u32::try_from(header.e_entry).map_err(|_| {
+            VmError::ElfError(format!("Entry point {:x} exceeds the 32-bit address space", header.e_entry))
+        })?;
+
+        let mut segments = Vec::new();
+        for ph in elf.program_headers.iter().filter(|ph| ph.p_type == PT_LOAD) {
+            if ph.p_filesz > ph.p_memsz {
+                return Err(VmError::ElfError(format!(
+                    "Segment at {:08x} has file size larger than memory size",
+                    ph.p_vaddr
+                )));
+            }
+            if ph.p_vaddr.checked_add(ph.p_memsz).is_none_or(|end| end > 1 << 32) {
+                return Err(VmError::ElfError(format!(
+                    "Segment at {:08x} exceeds the 32-bit address space",
+                    ph.p_vaddr
+                )));
+            }
+
+            let start = ph.p_offset as usize;
+            let data = start
+                .checked_add(ph.p_filesz as usize)
+                .and_then(|end| bytes.get(start..end))
+                .ok_or_else(|| {
+                    VmError::ElfError(format!("Segment at {:08x} is truncated", ph.p_vaddr))
+                })?;
+
+            segments.push(ElfSegment {
+                virtual_addr: ph.p_vaddr as u32,
+                data: data.to_vec(),
+                mem_size: ph.p_memsz as u32,
+                flags: ph.p_flags,
+            });
+        }
+
+        // Named code and data symbols, keeping the first name seen at each address
+        let mut symbols = BTreeMap::new();
+        for sym in elf.syms.iter() {
+            if !matches!(sym.st_type(), STT_NOTYPE | STT_FUNC | STT_OBJECT) {
+                continue;
+            }
+            let Ok(addr) = u32::try_from(sym.st_value) else {
+                continue;
+            };
+            if let Some(name) = elf.strtab.get_at(sym.st_name).filter(|name| !name.is_empty()) {
+                symbols.entry(addr).or_insert_with(|| name.to_string());
+            }
+        }
+
+        Ok(ElfFile {
+            xlen,
+            entry_point,
+            segments,
+            symbols,
+        })
+    }
+}
+
+impl Default for ElfLoader {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 /// Parsed ELF file representation
 pub struct ElfFile {
+    /// Register width from the ELF class
+    pub xlen: Xlen,
     pub entry_point: u32,
     pub segments: Vec<ElfSegment>,
+    /// Symbol names by address, from the symbol table if present
+    pub symbols: BTreeMap<u32, String>,
 }
 
 /// ELF segment
 pub struct ElfSegment {
     pub virtual_addr: u32,
+    /// Initialized bytes from the file (`p_filesz`)
     pub data: Vec<u8>,
-}
\ No newline at end of file
+    /// Size of the segment in memory (`p_memsz`); the tail past `data` is zero-filled
+    pub mem_size: u32,
+    /// Program header flags (`PF_R`, `PF_W`, `PF_X`)
+    pub flags: u32,
+}
+
+impl ElfSegment {
+    /// Check whether the segment contains executable code
+    pub fn is_executable(&self) -> bool {
+        self.flags & PF_X != 0
+    }
+
+    /// Memory permissions from the `PF_R`, `PF_W` and `PF_X` flags
+    pub fn permissions(&self) -> Permissions {
+        Permissions {
+            read: self.flags & PF_R != 0,
+            write: self.flags & PF_W != 0,
+            execute: self.flags & PF_X != 0,
+        }
+    }
+}
@@ -16,4 +16,10 @@ impl Linker {
         // TODO: Implement linking logic
         Ok(())
     }
+}
+
+impl Default for Linker {
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file
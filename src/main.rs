@@ -1,7 +1,13 @@
 use clap::{Parser, Subcommand};
+use serde::Serialize;
 use std::path::PathBuf;
 use tracing::info;
-use zk_risc_v_vm::{VirtualMachine, Result};
+use zk_risc_v_vm::loader::ElfLoader;
+use zk_risc_v_vm::risc_v::isa::IsaConfig;
+use zk_risc_v_vm::risc_v::Disassembler;
+use zk_risc_v_vm::utils::config::VmConfig;
+use zk_risc_v_vm::zk::{ProofSystem, Prover};
+use zk_risc_v_vm::{ExitReason, VirtualMachine, Result};
 
 #[derive(Parser)]
 #[command(name = "zkvm")]
@@ -25,6 +31,27 @@ enum Commands {
         /// Output file for the proof
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// Print the run summary as JSON
+        #[arg(long)]
+        json: bool,
+        /// VM configuration file (TOML if it ends in `.toml`, JSON otherwise)
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Maximum number of cycles before the run stops
+        #[arg(long)]
+        max_cycles: Option<u64>,
+        /// Memory limit in bytes, optionally with a K, M or G suffix
+        #[arg(long, value_parser = parse_size)]
+        memory_size: Option<usize>,
+        /// ISA string such as `rv32imac` (defaults to every extension at the ELF file's width)
+        #[arg(long)]
+        isa: Option<IsaConfig>,
+    },
+    /// Disassemble the executable segments of an ELF file
+    Disasm {
+        /// Path to the ELF file to disassemble
+        #[arg(short, long)]
+        file: PathBuf,
     },
     /// Verify a previously generated proof
     Verify {
@@ -43,38 +70,132 @@ enum Commands {
     },
 }
 
+/// Outcome of `zkvm execute`, printed with `--json`
+#[derive(Serialize)]
+struct RunSummary {
+    /// `halted`, `cycle_limit`, `breakpoint` or `fault`
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pc: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    cycles: u64,
+    instret: u64,
+}
+
+impl RunSummary {
+    fn new(reason: &ExitReason, vm: &VirtualMachine) -> Self {
+        let (status, exit_code, pc, error) = match reason {
+            ExitReason::Halted { code } => ("halted", Some(*code), None, None),
+            ExitReason::CycleLimit => ("cycle_limit", None, None, None),
+            ExitReason::Breakpoint { pc } => ("breakpoint", None, Some(*pc), None),
+            ExitReason::Fault { pc, error } => ("fault", None, Some(*pc), Some(error.to_string())),
+            ExitReason::Paused => unreachable!("a complete run does not pause"),
+        };
+        let stats = vm.get_stats();
+        Self { status, exit_code, pc, error, cycles: stats.cycles, instret: stats.instret }
+    }
+}
+
+/// Parse a byte count such as `65536`, `64K`, `16M` or `1G`
+fn parse_size(value: &str) -> std::result::Result<usize, String> {
+    let (digits, shift) = match value.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&value[..i], 10),
+        Some((i, 'm' | 'M')) => (&value[..i], 20),
+        Some((i, 'g' | 'G')) => (&value[..i], 30),
+        _ => (value, 0),
+    };
+    let count: usize = digits.parse().map_err(|_| format!("invalid size `{}`", value))?;
+    count.checked_mul(1 << shift).ok_or_else(|| format!("size `{}` is too large", value))
+}
+
 fn main() -> Result<()> {
     // Initialize tracing
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
         .init();
 
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Execute { file, prove, output } => {
+        Commands::Execute { file, prove, output, json, config, max_cycles, memory_size, isa } => {
             info!("Executing RISC-V program: {:?}", file);
             
-            let mut vm = VirtualMachine::new();
-            vm.load_elf(&file)?;
+            // Defaults to every extension available at the ELF file's register width,
+            // overridden by the config file and then by individual flags
+            let elf = ElfLoader::new().load(&file)?;
+            let mut vm_config = VmConfig { isa: IsaConfig::full(elf.xlen), ..VmConfig::default() };
+            if let Some(path) = config {
+                vm_config = vm_config.with_file(path)?;
+            }
+            if let Some(max_cycles) = max_cycles {
+                vm_config.max_cycles = max_cycles;
+            }
+            if let Some(memory_size) = memory_size {
+                vm_config.memory_size = memory_size;
+            }
+            if let Some(isa) = isa {
+                vm_config.isa = isa;
+            }
+            vm_config.enable_proofs |= prove;
+            
+            let mut vm = VirtualMachine::with_config(vm_config);
+            vm.load_elf_file(&elf)?;
             
-            if prove {
-                info!("Generating zero-knowledge proof...");
-                let proof = vm.execute_with_proof()?;
-                
-                if let Some(output_path) = output {
-                    std::fs::write(&output_path, serde_json::to_string(&proof)?)?;
-                    info!("Proof written to: {:?}", output_path);
-                } else {
-                    println!("{}", serde_json::to_string_pretty(&proof)?);
+            let reason = if prove {
+                // Only a run that halts is proven; other stops are reported in the summary
+                let (reason, trace) = vm.run_traced();
+                if let ExitReason::Halted { .. } = reason {
+                    info!("Generating zero-knowledge proof...");
+                    let proof = Prover::new().generate_proof(&trace)?;
+
+                    if let Some(output_path) = output {
+                        std::fs::write(&output_path, serde_json::to_string(&proof)?)?;
+                        info!("Proof written to: {:?}", output_path);
+                    } else if json {
+                        // Keep stdout for the JSON summary
+                        eprintln!("{}", serde_json::to_string_pretty(&proof)?);
+                    } else {
+                        println!("{}", serde_json::to_string_pretty(&proof)?);
+                    }
                 }
+                reason
+            } else {
+                vm.run()
+            };
+
+            let summary = RunSummary::new(&reason, &vm);
+            if json {
+                println!("{}", serde_json::to_string(&summary)?);
             } else {
-                vm.execute()?;
-                info!("Program executed successfully");
+                println!("{} (cycles: {}, instret: {})", reason, summary.cycles, summary.instret);
+            }
+
+            // The guest exit code becomes the process exit status; any other stop is a failure.
+            // Only the low byte survives, so a nonzero code like 256 must not turn into success.
+            let status = match reason {
+                ExitReason::Halted { code } if code != 0 && code & 0xff == 0 => 1,
+                ExitReason::Halted { code } => code as i32,
+                _ => 1,
+            };
+            if status != 0 {
+                std::process::exit(status);
+            }
+        }
+        
+        Commands::Disasm { file } => {
+            let elf = ElfLoader::new().load(&file)?;
+            let disassembler = Disassembler::with_symbols(elf.symbols).with_xlen(elf.xlen);
+
+            for segment in elf.segments.iter().filter(|s| s.is_executable()) {
+                print!("{}", disassembler.disassemble(&segment.data, segment.virtual_addr));
             }
         }
         
-        Commands::Verify { proof, vkey } => {
+        Commands::Verify { proof, vkey: _ } => {
             info!("Verifying proof: {:?}", proof);
             // TODO: Implement proof verification
             println!("Proof verification not yet implemented");
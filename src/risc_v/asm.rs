@@ -0,0 +1,1021 @@
+//! Two-pass RISC-V assembler for tests and examples
+//!
+//! Supports the RV32IMAFD, RV64IMA, Zicsr, bit-manipulation and scalar crypto instructions, labels, data directives
+//! (`.dword`, `.word`, `.half`, `.byte`, `.ascii`, `.asciz`, `.align`, `.space`) and the
+//! common pseudo-instructions (`li`, `la`, `mv`, `j`, `call`, `ret`, `fmv.s`, ...).
+//! Floating-point instructions that round take an optional trailing rounding
+//! mode operand (`rne`, `rtz`, `rdn`, `rup`, `rmm` or `dyn`).
+
+use crate::risc_v::{FloatFormat, Instruction, RegisterIndex, RoundingMode};
+use crate::utils::VmError;
+use crate::vm::csr::{csr_address, FCSR, FFLAGS, FRM};
+use std::collections::BTreeMap;
+
+type Reg = RegisterIndex;
+
+/// Assembled program image
+#[derive(Debug, Clone)]
+pub struct Program {
+    /// Address of the first byte of the image
+    pub base: u32,
+    /// Little-endian image bytes
+    pub data: Vec<u8>,
+    /// Entry point: the `_start` label if defined, otherwise `base`
+    pub entry: u32,
+    /// Label addresses
+    pub symbols: BTreeMap<String, u32>,
+}
+
+/// Assembler producing an image located at a fixed base address
+pub struct Assembler {
+    base: u32,
+}
+
+/// A source line split into its labels and an optional statement
+struct Line {
+    number: usize,
+    labels: Vec<String>,
+    mnemonic: Option<String>,
+    operands: Vec<String>,
+}
+
+impl Assembler {
+    /// Create an assembler for an image loaded at `base`
+    pub fn new(base: u32) -> Self {
+        Self { base }
+    }
+
+    /// Assemble source text into a program image
+    pub fn assemble(&self, source: &str) -> Result<Program, VmError> {
+        let lines = source
+            .lines()
+            .enumerate()
+            .map(|(i, text)| parse_line(i + 1, text))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Pass 1: lay out statements and assign label addresses. Unresolved
+        // labels evaluate to the current address; this never changes the size
+        // of a statement.
+        let mut symbols = BTreeMap::new();
+        let mut addr = self.base;
+        for line in &lines {
+            for label in &line.labels {
+                if symbols.insert(label.clone(), addr).is_some() {
+                    return Err(error(line.number, format!("duplicate label `{}`", label)));
+                }
+            }
+            if let Some(mnemonic) = &line.mnemonic {
+                let pass = Pass { symbols: &symbols, pc: addr, final_pass: false };
+                let size = pass.emit(mnemonic, &line.operands).map_err(|e| error(line.number, e))?.len();
+                addr = addr.wrapping_add(size as u32);
+            }
+        }
+
+        // Pass 2: emit the image with all labels resolved
+        let mut data = Vec::new();
+        for line in &lines {
+            if let Some(mnemonic) = &line.mnemonic {
+                let pc = self.base.wrapping_add(data.len() as u32);
+                let pass = Pass { symbols: &symbols, pc, final_pass: true };
+                data.extend(pass.emit(mnemonic, &line.operands).map_err(|e| error(line.number, e))?);
+            }
+        }
+
+        Ok(Program {
+            base: self.base,
+            data,
+            entry: symbols.get("_start").copied().unwrap_or(self.base),
+            symbols,
+        })
+    }
+}
+
+fn error(line: usize, message: String) -> VmError {
+    VmError::AssemblyError { line, message }
+}
+
+/// Strip comments, split off labels and the mnemonic, and split operands on commas
+fn parse_line(number: usize, text: &str) -> Result<Line, VmError> {
+    // Cut the comment, ignoring comment characters inside string and character literals
+    let mut quote = None;
+    let mut prev = '\0';
+    let mut end = text.len();
+    for (i, c) in text.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (_, Some(open)) if c == open && prev != '\\' => quote = None,
+            ('#' | ';', None) => {
+                end = i;
+                break;
+            },
+            ('/', None) if prev == '/' => {
+                end = i - 1;
+                break;
+            },
+            _ => {},
+        }
+        prev = c;
+    }
+    let mut rest = text[..end].trim();
+
+    let mut labels = Vec::new();
+    while let Some(colon) = rest.find(':') {
+        let label = &rest[..colon];
+        if label.is_empty() || !label.chars().all(|c| c.is_ascii_alphanumeric() || "_.$".contains(c)) {
+            break;
+        }
+        labels.push(label.to_string());
+        rest = rest[colon + 1..].trim_start();
+    }
+
+    if rest.is_empty() {
+        return Ok(Line { number, labels, mnemonic: None, operands: Vec::new() });
+    }
+
+    let (mnemonic, operands) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Ok(Line {
+        number,
+        labels,
+        mnemonic: Some(mnemonic.to_ascii_lowercase()),
+        operands: split_operands(operands),
+    })
+}
+
+/// Split operands on commas that are not inside string or character literals
+fn split_operands(text: &str) -> Vec<String> {
+    let mut operands = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut prev = '\0';
+
+    for c in text.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (_, Some(open)) if c == open && prev != '\\' => quote = None,
+            _ => {},
+        }
+        if c == ',' && quote.is_none() {
+            operands.push(std::mem::take(&mut current).trim().to_string());
+        } else {
+            current.push(c);
+        }
+        prev = c;
+    }
+    if !current.trim().is_empty() || !operands.is_empty() {
+        operands.push(current.trim().to_string());
+    }
+    operands
+}
+
+/// Parse an integer literal: decimal, `0x` hex, `0b` binary or a `'c'` character
+fn parse_int(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+
+    // Hex and binary literals may spell out all 64 bits, e.g. for `.dword`
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()? as i64
+    } else if let Some(bin) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        u64::from_str_radix(bin, 2).ok()? as i64
+    } else if digits.len() == 3 && digits.starts_with('\'') && digits.ends_with('\'') {
+        digits.as_bytes()[1] as i64
+    } else {
+        digits.parse().ok()?
+    };
+
+    if negative {
+        value.checked_neg()
+    } else {
+        Some(value)
+    }
+}
+
+/// Parse a double-quoted string literal with C-style escapes
+fn parse_string(text: &str) -> Result<Vec<u8>, String> {
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .ok_or_else(|| format!("expected string literal, found `{}`", text))?;
+
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        bytes.push(match chars.next() {
+            Some('n') => b'\n',
+            Some('t') => b'\t',
+            Some('r') => b'\r',
+            Some('0') => 0,
+            Some('\\') => b'\\',
+            Some('"') => b'"',
+            other => return Err(format!("invalid escape `\\{}`", other.unwrap_or(' '))),
+        });
+    }
+    Ok(bytes)
+}
+
+fn r_op(mnemonic: &str) -> Option<fn(Reg, Reg, Reg) -> Instruction> {
+    let op: fn(Reg, Reg, Reg) -> Instruction = match mnemonic {
+        "add" => |rd, rs1, rs2| Instruction::Add { rd, rs1, rs2 },
+        "sub" => |rd, rs1, rs2| Instruction::Sub { rd, rs1, rs2 },
+        "slt" => |rd, rs1, rs2| Instruction::Slt { rd, rs1, rs2 },
+        "sltu" => |rd, rs1, rs2| Instruction::Sltu { rd, rs1, rs2 },
+        "and" => |rd, rs1, rs2| Instruction::And { rd, rs1, rs2 },
+        "or" => |rd, rs1, rs2| Instruction::Or { rd, rs1, rs2 },
+        "xor" => |rd, rs1, rs2| Instruction::Xor { rd, rs1, rs2 },
+        "sll" => |rd, rs1, rs2| Instruction::Sll { rd, rs1, rs2 },
+        "srl" => |rd, rs1, rs2| Instruction::Srl { rd, rs1, rs2 },
+        "sra" => |rd, rs1, rs2| Instruction::Sra { rd, rs1, rs2 },
+        "mul" => |rd, rs1, rs2| Instruction::Mul { rd, rs1, rs2 },
+        "mulh" => |rd, rs1, rs2| Instruction::Mulh { rd, rs1, rs2 },
+        "mulhsu" => |rd, rs1, rs2| Instruction::Mulhsu { rd, rs1, rs2 },
+        "mulhu" => |rd, rs1, rs2| Instruction::Mulhu { rd, rs1, rs2 },
+        "div" => |rd, rs1, rs2| Instruction::Div { rd, rs1, rs2 },
+        "divu" => |rd, rs1, rs2| Instruction::Divu { rd, rs1, rs2 },
+        "rem" => |rd, rs1, rs2| Instruction::Rem { rd, rs1, rs2 },
+        "remu" => |rd, rs1, rs2| Instruction::Remu { rd, rs1, rs2 },
+        "addw" => |rd, rs1, rs2| Instruction::Addw { rd, rs1, rs2 },
+        "subw" => |rd, rs1, rs2| Instruction::Subw { rd, rs1, rs2 },
+        "sllw" => |rd, rs1, rs2| Instruction::Sllw { rd, rs1, rs2 },
+        "srlw" => |rd, rs1, rs2| Instruction::Srlw { rd, rs1, rs2 },
+        "sraw" => |rd, rs1, rs2| Instruction::Sraw { rd, rs1, rs2 },
+        "mulw" => |rd, rs1, rs2| Instruction::Mulw { rd, rs1, rs2 },
+        "divw" => |rd, rs1, rs2| Instruction::Divw { rd, rs1, rs2 },
+        "divuw" => |rd, rs1, rs2| Instruction::Divuw { rd, rs1, rs2 },
+        "remw" => |rd, rs1, rs2| Instruction::Remw { rd, rs1, rs2 },
+        "remuw" => |rd, rs1, rs2| Instruction::Remuw { rd, rs1, rs2 },
+        "sh1add" => |rd, rs1, rs2| Instruction::Sh1add { rd, rs1, rs2 },
+        "sh2add" => |rd, rs1, rs2| Instruction::Sh2add { rd, rs1, rs2 },
+        "sh3add" => |rd, rs1, rs2| Instruction::Sh3add { rd, rs1, rs2 },
+        "andn" => |rd, rs1, rs2| Instruction::Andn { rd, rs1, rs2 },
+        "orn" => |rd, rs1, rs2| Instruction::Orn { rd, rs1, rs2 },
+        "xnor" => |rd, rs1, rs2| Instruction::Xnor { rd, rs1, rs2 },
+        "max" => |rd, rs1, rs2| Instruction::Max { rd, rs1, rs2 },
+        "maxu" => |rd, rs1, rs2| Instruction::Maxu { rd, rs1, rs2 },
+        "min" => |rd, rs1, rs2| Instruction::Min { rd, rs1, rs2 },
+        "minu" => |rd, rs1, rs2| Instruction::Minu { rd, rs1, rs2 },
+        "rol" => |rd, rs1, rs2| Instruction::Rol { rd, rs1, rs2 },
+        "ror" => |rd, rs1, rs2| Instruction::Ror { rd, rs1, rs2 },
+        "bclr" => |rd, rs1, rs2| Instruction::Bclr { rd, rs1, rs2 },
+        "bext" => |rd, rs1, rs2| Instruction::Bext { rd, rs1, rs2 },
+        "binv" => |rd, rs1, rs2| Instruction::Binv { rd, rs1, rs2 },
+        "bset" => |rd, rs1, rs2| Instruction::Bset { rd, rs1, rs2 },
+        "sha512sig0h" => |rd, rs1, rs2| Instruction::Sha512Sig0h { rd, rs1, rs2 },
+        "sha512sig0l" => |rd, rs1, rs2| Instruction::Sha512Sig0l { rd, rs1, rs2 },
+        "sha512sig1h" => |rd, rs1, rs2| Instruction::Sha512Sig1h { rd, rs1, rs2 },
+        "sha512sig1l" => |rd, rs1, rs2| Instruction::Sha512Sig1l { rd, rs1, rs2 },
+        "sha512sum0r" => |rd, rs1, rs2| Instruction::Sha512Sum0r { rd, rs1, rs2 },
+        "sha512sum1r" => |rd, rs1, rs2| Instruction::Sha512Sum1r { rd, rs1, rs2 },
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Parse a FENCE predecessor or successor set such as `rw` or `iorw`
+fn fence_set(text: &str) -> Result<u32, String> {
+    let text = text.trim();
+    if text == "0" {
+        return Ok(0);
+    }
+    let mut bits = 0;
+    for c in text.chars() {
+        let bit = match c {
+            'i' => 8,
+            'o' => 4,
+            'r' => 2,
+            'w' => 1,
+            _ => return Err(format!("invalid fence set `{}`", text)),
+        };
+        if bits & bit != 0 {
+            return Err(format!("invalid fence set `{}`", text));
+        }
+        bits |= bit;
+    }
+    if bits == 0 {
+        return Err("empty fence set".to_string());
+    }
+    Ok(bits)
+}
+
+fn i_op(mnemonic: &str) -> Option<fn(Reg, Reg, i32) -> Instruction> {
+    let op: fn(Reg, Reg, i32) -> Instruction = match mnemonic {
+        "addi" => |rd, rs1, imm| Instruction::Addi { rd, rs1, imm },
+        "slti" => |rd, rs1, imm| Instruction::Slti { rd, rs1, imm },
+        "sltiu" => |rd, rs1, imm| Instruction::Sltiu { rd, rs1, imm },
+        "andi" => |rd, rs1, imm| Instruction::Andi { rd, rs1, imm },
+        "ori" => |rd, rs1, imm| Instruction::Ori { rd, rs1, imm },
+        "xori" => |rd, rs1, imm| Instruction::Xori { rd, rs1, imm },
+        "addiw" => |rd, rs1, imm| Instruction::Addiw { rd, rs1, imm },
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn shift_op(mnemonic: &str) -> Option<fn(Reg, Reg, u32) -> Instruction> {
+    let op: fn(Reg, Reg, u32) -> Instruction = match mnemonic {
+        "slli" => |rd, rs1, shamt| Instruction::Slli { rd, rs1, shamt },
+        "srli" => |rd, rs1, shamt| Instruction::Srli { rd, rs1, shamt },
+        "srai" => |rd, rs1, shamt| Instruction::Srai { rd, rs1, shamt },
+        "slliw" => |rd, rs1, shamt| Instruction::Slliw { rd, rs1, shamt },
+        "srliw" => |rd, rs1, shamt| Instruction::Srliw { rd, rs1, shamt },
+        "sraiw" => |rd, rs1, shamt| Instruction::Sraiw { rd, rs1, shamt },
+        "rori" => |rd, rs1, shamt| Instruction::Rori { rd, rs1, shamt },
+        "bclri" => |rd, rs1, shamt| Instruction::Bclri { rd, rs1, shamt },
+        "bexti" => |rd, rs1, shamt| Instruction::Bexti { rd, rs1, shamt },
+        "binvi" => |rd, rs1, shamt| Instruction::Binvi { rd, rs1, shamt },
+        "bseti" => |rd, rs1, shamt| Instruction::Bseti { rd, rs1, shamt },
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn unary_op(mnemonic: &str) -> Option<fn(Reg, Reg) -> Instruction> {
+    let op: fn(Reg, Reg) -> Instruction = match mnemonic {
+        "clz" => |rd, rs1| Instruction::Clz { rd, rs1 },
+        "ctz" => |rd, rs1| Instruction::Ctz { rd, rs1 },
+        "cpop" => |rd, rs1| Instruction::Cpop { rd, rs1 },
+        "sext.b" => |rd, rs1| Instruction::SextB { rd, rs1 },
+        "sext.h" => |rd, rs1| Instruction::SextH { rd, rs1 },
+        "zext.h" => |rd, rs1| Instruction::ZextH { rd, rs1 },
+        "orc.b" => |rd, rs1| Instruction::OrcB { rd, rs1 },
+        "rev8" => |rd, rs1| Instruction::Rev8 { rd, rs1 },
+        "sha256sig0" => |rd, rs1| Instruction::Sha256Sig0 { rd, rs1 },
+        "sha256sig1" => |rd, rs1| Instruction::Sha256Sig1 { rd, rs1 },
+        "sha256sum0" => |rd, rs1| Instruction::Sha256Sum0 { rd, rs1 },
+        "sha256sum1" => |rd, rs1| Instruction::Sha256Sum1 { rd, rs1 },
+        _ => return None,
+    };
+    Some(op)
+}
+
+type AesOp = fn(Reg, Reg, Reg, u32) -> Instruction;
+
+fn aes_op(mnemonic: &str) -> Option<AesOp> {
+    let op: AesOp = match mnemonic {
+        "aes32esi" => |rd, rs1, rs2, bs| Instruction::Aes32esi { rd, rs1, rs2, bs },
+        "aes32esmi" => |rd, rs1, rs2, bs| Instruction::Aes32esmi { rd, rs1, rs2, bs },
+        "aes32dsi" => |rd, rs1, rs2, bs| Instruction::Aes32dsi { rd, rs1, rs2, bs },
+        "aes32dsmi" => |rd, rs1, rs2, bs| Instruction::Aes32dsmi { rd, rs1, rs2, bs },
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn load_op(mnemonic: &str) -> Option<fn(Reg, Reg, i32) -> Instruction> {
+    let op: fn(Reg, Reg, i32) -> Instruction = match mnemonic {
+        "lb" => |rd, rs1, imm| Instruction::Lb { rd, rs1, imm },
+        "lh" => |rd, rs1, imm| Instruction::Lh { rd, rs1, imm },
+        "lw" => |rd, rs1, imm| Instruction::Lw { rd, rs1, imm },
+        "lbu" => |rd, rs1, imm| Instruction::Lbu { rd, rs1, imm },
+        "lhu" => |rd, rs1, imm| Instruction::Lhu { rd, rs1, imm },
+        "lwu" => |rd, rs1, imm| Instruction::Lwu { rd, rs1, imm },
+        "ld" => |rd, rs1, imm| Instruction::Ld { rd, rs1, imm },
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn store_op(mnemonic: &str) -> Option<fn(Reg, Reg, i32) -> Instruction> {
+    let op: fn(Reg, Reg, i32) -> Instruction = match mnemonic {
+        "sb" => |rs1, rs2, imm| Instruction::Sb { rs1, rs2, imm },
+        "sh" => |rs1, rs2, imm| Instruction::Sh { rs1, rs2, imm },
+        "sw" => |rs1, rs2, imm| Instruction::Sw { rs1, rs2, imm },
+        "sd" => |rs1, rs2, imm| Instruction::Sd { rs1, rs2, imm },
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn branch_op(mnemonic: &str) -> Option<fn(Reg, Reg, i32) -> Instruction> {
+    let op: fn(Reg, Reg, i32) -> Instruction = match mnemonic {
+        "beq" => |rs1, rs2, imm| Instruction::Beq { rs1, rs2, imm },
+        "bne" => |rs1, rs2, imm| Instruction::Bne { rs1, rs2, imm },
+        "blt" => |rs1, rs2, imm| Instruction::Blt { rs1, rs2, imm },
+        "bge" => |rs1, rs2, imm| Instruction::Bge { rs1, rs2, imm },
+        "bltu" => |rs1, rs2, imm| Instruction::Bltu { rs1, rs2, imm },
+        "bgeu" => |rs1, rs2, imm| Instruction::Bgeu { rs1, rs2, imm },
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn amo_op(mnemonic: &str) -> Option<fn(Reg, Reg, Reg) -> Instruction> {
+    let op: fn(Reg, Reg, Reg) -> Instruction = match mnemonic {
+        "sc.w" => |rd, rs1, rs2| Instruction::ScW { rd, rs1, rs2 },
+        "amoswap.w" => |rd, rs1, rs2| Instruction::AmoswapW { rd, rs1, rs2 },
+        "amoadd.w" => |rd, rs1, rs2| Instruction::AmoaddW { rd, rs1, rs2 },
+        "amoand.w" => |rd, rs1, rs2| Instruction::AmoandW { rd, rs1, rs2 },
+        "amoor.w" => |rd, rs1, rs2| Instruction::AmoorW { rd, rs1, rs2 },
+        "amoxor.w" => |rd, rs1, rs2| Instruction::AmoxorW { rd, rs1, rs2 },
+        "amomin.w" => |rd, rs1, rs2| Instruction::AmominW { rd, rs1, rs2 },
+        "amomax.w" => |rd, rs1, rs2| Instruction::AmomaxW { rd, rs1, rs2 },
+        "amominu.w" => |rd, rs1, rs2| Instruction::AmominuW { rd, rs1, rs2 },
+        "amomaxu.w" => |rd, rs1, rs2| Instruction::AmomaxuW { rd, rs1, rs2 },
+        "sc.d" => |rd, rs1, rs2| Instruction::ScD { rd, rs1, rs2 },
+        "amoswap.d" => |rd, rs1, rs2| Instruction::AmoswapD { rd, rs1, rs2 },
+        "amoadd.d" => |rd, rs1, rs2| Instruction::AmoaddD { rd, rs1, rs2 },
+        "amoand.d" => |rd, rs1, rs2| Instruction::AmoandD { rd, rs1, rs2 },
+        "amoor.d" => |rd, rs1, rs2| Instruction::AmoorD { rd, rs1, rs2 },
+        "amoxor.d" => |rd, rs1, rs2| Instruction::AmoxorD { rd, rs1, rs2 },
+        "amomin.d" => |rd, rs1, rs2| Instruction::AmominD { rd, rs1, rs2 },
+        "amomax.d" => |rd, rs1, rs2| Instruction::AmomaxD { rd, rs1, rs2 },
+        "amominu.d" => |rd, rs1, rs2| Instruction::AmominuD { rd, rs1, rs2 },
+        "amomaxu.d" => |rd, rs1, rs2| Instruction::AmomaxuD { rd, rs1, rs2 },
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn csr_op(mnemonic: &str) -> Option<fn(Reg, Reg, u16) -> Instruction> {
+    let op: fn(Reg, Reg, u16) -> Instruction = match mnemonic {
+        "csrrw" => |rd, rs1, csr| Instruction::Csrrw { rd, rs1, csr },
+        "csrrs" => |rd, rs1, csr| Instruction::Csrrs { rd, rs1, csr },
+        "csrrc" => |rd, rs1, csr| Instruction::Csrrc { rd, rs1, csr },
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn csr_imm_op(mnemonic: &str) -> Option<fn(Reg, u32, u16) -> Instruction> {
+    let op: fn(Reg, u32, u16) -> Instruction = match mnemonic {
+        "csrrwi" => |rd, uimm, csr| Instruction::Csrrwi { rd, uimm, csr },
+        "csrrsi" => |rd, uimm, csr| Instruction::Csrrsi { rd, uimm, csr },
+        "csrrci" => |rd, uimm, csr| Instruction::Csrrci { rd, uimm, csr },
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// CSR accessed by a floating-point CSR pseudo-instruction (`frrm`, `fsflags`, ...)
+fn float_csr(mnemonic: &str) -> u16 {
+    match &mnemonic[2..] {
+        "rm" => FRM,
+        "flags" => FFLAGS,
+        _ => FCSR,
+    }
+}
+
+/// Split a PC-relative offset into `auipc`/`lui` upper and sign-extended lower parts
+fn split_hi_lo(value: u32) -> (i32, i32) {
+    let hi = value.wrapping_add(0x800) & !0xfff;
+    (hi as i32, value.wrapping_sub(hi) as i32)
+}
+
+/// State for emitting a single statement during one assembler pass
+struct Pass<'a> {
+    symbols: &'a BTreeMap<String, u32>,
+    pc: u32,
+    final_pass: bool,
+}
+
+impl Pass<'_> {
+    /// Emit the bytes of a directive or (pseudo-)instruction
+    fn emit(&self, mnemonic: &str, ops: &[String]) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+
+        match mnemonic {
+            ".dword" | ".8byte" | ".quad" => {
+                for op in ops {
+                    let value = match parse_int(op) {
+                        Some(value) => value as u64,
+                        None => self.address(op)? as u64,
+                    };
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            },
+            ".word" | ".4byte" => {
+                for op in ops {
+                    bytes.extend_from_slice(&self.address(op)?.to_le_bytes());
+                }
+            },
+            ".half" | ".short" | ".2byte" => {
+                for op in ops {
+                    bytes.extend_from_slice(&(self.address(op)? as u16).to_le_bytes());
+                }
+            },
+            ".byte" => {
+                for op in ops {
+                    bytes.push(self.address(op)? as u8);
+                }
+            },
+            ".ascii" | ".asciz" | ".string" => {
+                for op in ops {
+                    bytes.extend(parse_string(op)?);
+                    if mnemonic != ".ascii" {
+                        bytes.push(0);
+                    }
+                }
+            },
+            ".align" | ".p2align" | ".balign" => {
+                self.arity(ops, 1)?;
+                let n = self.uimm(&ops[0])?;
+                let align = if mnemonic == ".balign" { n.max(1) } else { 1u32.checked_shl(n).ok_or("alignment too large")? };
+                let padding = self.pc.wrapping_neg() % align;
+                bytes.resize(padding as usize, 0);
+            },
+            ".space" | ".zero" | ".skip" => {
+                self.arity(ops, 1)?;
+                let size = self.constant(&ops[0])?;
+                let size = usize::try_from(size).map_err(|_| format!("negative size {}", size))?;
+                bytes.resize(size, 0);
+            },
+            ".globl" | ".global" | ".local" | ".text" | ".data" | ".rodata" | ".bss"
+            | ".section" | ".type" | ".size" | ".option" | ".file" | ".ident" => {},
+            _ if mnemonic.starts_with('.') => return Err(format!("unknown directive `{}`", mnemonic)),
+            _ => {
+                for instruction in self.instructions(mnemonic, ops)? {
+                    let word = instruction.encode().map_err(|e| e.to_string())?;
+                    bytes.extend_from_slice(&word.to_le_bytes());
+                }
+            },
+        }
+
+        Ok(bytes)
+    }
+
+    /// Expand a (pseudo-)instruction into the instructions it assembles to
+    fn instructions(&self, mnemonic: &str, ops: &[String]) -> Result<Vec<Instruction>, String> {
+        use RegisterIndex::{X0, X1};
+
+        // Ordering suffixes have no effect on a single hart
+        let mnemonic = mnemonic.trim_end_matches(".aqrl").trim_end_matches(".aq").trim_end_matches(".rl");
+
+        if let Some(op) = r_op(mnemonic) {
+            self.arity(ops, 3)?;
+            return Ok(vec![op(self.reg(&ops[0])?, self.reg(&ops[1])?, self.reg(&ops[2])?)]);
+        }
+        if let Some(op) = i_op(mnemonic) {
+            self.arity(ops, 3)?;
+            return Ok(vec![op(self.reg(&ops[0])?, self.reg(&ops[1])?, self.imm(&ops[2])?)]);
+        }
+        if let Some(op) = shift_op(mnemonic) {
+            self.arity(ops, 3)?;
+            return Ok(vec![op(self.reg(&ops[0])?, self.reg(&ops[1])?, self.uimm(&ops[2])?)]);
+        }
+        if let Some(op) = unary_op(mnemonic) {
+            self.arity(ops, 2)?;
+            return Ok(vec![op(self.reg(&ops[0])?, self.reg(&ops[1])?)]);
+        }
+        if let Some(op) = aes_op(mnemonic) {
+            self.arity(ops, 4)?;
+            let bs = self.uimm(&ops[3])?;
+            return Ok(vec![op(self.reg(&ops[0])?, self.reg(&ops[1])?, self.reg(&ops[2])?, bs)]);
+        }
+        if let Some(op) = load_op(mnemonic) {
+            self.arity(ops, 2)?;
+            let (imm, base) = self.mem(&ops[1])?;
+            return Ok(vec![op(self.reg(&ops[0])?, base, imm)]);
+        }
+        if let Some(op) = store_op(mnemonic) {
+            self.arity(ops, 2)?;
+            let (imm, base) = self.mem(&ops[1])?;
+            return Ok(vec![op(base, self.reg(&ops[0])?, imm)]);
+        }
+        if let Some(op) = branch_op(mnemonic) {
+            self.arity(ops, 3)?;
+            return Ok(vec![op(self.reg(&ops[0])?, self.reg(&ops[1])?, self.offset(&ops[2])?)]);
+        }
+        if let Some(op) = amo_op(mnemonic) {
+            self.arity(ops, 3)?;
+            return Ok(vec![op(self.reg(&ops[0])?, self.mem_reg(&ops[2])?, self.reg(&ops[1])?)]);
+        }
+        if let Some(op) = csr_op(mnemonic) {
+            self.arity(ops, 3)?;
+            return Ok(vec![op(self.reg(&ops[0])?, self.reg(&ops[2])?, self.csr(&ops[1])?)]);
+        }
+        if let Some(op) = csr_imm_op(mnemonic) {
+            self.arity(ops, 3)?;
+            return Ok(vec![op(self.reg(&ops[0])?, self.uimm(&ops[2])?, self.csr(&ops[1])?)]);
+        }
+        if let Some(instruction) = self.float_instruction(mnemonic, ops)? {
+            return Ok(vec![instruction]);
+        }
+
+        let instructions = match mnemonic {
+            "lr.w" => {
+                self.arity(ops, 2)?;
+                vec![Instruction::LrW { rd: self.reg(&ops[0])?, rs1: self.mem_reg(&ops[1])? }]
+            },
+            "lr.d" => {
+                self.arity(ops, 2)?;
+                vec![Instruction::LrD { rd: self.reg(&ops[0])?, rs1: self.mem_reg(&ops[1])? }]
+            },
+            "jal" => match ops.len() {
+                1 => vec![Instruction::Jal { rd: X1, imm: self.offset(&ops[0])? }],
+                _ => {
+                    self.arity(ops, 2)?;
+                    vec![Instruction::Jal { rd: self.reg(&ops[0])?, imm: self.offset(&ops[1])? }]
+                },
+            },
+            "jalr" => match ops.len() {
+                1 => vec![Instruction::Jalr { rd: X1, rs1: self.reg(&ops[0])?, imm: 0 }],
+                2 => {
+                    let (imm, rs1) = self.mem(&ops[1])?;
+                    vec![Instruction::Jalr { rd: self.reg(&ops[0])?, rs1, imm }]
+                },
+                _ => {
+                    self.arity(ops, 3)?;
+                    let imm = self.imm(&ops[2])?;
+                    vec![Instruction::Jalr { rd: self.reg(&ops[0])?, rs1: self.reg(&ops[1])?, imm }]
+                },
+            },
+            "lui" | "auipc" => {
+                self.arity(ops, 2)?;
+                let rd = self.reg(&ops[0])?;
+                let imm = self.constant(&ops[1])?;
+                if !(-0x80000..=0xfffff).contains(&imm) {
+                    return Err(format!("upper immediate {} out of range", imm));
+                }
+                let imm = (imm << 12) as i32;
+                if mnemonic == "lui" {
+                    vec![Instruction::Lui { rd, imm }]
+                } else {
+                    vec![Instruction::Auipc { rd, imm }]
+                }
+            },
+            "fence" => match ops.len() {
+                0 => vec![Instruction::Fence { fm: 0, pred: 0xf, succ: 0xf }],
+                _ => {
+                    self.arity(ops, 2)?;
+                    vec![Instruction::Fence { fm: 0, pred: fence_set(&ops[0])?, succ: fence_set(&ops[1])? }]
+                },
+            },
+            "fence.tso" => vec![Instruction::Fence { fm: 8, pred: 0x3, succ: 0x3 }],
+            "fence.i" => vec![Instruction::FenceI],
+            "ecall" => vec![Instruction::Ecall],
+            "ebreak" => vec![Instruction::Ebreak],
+            "mret" => vec![Instruction::Mret],
+
+            // Pseudo-instructions
+            "nop" => vec![Instruction::Addi { rd: X0, rs1: X0, imm: 0 }],
+            "li" => {
+                self.arity(ops, 2)?;
+                let rd = self.reg(&ops[0])?;
+                let value = self.constant(&ops[1])?;
+                if !(-(1i64 << 31)..(1i64 << 32)).contains(&value) {
+                    return Err(format!("immediate {} does not fit in 32 bits", value));
+                }
+                let value = value as u32;
+                if (-2048..2048).contains(&(value as i32)) {
+                    vec![Instruction::Addi { rd, rs1: X0, imm: value as i32 }]
+                } else if (0x7fff_f800..=0x7fff_ffff).contains(&value) {
+                    // Rounding the upper part would carry into bit 31, which `lui` sign-extends
+                    // on RV64; flip the low bits of 0x80000000 instead so the result is the same
+                    // on either XLEN
+                    vec![
+                        Instruction::Lui { rd, imm: 0x8000_0000u32 as i32 },
+                        Instruction::Xori { rd, rs1: rd, imm: (value ^ 0x8000_0000) as i32 },
+                    ]
+                } else {
+                    let (hi, lo) = split_hi_lo(value);
+                    let mut expansion = vec![Instruction::Lui { rd, imm: hi }];
+                    if lo != 0 {
+                        expansion.push(Instruction::Addi { rd, rs1: rd, imm: lo });
+                    }
+                    expansion
+                }
+            },
+            "la" => {
+                self.arity(ops, 2)?;
+                let rd = self.reg(&ops[0])?;
+                let (hi, lo) = split_hi_lo(self.address(&ops[1])?.wrapping_sub(self.pc));
+                vec![Instruction::Auipc { rd, imm: hi }, Instruction::Addi { rd, rs1: rd, imm: lo }]
+            },
+            "call" | "tail" => {
+                self.arity(ops, 1)?;
+                let (rd, scratch) = if mnemonic == "call" { (X1, X1) } else { (X0, RegisterIndex::X6) };
+                let (hi, lo) = split_hi_lo(self.address(&ops[0])?.wrapping_sub(self.pc));
+                vec![Instruction::Auipc { rd: scratch, imm: hi }, Instruction::Jalr { rd, rs1: scratch, imm: lo }]
+            },
+            "ret" => vec![Instruction::Jalr { rd: X0, rs1: X1, imm: 0 }],
+            "j" => {
+                self.arity(ops, 1)?;
+                vec![Instruction::Jal { rd: X0, imm: self.offset(&ops[0])? }]
+            },
+            "jr" => {
+                self.arity(ops, 1)?;
+                vec![Instruction::Jalr { rd: X0, rs1: self.reg(&ops[0])?, imm: 0 }]
+            },
+            "mv" | "not" | "neg" | "negw" | "sext.w" | "seqz" | "snez" => {
+                self.arity(ops, 2)?;
+                let (rd, rs) = (self.reg(&ops[0])?, self.reg(&ops[1])?);
+                vec![match mnemonic {
+                    "mv" => Instruction::Addi { rd, rs1: rs, imm: 0 },
+                    "not" => Instruction::Xori { rd, rs1: rs, imm: -1 },
+                    "neg" => Instruction::Sub { rd, rs1: X0, rs2: rs },
+                    "negw" => Instruction::Subw { rd, rs1: X0, rs2: rs },
+                    "sext.w" => Instruction::Addiw { rd, rs1: rs, imm: 0 },
+                    "seqz" => Instruction::Sltiu { rd, rs1: rs, imm: 1 },
+                    _ => Instruction::Sltu { rd, rs1: X0, rs2: rs },
+                }]
+            },
+            "beqz" | "bnez" | "bltz" | "bgez" | "blez" | "bgtz" => {
+                self.arity(ops, 2)?;
+                let (rs, imm) = (self.reg(&ops[0])?, self.offset(&ops[1])?);
+                vec![match mnemonic {
+                    "beqz" => Instruction::Beq { rs1: rs, rs2: X0, imm },
+                    "bnez" => Instruction::Bne { rs1: rs, rs2: X0, imm },
+                    "bltz" => Instruction::Blt { rs1: rs, rs2: X0, imm },
+                    "bgez" => Instruction::Bge { rs1: rs, rs2: X0, imm },
+                    "blez" => Instruction::Bge { rs1: X0, rs2: rs, imm },
+                    _ => Instruction::Blt { rs1: X0, rs2: rs, imm },
+                }]
+            },
+            "bgt" | "ble" | "bgtu" | "bleu" => {
+                // Same as the reversed comparison with swapped operands
+                self.arity(ops, 3)?;
+                let (rs1, rs2, imm) = (self.reg(&ops[1])?, self.reg(&ops[0])?, self.offset(&ops[2])?);
+                vec![match mnemonic {
+                    "bgt" => Instruction::Blt { rs1, rs2, imm },
+                    "ble" => Instruction::Bge { rs1, rs2, imm },
+                    "bgtu" => Instruction::Bltu { rs1, rs2, imm },
+                    _ => Instruction::Bgeu { rs1, rs2, imm },
+                }]
+            },
+            "csrr" => {
+                self.arity(ops, 2)?;
+                vec![Instruction::Csrrs { rd: self.reg(&ops[0])?, rs1: X0, csr: self.csr(&ops[1])? }]
+            },
+            "csrw" | "csrs" | "csrc" => {
+                self.arity(ops, 2)?;
+                let (csr, rs1) = (self.csr(&ops[0])?, self.reg(&ops[1])?);
+                vec![match mnemonic {
+                    "csrw" => Instruction::Csrrw { rd: X0, rs1, csr },
+                    "csrs" => Instruction::Csrrs { rd: X0, rs1, csr },
+                    _ => Instruction::Csrrc { rd: X0, rs1, csr },
+                }]
+            },
+            "frcsr" | "frrm" | "frflags" => {
+                self.arity(ops, 1)?;
+                let csr = float_csr(mnemonic);
+                vec![Instruction::Csrrs { rd: self.reg(&ops[0])?, rs1: X0, csr }]
+            },
+            "fscsr" | "fsrm" | "fsflags" => {
+                // Optionally returns the old value: `fsrm rs` or `fsrm rd, rs`
+                let csr = float_csr(mnemonic);
+                match ops.len() {
+                    1 => vec![Instruction::Csrrw { rd: X0, rs1: self.reg(&ops[0])?, csr }],
+                    _ => {
+                        self.arity(ops, 2)?;
+                        vec![Instruction::Csrrw { rd: self.reg(&ops[0])?, rs1: self.reg(&ops[1])?, csr }]
+                    },
+                }
+            },
+            "rdcycle" | "rdtime" | "rdinstret" => {
+                self.arity(ops, 1)?;
+                let csr = csr_address(&mnemonic[2..]).ok_or("unknown counter")?;
+                vec![Instruction::Csrrs { rd: self.reg(&ops[0])?, rs1: X0, csr }]
+            },
+            _ => return Err(format!("unknown instruction `{}`", mnemonic)),
+        };
+
+        Ok(instructions)
+    }
+
+    /// Assemble an F or D instruction, or return `None` if `mnemonic` is not one
+    fn float_instruction(&self, mnemonic: &str, ops: &[String]) -> Result<Option<Instruction>, String> {
+        let format = |suffix: &str| match suffix {
+            "s" => Some(FloatFormat::Single),
+            "d" => Some(FloatFormat::Double),
+            _ => None,
+        };
+
+        let instruction = match mnemonic {
+            "flw" | "fld" | "fsw" | "fsd" => {
+                self.arity(ops, 2)?;
+                let (reg, (imm, rs1)) = (self.freg(&ops[0])?, self.mem(&ops[1])?);
+                match mnemonic {
+                    "flw" => Instruction::Flw { rd: reg, rs1, imm },
+                    "fld" => Instruction::Fld { rd: reg, rs1, imm },
+                    "fsw" => Instruction::Fsw { rs1, rs2: reg, imm },
+                    _ => Instruction::Fsd { rs1, rs2: reg, imm },
+                }
+            },
+            "fmv.x.w" => {
+                self.arity(ops, 2)?;
+                Instruction::FmvXW { rd: self.reg(&ops[0])?, rs1: self.freg(&ops[1])? }
+            },
+            "fmv.w.x" => {
+                self.arity(ops, 2)?;
+                Instruction::FmvWX { rd: self.freg(&ops[0])?, rs1: self.reg(&ops[1])? }
+            },
+            "fmv.x.d" => {
+                self.arity(ops, 2)?;
+                Instruction::FmvXD { rd: self.reg(&ops[0])?, rs1: self.freg(&ops[1])? }
+            },
+            "fmv.d.x" => {
+                self.arity(ops, 2)?;
+                Instruction::FmvDX { rd: self.freg(&ops[0])?, rs1: self.reg(&ops[1])? }
+            },
+            _ => {
+                let Some((name, suffix)) = mnemonic.rsplit_once('.') else {
+                    return Ok(None);
+                };
+
+                // Conversions name the destination then the source type: `fcvt.w.s`, `fcvt.d.wu`
+                if let Some(dest) = name.strip_prefix("fcvt.") {
+                    let rm = self.rounding(ops, 2)?;
+                    let instruction = match (dest, suffix) {
+                        ("s", "d") => Instruction::FcvtSD { rd: self.freg(&ops[0])?, rs1: self.freg(&ops[1])?, rm },
+                        ("d", "s") => Instruction::FcvtDS { rd: self.freg(&ops[0])?, rs1: self.freg(&ops[1])?, rm },
+                        ("w" | "wu" | "l" | "lu", _) => {
+                            let fmt = format(suffix).ok_or_else(|| format!("unknown instruction `{}`", mnemonic))?;
+                            let (rd, rs1) = (self.reg(&ops[0])?, self.freg(&ops[1])?);
+                            match dest {
+                                "w" => Instruction::FcvtW { fmt, rd, rs1, rm },
+                                "wu" => Instruction::FcvtWu { fmt, rd, rs1, rm },
+                                "l" => Instruction::FcvtL { fmt, rd, rs1, rm },
+                                _ => Instruction::FcvtLu { fmt, rd, rs1, rm },
+                            }
+                        },
+                        (_, "w" | "wu" | "l" | "lu") => {
+                            let fmt = format(dest).ok_or_else(|| format!("unknown instruction `{}`", mnemonic))?;
+                            let (rd, rs1) = (self.freg(&ops[0])?, self.reg(&ops[1])?);
+                            match suffix {
+                                "w" => Instruction::FcvtFW { fmt, rd, rs1, rm },
+                                "wu" => Instruction::FcvtFWu { fmt, rd, rs1, rm },
+                                "l" => Instruction::FcvtFL { fmt, rd, rs1, rm },
+                                _ => Instruction::FcvtFLu { fmt, rd, rs1, rm },
+                            }
+                        },
+                        _ => return Err(format!("unknown instruction `{}`", mnemonic)),
+                    };
+                    return Ok(Some(instruction));
+                }
+
+                let Some(fmt) = format(suffix) else {
+                    return Ok(None);
+                };
+                match name {
+                    "fadd" | "fsub" | "fmul" | "fdiv" => {
+                        let rm = self.rounding(ops, 3)?;
+                        let (rd, rs1, rs2) = (self.freg(&ops[0])?, self.freg(&ops[1])?, self.freg(&ops[2])?);
+                        match name {
+                            "fadd" => Instruction::Fadd { fmt, rd, rs1, rs2, rm },
+                            "fsub" => Instruction::Fsub { fmt, rd, rs1, rs2, rm },
+                            "fmul" => Instruction::Fmul { fmt, rd, rs1, rs2, rm },
+                            _ => Instruction::Fdiv { fmt, rd, rs1, rs2, rm },
+                        }
+                    },
+                    "fmadd" | "fmsub" | "fnmsub" | "fnmadd" => {
+                        let rm = self.rounding(ops, 4)?;
+                        let (rd, rs1) = (self.freg(&ops[0])?, self.freg(&ops[1])?);
+                        let (rs2, rs3) = (self.freg(&ops[2])?, self.freg(&ops[3])?);
+                        match name {
+                            "fmadd" => Instruction::Fmadd { fmt, rd, rs1, rs2, rs3, rm },
+                            "fmsub" => Instruction::Fmsub { fmt, rd, rs1, rs2, rs3, rm },
+                            "fnmsub" => Instruction::Fnmsub { fmt, rd, rs1, rs2, rs3, rm },
+                            _ => Instruction::Fnmadd { fmt, rd, rs1, rs2, rs3, rm },
+                        }
+                    },
+                    "fsqrt" => {
+                        let rm = self.rounding(ops, 2)?;
+                        Instruction::Fsqrt { fmt, rd: self.freg(&ops[0])?, rs1: self.freg(&ops[1])?, rm }
+                    },
+                    "fsgnj" | "fsgnjn" | "fsgnjx" | "fmin" | "fmax" => {
+                        self.arity(ops, 3)?;
+                        let (rd, rs1, rs2) = (self.freg(&ops[0])?, self.freg(&ops[1])?, self.freg(&ops[2])?);
+                        match name {
+                            "fsgnj" => Instruction::Fsgnj { fmt, rd, rs1, rs2 },
+                            "fsgnjn" => Instruction::Fsgnjn { fmt, rd, rs1, rs2 },
+                            "fsgnjx" => Instruction::Fsgnjx { fmt, rd, rs1, rs2 },
+                            "fmin" => Instruction::Fmin { fmt, rd, rs1, rs2 },
+                            _ => Instruction::Fmax { fmt, rd, rs1, rs2 },
+                        }
+                    },
+                    // Sign-injection pseudo-instructions
+                    "fmv" | "fneg" | "fabs" => {
+                        self.arity(ops, 2)?;
+                        let (rd, rs) = (self.freg(&ops[0])?, self.freg(&ops[1])?);
+                        match name {
+                            "fmv" => Instruction::Fsgnj { fmt, rd, rs1: rs, rs2: rs },
+                            "fneg" => Instruction::Fsgnjn { fmt, rd, rs1: rs, rs2: rs },
+                            _ => Instruction::Fsgnjx { fmt, rd, rs1: rs, rs2: rs },
+                        }
+                    },
+                    "feq" | "flt" | "fle" => {
+                        self.arity(ops, 3)?;
+                        let (rd, rs1, rs2) = (self.reg(&ops[0])?, self.freg(&ops[1])?, self.freg(&ops[2])?);
+                        match name {
+                            "feq" => Instruction::Feq { fmt, rd, rs1, rs2 },
+                            "flt" => Instruction::Flt { fmt, rd, rs1, rs2 },
+                            _ => Instruction::Fle { fmt, rd, rs1, rs2 },
+                        }
+                    },
+                    "fclass" => {
+                        self.arity(ops, 2)?;
+                        Instruction::Fclass { fmt, rd: self.reg(&ops[0])?, rs1: self.freg(&ops[1])? }
+                    },
+                    _ => return Ok(None),
+                }
+            },
+        };
+
+        Ok(Some(instruction))
+    }
+
+    /// Check the arity of an instruction that takes an optional trailing
+    /// rounding mode, returning the mode (`dyn` if omitted)
+    fn rounding(&self, ops: &[String], expected: usize) -> Result<RoundingMode, String> {
+        if ops.len() != expected + 1 {
+            self.arity(ops, expected)?;
+            return Ok(RoundingMode::Dyn);
+        }
+        RoundingMode::ALL
+            .into_iter()
+            .find(|rm| rm.name() == ops[expected])
+            .ok_or_else(|| format!("invalid rounding mode `{}`", ops[expected]))
+    }
+
+    fn arity(&self, ops: &[String], expected: usize) -> Result<(), String> {
+        if ops.len() != expected {
+            return Err(format!("expected {} operands, found {}", expected, ops.len()));
+        }
+        Ok(())
+    }
+
+    fn reg(&self, text: &str) -> Result<Reg, String> {
+        text.trim().parse().map_err(|_| format!("invalid register `{}`", text))
+    }
+
+    fn freg(&self, text: &str) -> Result<Reg, String> {
+        RegisterIndex::parse_fp(text.trim()).map_err(|_| format!("invalid register `{}`", text))
+    }
+
+    fn constant(&self, text: &str) -> Result<i64, String> {
+        parse_int(text).ok_or_else(|| format!("invalid constant `{}`", text))
+    }
+
+    /// A constant that must fit in an `i32`; the encoder checks the field width
+    fn imm(&self, text: &str) -> Result<i32, String> {
+        let value = self.constant(text)?;
+        i32::try_from(value).map_err(|_| format!("immediate {} out of range", value))
+    }
+
+    /// A constant that must fit in a `u32`; the encoder checks the field width
+    fn uimm(&self, text: &str) -> Result<u32, String> {
+        let value = self.constant(text)?;
+        u32::try_from(value).map_err(|_| format!("immediate {} out of range", value))
+    }
+
+    fn csr(&self, text: &str) -> Result<u16, String> {
+        if let Some(csr) = csr_address(text) {
+            return Ok(csr);
+        }
+        match parse_int(text) {
+            Some(csr) if (0..4096).contains(&csr) => Ok(csr as u16),
+            _ => Err(format!("invalid CSR `{}`", text)),
+        }
+    }
+
+    /// Evaluate a constant or `label[+/-offset]` expression
+    fn address(&self, text: &str) -> Result<u32, String> {
+        if let Some(value) = parse_int(text) {
+            return Ok(value as u32);
+        }
+
+        let (name, offset) = match text.rfind(['+', '-']).filter(|&i| i > 0) {
+            Some(i) => (text[..i].trim(), self.constant(&text[i..])?),
+            None => (text.trim(), 0),
+        };
+
+        let base = match self.symbols.get(name) {
+            Some(&addr) => addr,
+            None if !self.final_pass => self.pc,
+            None => return Err(format!("undefined symbol `{}`", name)),
+        };
+        Ok(base.wrapping_add(offset as u32))
+    }
+
+    /// PC-relative offset of a branch or jump target
+    fn offset(&self, text: &str) -> Result<i32, String> {
+        Ok(self.address(text)?.wrapping_sub(self.pc) as i32)
+    }
+
+    /// Parse a memory operand of the form `imm(reg)` or `(reg)`
+    fn mem(&self, text: &str) -> Result<(i32, Reg), String> {
+        let (imm, rest) = text
+            .split_once('(')
+            .ok_or_else(|| format!("expected memory operand, found `{}`", text))?;
+        let reg = rest
+            .strip_suffix(')')
+            .ok_or_else(|| format!("expected memory operand, found `{}`", text))?;
+        let imm = if imm.trim().is_empty() { 0 } else { self.imm(imm)? };
+        Ok((imm, self.reg(reg)?))
+    }
+
+    /// Parse an AMO address operand `(reg)`, which must have no offset
+    fn mem_reg(&self, text: &str) -> Result<Reg, String> {
+        match self.mem(text)? {
+            (0, reg) => Ok(reg),
+            _ => Err(format!("atomic address `{}` must not have an offset", text)),
+        }
+    }
+}
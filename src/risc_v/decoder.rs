@@ -1,5 +1,7 @@
 //! RISC-V instruction decoder
 
+use crate::risc_v::isa::{Extension, IsaConfig, Xlen};
+use crate::risc_v::instructions::{FloatFormat, RoundingMode};
 use crate::risc_v::{Instruction, RegisterIndex};
 use crate::utils::VmError;
 
@@ -32,27 +34,163 @@ impl Decoder {
                     (0x5, 0x20) => Ok(Instruction::Sra { rd, rs1, rs2 }),
                     (0x6, 0x00) => Ok(Instruction::Or { rd, rs1, rs2 }),
                     (0x7, 0x00) => Ok(Instruction::And { rd, rs1, rs2 }),
+
+                    // M extension
+                    (0x0, 0x01) => Ok(Instruction::Mul { rd, rs1, rs2 }),
+                    (0x1, 0x01) => Ok(Instruction::Mulh { rd, rs1, rs2 }),
+                    (0x2, 0x01) => Ok(Instruction::Mulhsu { rd, rs1, rs2 }),
+                    (0x3, 0x01) => Ok(Instruction::Mulhu { rd, rs1, rs2 }),
+                    (0x4, 0x01) => Ok(Instruction::Div { rd, rs1, rs2 }),
+                    (0x5, 0x01) => Ok(Instruction::Divu { rd, rs1, rs2 }),
+                    (0x6, 0x01) => Ok(Instruction::Rem { rd, rs1, rs2 }),
+                    (0x7, 0x01) => Ok(Instruction::Remu { rd, rs1, rs2 }),
+
+                    // Zba extension
+                    (0x2, 0x10) => Ok(Instruction::Sh1add { rd, rs1, rs2 }),
+                    (0x4, 0x10) => Ok(Instruction::Sh2add { rd, rs1, rs2 }),
+                    (0x6, 0x10) => Ok(Instruction::Sh3add { rd, rs1, rs2 }),
+
+                    // Zbb extension
+                    (0x7, 0x20) => Ok(Instruction::Andn { rd, rs1, rs2 }),
+                    (0x6, 0x20) => Ok(Instruction::Orn { rd, rs1, rs2 }),
+                    (0x4, 0x20) => Ok(Instruction::Xnor { rd, rs1, rs2 }),
+                    (0x4, 0x05) => Ok(Instruction::Min { rd, rs1, rs2 }),
+                    (0x5, 0x05) => Ok(Instruction::Minu { rd, rs1, rs2 }),
+                    (0x6, 0x05) => Ok(Instruction::Max { rd, rs1, rs2 }),
+                    (0x7, 0x05) => Ok(Instruction::Maxu { rd, rs1, rs2 }),
+                    (0x1, 0x30) => Ok(Instruction::Rol { rd, rs1, rs2 }),
+                    (0x5, 0x30) => Ok(Instruction::Ror { rd, rs1, rs2 }),
+                    (0x4, 0x04) if rs2 == RegisterIndex::X0 => Ok(Instruction::ZextH { rd, rs1 }),
+
+                    // Zbs extension
+                    (0x1, 0x24) => Ok(Instruction::Bclr { rd, rs1, rs2 }),
+                    (0x5, 0x24) => Ok(Instruction::Bext { rd, rs1, rs2 }),
+                    (0x1, 0x34) => Ok(Instruction::Binv { rd, rs1, rs2 }),
+                    (0x1, 0x14) => Ok(Instruction::Bset { rd, rs1, rs2 }),
+
+                    // Zknh extension
+                    (0x0, 0x28) => Ok(Instruction::Sha512Sum0r { rd, rs1, rs2 }),
+                    (0x0, 0x29) => Ok(Instruction::Sha512Sum1r { rd, rs1, rs2 }),
+                    (0x0, 0x2a) => Ok(Instruction::Sha512Sig0l { rd, rs1, rs2 }),
+                    (0x0, 0x2b) => Ok(Instruction::Sha512Sig1l { rd, rs1, rs2 }),
+                    (0x0, 0x2e) => Ok(Instruction::Sha512Sig0h { rd, rs1, rs2 }),
+                    (0x0, 0x2f) => Ok(Instruction::Sha512Sig1h { rd, rs1, rs2 }),
+
+                    // Zkne/Zknd extensions (funct7 holds the byte select in its top two bits)
+                    (0x0, f) if f & 0x1f == 0x11 => Ok(Instruction::Aes32esi { rd, rs1, rs2, bs: f >> 5 }),
+                    (0x0, f) if f & 0x1f == 0x13 => Ok(Instruction::Aes32esmi { rd, rs1, rs2, bs: f >> 5 }),
+                    (0x0, f) if f & 0x1f == 0x15 => Ok(Instruction::Aes32dsi { rd, rs1, rs2, bs: f >> 5 }),
+                    (0x0, f) if f & 0x1f == 0x17 => Ok(Instruction::Aes32dsmi { rd, rs1, rs2, bs: f >> 5 }),
+                    _ => Err(VmError::InvalidInstruction(instruction)),
+                }
+            },
+
+            // Atomic instructions (AMO)
+            0x2f => {
+                // The aq/rl ordering bits have no effect on a single hart
+                let funct5 = instruction >> 27;
+
+                match (funct3, funct5) {
+                    (0x2, 0x02) if rs2 == RegisterIndex::X0 => Ok(Instruction::LrW { rd, rs1 }),
+                    (0x2, 0x03) => Ok(Instruction::ScW { rd, rs1, rs2 }),
+                    (0x2, 0x01) => Ok(Instruction::AmoswapW { rd, rs1, rs2 }),
+                    (0x2, 0x00) => Ok(Instruction::AmoaddW { rd, rs1, rs2 }),
+                    (0x2, 0x04) => Ok(Instruction::AmoxorW { rd, rs1, rs2 }),
+                    (0x2, 0x0c) => Ok(Instruction::AmoandW { rd, rs1, rs2 }),
+                    (0x2, 0x08) => Ok(Instruction::AmoorW { rd, rs1, rs2 }),
+                    (0x2, 0x10) => Ok(Instruction::AmominW { rd, rs1, rs2 }),
+                    (0x2, 0x14) => Ok(Instruction::AmomaxW { rd, rs1, rs2 }),
+                    (0x2, 0x18) => Ok(Instruction::AmominuW { rd, rs1, rs2 }),
+                    (0x2, 0x1c) => Ok(Instruction::AmomaxuW { rd, rs1, rs2 }),
+                    (0x3, 0x02) if rs2 == RegisterIndex::X0 => Ok(Instruction::LrD { rd, rs1 }),
+                    (0x3, 0x03) => Ok(Instruction::ScD { rd, rs1, rs2 }),
+                    (0x3, 0x01) => Ok(Instruction::AmoswapD { rd, rs1, rs2 }),
+                    (0x3, 0x00) => Ok(Instruction::AmoaddD { rd, rs1, rs2 }),
+                    (0x3, 0x04) => Ok(Instruction::AmoxorD { rd, rs1, rs2 }),
+                    (0x3, 0x0c) => Ok(Instruction::AmoandD { rd, rs1, rs2 }),
+                    (0x3, 0x08) => Ok(Instruction::AmoorD { rd, rs1, rs2 }),
+                    (0x3, 0x10) => Ok(Instruction::AmominD { rd, rs1, rs2 }),
+                    (0x3, 0x14) => Ok(Instruction::AmomaxD { rd, rs1, rs2 }),
+                    (0x3, 0x18) => Ok(Instruction::AmominuD { rd, rs1, rs2 }),
+                    (0x3, 0x1c) => Ok(Instruction::AmomaxuD { rd, rs1, rs2 }),
+                    _ => Err(VmError::InvalidInstruction(instruction)),
+                }
+            },
+
+            // Word arithmetic instructions (OP-32, RV64 only)
+            0x3b => {
+                match (funct3, funct7) {
+                    (0x0, 0x00) => Ok(Instruction::Addw { rd, rs1, rs2 }),
+                    (0x0, 0x20) => Ok(Instruction::Subw { rd, rs1, rs2 }),
+                    (0x1, 0x00) => Ok(Instruction::Sllw { rd, rs1, rs2 }),
+                    (0x5, 0x00) => Ok(Instruction::Srlw { rd, rs1, rs2 }),
+                    (0x5, 0x20) => Ok(Instruction::Sraw { rd, rs1, rs2 }),
+                    (0x0, 0x01) => Ok(Instruction::Mulw { rd, rs1, rs2 }),
+                    (0x4, 0x01) => Ok(Instruction::Divw { rd, rs1, rs2 }),
+                    (0x5, 0x01) => Ok(Instruction::Divuw { rd, rs1, rs2 }),
+                    (0x6, 0x01) => Ok(Instruction::Remw { rd, rs1, rs2 }),
+                    (0x7, 0x01) => Ok(Instruction::Remuw { rd, rs1, rs2 }),
+                    _ => Err(VmError::InvalidInstruction(instruction)),
+                }
+            },
+
+            // Word immediate instructions (OP-IMM-32, RV64 only)
+            0x1b => {
+                let imm = (instruction as i32) >> 20;
+                let shamt = (instruction >> 20) & 0x1f;
+
+                match (funct3, funct7) {
+                    (0x0, _) => Ok(Instruction::Addiw { rd, rs1, imm }),
+                    (0x1, 0x00) => Ok(Instruction::Slliw { rd, rs1, shamt }),
+                    (0x5, 0x00) => Ok(Instruction::Srliw { rd, rs1, shamt }),
+                    (0x5, 0x20) => Ok(Instruction::Sraiw { rd, rs1, shamt }),
                     _ => Err(VmError::InvalidInstruction(instruction)),
                 }
             },
 
             // I-type instructions (OP-IMM)
             0x13 => {
-                let imm = ((instruction as i32) >> 20) as i32;
+                let imm = (instruction as i32) >> 20;
                 let shamt = (instruction >> 20) & 0x1f;
+                // RV64 base shifts take a 6-bit shift amount, leaving a 6-bit funct6
+                let (funct6, shamt6) = (instruction >> 26, (instruction >> 20) & 0x3f);
                 
                 match funct3 {
                     0x0 => Ok(Instruction::Addi { rd, rs1, imm }),
-                    0x1 => Ok(Instruction::Slli { rd, rs1, shamt }),
+                    0x1 if funct6 == 0x00 => Ok(Instruction::Slli { rd, rs1, shamt: shamt6 }),
+                    0x5 if funct6 == 0x00 => Ok(Instruction::Srli { rd, rs1, shamt: shamt6 }),
+                    0x5 if funct6 == 0x10 => Ok(Instruction::Srai { rd, rs1, shamt: shamt6 }),
+                    0x1 => match funct7 {
+                        0x14 => Ok(Instruction::Bseti { rd, rs1, shamt }),
+                        0x24 => Ok(Instruction::Bclri { rd, rs1, shamt }),
+                        0x34 => Ok(Instruction::Binvi { rd, rs1, shamt }),
+                        0x08 => match shamt {
+                            0x0 => Ok(Instruction::Sha256Sum0 { rd, rs1 }),
+                            0x1 => Ok(Instruction::Sha256Sum1 { rd, rs1 }),
+                            0x2 => Ok(Instruction::Sha256Sig0 { rd, rs1 }),
+                            0x3 => Ok(Instruction::Sha256Sig1 { rd, rs1 }),
+                            _ => Err(VmError::InvalidInstruction(instruction)),
+                        },
+                        // Zbb unary operations select the operation with the rs2 field
+                        0x30 => match shamt {
+                            0x0 => Ok(Instruction::Clz { rd, rs1 }),
+                            0x1 => Ok(Instruction::Ctz { rd, rs1 }),
+                            0x2 => Ok(Instruction::Cpop { rd, rs1 }),
+                            0x4 => Ok(Instruction::SextB { rd, rs1 }),
+                            0x5 => Ok(Instruction::SextH { rd, rs1 }),
+                            _ => Err(VmError::InvalidInstruction(instruction)),
+                        },
+                        _ => Err(VmError::InvalidInstruction(instruction)),
+                    },
                     0x2 => Ok(Instruction::Slti { rd, rs1, imm }),
                     0x3 => Ok(Instruction::Sltiu { rd, rs1, imm }),
                     0x4 => Ok(Instruction::Xori { rd, rs1, imm }),
-                    0x5 => {
-                        if (instruction >> 30) & 1 == 0 {
-                            Ok(Instruction::Srli { rd, rs1, shamt })
-                        } else {
-                            Ok(Instruction::Srai { rd, rs1, shamt })
-                        }
+                    0x5 => match (funct7, shamt) {
+                        (0x30, _) => Ok(Instruction::Rori { rd, rs1, shamt }),
+                        (0x24, _) => Ok(Instruction::Bexti { rd, rs1, shamt }),
+                        (0x14, 0x07) => Ok(Instruction::OrcB { rd, rs1 }),
+                        (0x34, 0x18) => Ok(Instruction::Rev8 { rd, rs1 }),
+                        _ => Err(VmError::InvalidInstruction(instruction)),
                     },
                     0x6 => Ok(Instruction::Ori { rd, rs1, imm }),
                     0x7 => Ok(Instruction::Andi { rd, rs1, imm }),
@@ -62,7 +200,7 @@ impl Decoder {
 
             // Load instructions
             0x03 => {
-                let imm = ((instruction as i32) >> 20) as i32;
+                let imm = (instruction as i32) >> 20;
                 
                 match funct3 {
                     0x0 => Ok(Instruction::Lb { rd, rs1, imm }),
@@ -70,6 +208,8 @@ impl Decoder {
                     0x2 => Ok(Instruction::Lw { rd, rs1, imm }),
                     0x4 => Ok(Instruction::Lbu { rd, rs1, imm }),
                     0x5 => Ok(Instruction::Lhu { rd, rs1, imm }),
+                    0x6 => Ok(Instruction::Lwu { rd, rs1, imm }),
+                    0x3 => Ok(Instruction::Ld { rd, rs1, imm }),
                     _ => Err(VmError::InvalidInstruction(instruction)),
                 }
             },
@@ -82,10 +222,89 @@ impl Decoder {
                     0x0 => Ok(Instruction::Sb { rs1, rs2, imm }),
                     0x1 => Ok(Instruction::Sh { rs1, rs2, imm }),
                     0x2 => Ok(Instruction::Sw { rs1, rs2, imm }),
+                    0x3 => Ok(Instruction::Sd { rs1, rs2, imm }),
+                    _ => Err(VmError::InvalidInstruction(instruction)),
+                }
+            },
+
+            // Floating-point loads (LOAD-FP)
+            0x07 => {
+                let imm = (instruction as i32) >> 20;
+
+                match funct3 {
+                    0x2 => Ok(Instruction::Flw { rd, rs1, imm }),
+                    0x3 => Ok(Instruction::Fld { rd, rs1, imm }),
                     _ => Err(VmError::InvalidInstruction(instruction)),
                 }
             },
 
+            // Floating-point stores (STORE-FP)
+            0x27 => {
+                let imm = (((instruction as i32) >> 25) << 5) | (((instruction >> 7) & 0x1f) as i32);
+
+                match funct3 {
+                    0x2 => Ok(Instruction::Fsw { rs1, rs2, imm }),
+                    0x3 => Ok(Instruction::Fsd { rs1, rs2, imm }),
+                    _ => Err(VmError::InvalidInstruction(instruction)),
+                }
+            },
+
+            // Fused multiply-add instructions (MADD, MSUB, NMSUB, NMADD)
+            0x43 | 0x47 | 0x4b | 0x4f => {
+                let fmt = Self::float_format(instruction)?;
+                let rm = Self::rounding_mode(instruction)?;
+                let rs3 = RegisterIndex::from_u32(instruction >> 27)
+                    .ok_or(VmError::InvalidInstruction(instruction))?;
+
+                match opcode {
+                    0x43 => Ok(Instruction::Fmadd { fmt, rd, rs1, rs2, rs3, rm }),
+                    0x47 => Ok(Instruction::Fmsub { fmt, rd, rs1, rs2, rs3, rm }),
+                    0x4b => Ok(Instruction::Fnmsub { fmt, rd, rs1, rs2, rs3, rm }),
+                    _ => Ok(Instruction::Fnmadd { fmt, rd, rs1, rs2, rs3, rm }),
+                }
+            },
+
+            // Floating-point computational instructions (OP-FP)
+            0x53 => {
+                let fmt = Self::float_format(instruction)?;
+                let rm = || Self::rounding_mode(instruction);
+                let rs2_field = (instruction >> 20) & 0x1f;
+                let illegal = || VmError::InvalidInstruction(instruction);
+
+                // funct5 selects the operation; the low two bits of funct7 hold fmt
+                match (funct7 >> 2, funct3, rs2_field) {
+                    (0x00, _, _) => Ok(Instruction::Fadd { fmt, rd, rs1, rs2, rm: rm()? }),
+                    (0x01, _, _) => Ok(Instruction::Fsub { fmt, rd, rs1, rs2, rm: rm()? }),
+                    (0x02, _, _) => Ok(Instruction::Fmul { fmt, rd, rs1, rs2, rm: rm()? }),
+                    (0x03, _, _) => Ok(Instruction::Fdiv { fmt, rd, rs1, rs2, rm: rm()? }),
+                    (0x0b, _, 0) => Ok(Instruction::Fsqrt { fmt, rd, rs1, rm: rm()? }),
+                    (0x04, 0x0, _) => Ok(Instruction::Fsgnj { fmt, rd, rs1, rs2 }),
+                    (0x04, 0x1, _) => Ok(Instruction::Fsgnjn { fmt, rd, rs1, rs2 }),
+                    (0x04, 0x2, _) => Ok(Instruction::Fsgnjx { fmt, rd, rs1, rs2 }),
+                    (0x05, 0x0, _) => Ok(Instruction::Fmin { fmt, rd, rs1, rs2 }),
+                    (0x05, 0x1, _) => Ok(Instruction::Fmax { fmt, rd, rs1, rs2 }),
+                    (0x08, _, 1) if fmt == FloatFormat::Single => Ok(Instruction::FcvtSD { rd, rs1, rm: rm()? }),
+                    (0x08, _, 0) if fmt == FloatFormat::Double => Ok(Instruction::FcvtDS { rd, rs1, rm: rm()? }),
+                    (0x14, 0x2, _) => Ok(Instruction::Feq { fmt, rd, rs1, rs2 }),
+                    (0x14, 0x1, _) => Ok(Instruction::Flt { fmt, rd, rs1, rs2 }),
+                    (0x14, 0x0, _) => Ok(Instruction::Fle { fmt, rd, rs1, rs2 }),
+                    (0x18, _, 0) => Ok(Instruction::FcvtW { fmt, rd, rs1, rm: rm()? }),
+                    (0x18, _, 1) => Ok(Instruction::FcvtWu { fmt, rd, rs1, rm: rm()? }),
+                    (0x1a, _, 0) => Ok(Instruction::FcvtFW { fmt, rd, rs1, rm: rm()? }),
+                    (0x1a, _, 1) => Ok(Instruction::FcvtFWu { fmt, rd, rs1, rm: rm()? }),
+                    (0x18, _, 2) => Ok(Instruction::FcvtL { fmt, rd, rs1, rm: rm()? }),
+                    (0x18, _, 3) => Ok(Instruction::FcvtLu { fmt, rd, rs1, rm: rm()? }),
+                    (0x1a, _, 2) => Ok(Instruction::FcvtFL { fmt, rd, rs1, rm: rm()? }),
+                    (0x1a, _, 3) => Ok(Instruction::FcvtFLu { fmt, rd, rs1, rm: rm()? }),
+                    (0x1c, 0x1, 0) => Ok(Instruction::Fclass { fmt, rd, rs1 }),
+                    (0x1c, 0x0, 0) if fmt == FloatFormat::Single => Ok(Instruction::FmvXW { rd, rs1 }),
+                    (0x1e, 0x0, 0) if fmt == FloatFormat::Single => Ok(Instruction::FmvWX { rd, rs1 }),
+                    (0x1c, 0x0, 0) => Ok(Instruction::FmvXD { rd, rs1 }),
+                    (0x1e, 0x0, 0) => Ok(Instruction::FmvDX { rd, rs1 }),
+                    _ => Err(illegal()),
+                }
+            },
+
             // Branch instructions
             0x63 => {
                 let imm = (((instruction as i32) >> 31) << 12) |
@@ -116,7 +335,7 @@ impl Decoder {
 
             // JALR
             0x67 => {
-                let imm = ((instruction as i32) >> 20) as i32;
+                let imm = (instruction as i32) >> 20;
                 
                 if funct3 == 0x0 {
                     Ok(Instruction::Jalr { rd, rs1, imm })
@@ -137,16 +356,39 @@ impl Decoder {
                 Ok(Instruction::Auipc { rd, imm })
             },
 
+            // MISC-MEM; the reserved rd, rs1 and FENCE.I immediate fields are ignored
+            0x0f => {
+                match funct3 {
+                    0x0 => Ok(Instruction::Fence {
+                        fm: instruction >> 28,
+                        pred: (instruction >> 24) & 0xf,
+                        succ: (instruction >> 20) & 0xf,
+                    }),
+                    0x1 => Ok(Instruction::FenceI),
+                    _ => Err(VmError::InvalidInstruction(instruction)),
+                }
+            },
+
             // System instructions
             0x73 => {
+                let csr = (instruction >> 20) as u16;
+                let uimm = (instruction >> 15) & 0x1f;
+
                 match funct3 {
                     0x0 => {
                         match instruction {
                             0x00000073 => Ok(Instruction::Ecall),
                             0x00100073 => Ok(Instruction::Ebreak),
+                            0x30200073 => Ok(Instruction::Mret),
                             _ => Err(VmError::InvalidInstruction(instruction)),
                         }
                     },
+                    0x1 => Ok(Instruction::Csrrw { rd, rs1, csr }),
+                    0x2 => Ok(Instruction::Csrrs { rd, rs1, csr }),
+                    0x3 => Ok(Instruction::Csrrc { rd, rs1, csr }),
+                    0x5 => Ok(Instruction::Csrrwi { rd, uimm, csr }),
+                    0x6 => Ok(Instruction::Csrrsi { rd, uimm, csr }),
+                    0x7 => Ok(Instruction::Csrrci { rd, uimm, csr }),
                     _ => Err(VmError::InvalidInstruction(instruction)),
                 }
             },
@@ -154,4 +396,277 @@ impl Decoder {
             _ => Err(VmError::InvalidInstruction(instruction)),
         }
     }
-}
\ No newline at end of file
+}
+impl Decoder {
+    /// Decode the `fmt` field (bits 26:25) of a floating-point instruction
+    fn float_format(instruction: u32) -> Result<FloatFormat, VmError> {
+        match (instruction >> 25) & 0x3 {
+            0x0 => Ok(FloatFormat::Single),
+            0x1 => Ok(FloatFormat::Double),
+            _ => Err(VmError::InvalidInstruction(instruction)),
+        }
+    }
+
+    /// Decode the `rm` field (bits 14:12) of a floating-point instruction
+    fn rounding_mode(instruction: u32) -> Result<RoundingMode, VmError> {
+        RoundingMode::from_u32((instruction >> 12) & 0x7).ok_or(VmError::InvalidInstruction(instruction))
+    }
+
+    /// Decode a 32-bit instruction word, rejecting instructions from
+    /// extensions that are disabled in `isa` as illegal
+    pub fn decode_with(instruction: u32, isa: &IsaConfig) -> Result<Instruction, VmError> {
+        let decoded = Self::decode(instruction)?;
+        if !isa.allows(&decoded) {
+            return Err(VmError::InvalidInstruction(instruction));
+        }
+        Ok(decoded)
+    }
+
+    /// Decode a 16-bit compressed instruction, rejecting it as illegal unless
+    /// the C extension and the extension of its expansion are enabled in `isa`
+    pub fn decode_compressed_with(parcel: u16, isa: &IsaConfig) -> Result<Instruction, VmError> {
+        if !isa.has(Extension::C) {
+            return Err(VmError::InvalidInstruction(parcel as u32));
+        }
+        let decoded = Self::decode_compressed_xlen(parcel, isa.xlen())?;
+        if !isa.allows(&decoded) {
+            return Err(VmError::InvalidInstruction(parcel as u32));
+        }
+        Ok(decoded)
+    }
+
+    /// Check whether a 16-bit instruction parcel starts a compressed (RVC) instruction
+    pub fn is_compressed(parcel: u16) -> bool {
+        parcel & 0x3 != 0x3
+    }
+
+    /// Decode a 16-bit RV32 compressed instruction into its expanded Instruction form
+    pub fn decode_compressed(parcel: u16) -> Result<Instruction, VmError> {
+        Self::decode_compressed_xlen(parcel, Xlen::Rv32)
+    }
+
+    /// Decode a 16-bit compressed instruction for a register width; several
+    /// RV32 floating-point and jump encodings are reused by RV64 for
+    /// double-word loads/stores and C.ADDIW
+    pub fn decode_compressed_xlen(parcel: u16, xlen: Xlen) -> Result<Instruction, VmError> {
+        let rv64 = xlen == Xlen::Rv64;
+        let inst = parcel as u32;
+        let illegal = || VmError::InvalidInstruction(inst);
+        let bits = |hi: u32, lo: u32| (inst >> lo) & ((1 << (hi - lo + 1)) - 1);
+        let reg = |val: u32| RegisterIndex::from_u32(val).ok_or_else(illegal);
+        // 3-bit register fields address x8-x15
+        let reg_prime = |val: u32| reg(val + 8);
+        // Sign-extend the low `width` bits of `val`
+        let sext = |val: u32, width: u32| ((val << (32 - width)) as i32) >> (32 - width);
+
+        let funct3 = bits(15, 13);
+        let rd = reg(bits(11, 7))?;
+        let rs2 = reg(bits(6, 2))?;
+        let rd_prime = reg_prime(bits(4, 2))?;
+        let rs1_prime = reg_prime(bits(9, 7))?;
+
+        // 6-bit signed immediate used by C.ADDI, C.LI and C.ANDI
+        let imm6 = sext((bits(12, 12) << 5) | bits(6, 2), 6);
+        // 6-bit shift amount; shamt[5] must be zero on RV32
+        let shamt = (bits(12, 12) << 5) | bits(6, 2);
+
+        match (inst & 0x3, funct3) {
+            // Quadrant 0
+            (0x0, 0x0) => {
+                // C.ADDI4SPN
+                let imm = (bits(12, 11) << 4) | (bits(10, 7) << 6) | (bits(6, 6) << 2) | (bits(5, 5) << 3);
+                if imm == 0 {
+                    return Err(illegal());
+                }
+                Ok(Instruction::Addi { rd: rd_prime, rs1: RegisterIndex::X2, imm: imm as i32 })
+            },
+            (0x0, 0x1) => {
+                // C.FLD
+                let imm = (bits(12, 10) << 3) | (bits(6, 5) << 6);
+                Ok(Instruction::Fld { rd: rd_prime, rs1: rs1_prime, imm: imm as i32 })
+            },
+            (0x0, 0x2) => {
+                // C.LW
+                let imm = (bits(12, 10) << 3) | (bits(6, 6) << 2) | (bits(5, 5) << 6);
+                Ok(Instruction::Lw { rd: rd_prime, rs1: rs1_prime, imm: imm as i32 })
+            },
+            (0x0, 0x3) if rv64 => {
+                // C.LD
+                let imm = (bits(12, 10) << 3) | (bits(6, 5) << 6);
+                Ok(Instruction::Ld { rd: rd_prime, rs1: rs1_prime, imm: imm as i32 })
+            },
+            (0x0, 0x3) => {
+                // C.FLW
+                let imm = (bits(12, 10) << 3) | (bits(6, 6) << 2) | (bits(5, 5) << 6);
+                Ok(Instruction::Flw { rd: rd_prime, rs1: rs1_prime, imm: imm as i32 })
+            },
+            (0x0, 0x5) => {
+                // C.FSD
+                let imm = (bits(12, 10) << 3) | (bits(6, 5) << 6);
+                Ok(Instruction::Fsd { rs1: rs1_prime, rs2: rd_prime, imm: imm as i32 })
+            },
+            (0x0, 0x6) => {
+                // C.SW
+                let imm = (bits(12, 10) << 3) | (bits(6, 6) << 2) | (bits(5, 5) << 6);
+                Ok(Instruction::Sw { rs1: rs1_prime, rs2: rd_prime, imm: imm as i32 })
+            },
+            (0x0, 0x7) if rv64 => {
+                // C.SD
+                let imm = (bits(12, 10) << 3) | (bits(6, 5) << 6);
+                Ok(Instruction::Sd { rs1: rs1_prime, rs2: rd_prime, imm: imm as i32 })
+            },
+            (0x0, 0x7) => {
+                // C.FSW
+                let imm = (bits(12, 10) << 3) | (bits(6, 6) << 2) | (bits(5, 5) << 6);
+                Ok(Instruction::Fsw { rs1: rs1_prime, rs2: rd_prime, imm: imm as i32 })
+            },
+
+            // Quadrant 1
+            (0x1, 0x0) => {
+                // C.ADDI / C.NOP
+                Ok(Instruction::Addi { rd, rs1: rd, imm: imm6 })
+            },
+            (0x1, 0x1) if rv64 => {
+                // C.ADDIW
+                if rd == RegisterIndex::X0 {
+                    return Err(illegal());
+                }
+                Ok(Instruction::Addiw { rd, rs1: rd, imm: imm6 })
+            },
+            (0x1, 0x1) | (0x1, 0x5) => {
+                // C.JAL / C.J
+                let imm = (bits(12, 12) << 11) | (bits(11, 11) << 4) | (bits(10, 9) << 8)
+                    | (bits(8, 8) << 10) | (bits(7, 7) << 6) | (bits(6, 6) << 7)
+                    | (bits(5, 3) << 1) | (bits(2, 2) << 5);
+                let rd = if funct3 == 0x1 { RegisterIndex::X1 } else { RegisterIndex::X0 };
+                Ok(Instruction::Jal { rd, imm: sext(imm, 12) })
+            },
+            (0x1, 0x2) => {
+                // C.LI
+                Ok(Instruction::Addi { rd, rs1: RegisterIndex::X0, imm: imm6 })
+            },
+            (0x1, 0x3) => {
+                if rd == RegisterIndex::X2 {
+                    // C.ADDI16SP
+                    let imm = (bits(12, 12) << 9) | (bits(6, 6) << 4) | (bits(5, 5) << 6)
+                        | (bits(4, 3) << 7) | (bits(2, 2) << 5);
+                    if imm == 0 {
+                        return Err(illegal());
+                    }
+                    Ok(Instruction::Addi { rd, rs1: rd, imm: sext(imm, 10) })
+                } else {
+                    // C.LUI
+                    if imm6 == 0 {
+                        return Err(illegal());
+                    }
+                    Ok(Instruction::Lui { rd, imm: imm6 << 12 })
+                }
+            },
+            (0x1, 0x4) => {
+                let rd = rs1_prime;
+                match bits(11, 10) {
+                    0x0 | 0x1 if shamt >= 32 && !rv64 => Err(illegal()),
+                    0x0 => Ok(Instruction::Srli { rd, rs1: rd, shamt }),
+                    0x1 => Ok(Instruction::Srai { rd, rs1: rd, shamt }),
+                    0x2 => Ok(Instruction::Andi { rd, rs1: rd, imm: imm6 }),
+                    _ => {
+                        let rs2 = rd_prime;
+                        match (bits(12, 12), bits(6, 5)) {
+                            (0, 0x0) => Ok(Instruction::Sub { rd, rs1: rd, rs2 }),
+                            (0, 0x1) => Ok(Instruction::Xor { rd, rs1: rd, rs2 }),
+                            (0, 0x2) => Ok(Instruction::Or { rd, rs1: rd, rs2 }),
+                            (0, 0x3) => Ok(Instruction::And { rd, rs1: rd, rs2 }),
+                            // C.SUBW / C.ADDW
+                            (1, 0x0) if rv64 => Ok(Instruction::Subw { rd, rs1: rd, rs2 }),
+                            (1, 0x1) if rv64 => Ok(Instruction::Addw { rd, rs1: rd, rs2 }),
+                            _ => Err(illegal()),
+                        }
+                    },
+                }
+            },
+            (0x1, 0x6) | (0x1, 0x7) => {
+                // C.BEQZ / C.BNEZ
+                let imm = (bits(12, 12) << 8) | (bits(11, 10) << 3) | (bits(6, 5) << 6)
+                    | (bits(4, 3) << 1) | (bits(2, 2) << 5);
+                let imm = sext(imm, 9);
+                if funct3 == 0x6 {
+                    Ok(Instruction::Beq { rs1: rs1_prime, rs2: RegisterIndex::X0, imm })
+                } else {
+                    Ok(Instruction::Bne { rs1: rs1_prime, rs2: RegisterIndex::X0, imm })
+                }
+            },
+
+            // Quadrant 2
+            (0x2, 0x0) => {
+                // C.SLLI
+                if shamt >= 32 && !rv64 {
+                    return Err(illegal());
+                }
+                Ok(Instruction::Slli { rd, rs1: rd, shamt })
+            },
+            (0x2, 0x1) => {
+                // C.FLDSP
+                let imm = (bits(12, 12) << 5) | (bits(6, 5) << 3) | (bits(4, 2) << 6);
+                Ok(Instruction::Fld { rd, rs1: RegisterIndex::X2, imm: imm as i32 })
+            },
+            (0x2, 0x2) => {
+                // C.LWSP
+                if rd == RegisterIndex::X0 {
+                    return Err(illegal());
+                }
+                let imm = (bits(12, 12) << 5) | (bits(6, 4) << 2) | (bits(3, 2) << 6);
+                Ok(Instruction::Lw { rd, rs1: RegisterIndex::X2, imm: imm as i32 })
+            },
+            (0x2, 0x3) if rv64 => {
+                // C.LDSP
+                if rd == RegisterIndex::X0 {
+                    return Err(illegal());
+                }
+                let imm = (bits(12, 12) << 5) | (bits(6, 5) << 3) | (bits(4, 2) << 6);
+                Ok(Instruction::Ld { rd, rs1: RegisterIndex::X2, imm: imm as i32 })
+            },
+            (0x2, 0x3) => {
+                // C.FLWSP
+                let imm = (bits(12, 12) << 5) | (bits(6, 4) << 2) | (bits(3, 2) << 6);
+                Ok(Instruction::Flw { rd, rs1: RegisterIndex::X2, imm: imm as i32 })
+            },
+            (0x2, 0x4) => {
+                match (bits(12, 12), rd, rs2) {
+                    (0, RegisterIndex::X0, RegisterIndex::X0) => Err(illegal()),
+                    // C.JR
+                    (0, rs1, RegisterIndex::X0) => Ok(Instruction::Jalr { rd: RegisterIndex::X0, rs1, imm: 0 }),
+                    // C.MV
+                    (0, rd, rs2) => Ok(Instruction::Add { rd, rs1: RegisterIndex::X0, rs2 }),
+                    // C.EBREAK
+                    (_, RegisterIndex::X0, RegisterIndex::X0) => Ok(Instruction::Ebreak),
+                    // C.JALR
+                    (_, rs1, RegisterIndex::X0) => Ok(Instruction::Jalr { rd: RegisterIndex::X1, rs1, imm: 0 }),
+                    // C.ADD
+                    (_, rd, rs2) => Ok(Instruction::Add { rd, rs1: rd, rs2 }),
+                }
+            },
+            (0x2, 0x5) => {
+                // C.FSDSP
+                let imm = (bits(12, 10) << 3) | (bits(9, 7) << 6);
+                Ok(Instruction::Fsd { rs1: RegisterIndex::X2, rs2, imm: imm as i32 })
+            },
+            (0x2, 0x6) => {
+                // C.SWSP
+                let imm = (bits(12, 9) << 2) | (bits(8, 7) << 6);
+                Ok(Instruction::Sw { rs1: RegisterIndex::X2, rs2, imm: imm as i32 })
+            },
+            (0x2, 0x7) if rv64 => {
+                // C.SDSP
+                let imm = (bits(12, 10) << 3) | (bits(9, 7) << 6);
+                Ok(Instruction::Sd { rs1: RegisterIndex::X2, rs2, imm: imm as i32 })
+            },
+            (0x2, 0x7) => {
+                // C.FSWSP
+                let imm = (bits(12, 9) << 2) | (bits(8, 7) << 6);
+                Ok(Instruction::Fsw { rs1: RegisterIndex::X2, rs2, imm: imm as i32 })
+            },
+
+            _ => Err(illegal()),
+        }
+    }
+}
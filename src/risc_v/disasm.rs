@@ -0,0 +1,384 @@
+//! RISC-V disassembler producing GNU-style assembly text
+
+use crate::risc_v::isa::Xlen;
+use crate::risc_v::{Decoder, FloatFormat, Instruction, RegisterIndex, RoundingMode};
+use crate::vm::csr::csr_name;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+/// Disassembler with optional symbol annotation of branch and jump targets
+#[derive(Debug, Clone, Default)]
+pub struct Disassembler {
+    symbols: BTreeMap<u32, String>,
+    xlen: Xlen,
+}
+
+impl Disassembler {
+    /// Create a disassembler without symbols
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a disassembler that annotates targets with the nearest preceding symbol
+    pub fn with_symbols(symbols: BTreeMap<u32, String>) -> Self {
+        Self { symbols, xlen: Xlen::Rv32 }
+    }
+
+    /// Decode compressed instructions for the given register width (RV32 by default)
+    pub fn with_xlen(self, xlen: Xlen) -> Self {
+        Self { xlen, ..self }
+    }
+
+    /// Format an instruction located at `pc`, resolving branch and jump targets
+    pub fn format(&self, instruction: &Instruction, pc: u32) -> String {
+        self.render(instruction, Some(pc))
+    }
+
+    /// Disassemble a block of code loaded at `base` into an objdump-like listing
+    pub fn disassemble(&self, code: &[u8], base: u32) -> String {
+        let mut out = String::new();
+        let mut offset = 0;
+
+        while offset + 2 <= code.len() {
+            let addr = base.wrapping_add(offset as u32);
+            if let Some(name) = self.symbols.get(&addr) {
+                let _ = writeln!(out, "\n{:08x} <{}>:", addr, name);
+            }
+
+            let parcel = u16::from_le_bytes([code[offset], code[offset + 1]]);
+            if Decoder::is_compressed(parcel) {
+                let text = match Decoder::decode_compressed_xlen(parcel, self.xlen) {
+                    Ok(instruction) => self.format(&instruction, addr),
+                    Err(_) => format!(".half 0x{:04x}", parcel),
+                };
+                let _ = writeln!(out, "{:8x}:  {:04x}      {}", addr, parcel, text);
+                offset += 2;
+            } else if offset + 4 <= code.len() {
+                let word = u32::from_le_bytes([
+                    code[offset],
+                    code[offset + 1],
+                    code[offset + 2],
+                    code[offset + 3],
+                ]);
+                let text = match Decoder::decode(word) {
+                    Ok(instruction) => self.format(&instruction, addr),
+                    Err(_) => format!(".word 0x{:08x}", word),
+                };
+                let _ = writeln!(out, "{:8x}:  {:08x}  {}", addr, word, text);
+                offset += 4;
+            } else {
+                let _ = writeln!(out, "{:8x}:  {:04x}      .half 0x{:04x}", addr, parcel, parcel);
+                offset += 2;
+            }
+        }
+
+        out
+    }
+
+    /// Format a branch or jump target, as an absolute address when `pc` is known
+    fn target(&self, pc: Option<u32>, offset: i32) -> String {
+        let Some(pc) = pc else {
+            return if offset < 0 {
+                format!(". - {}", offset.unsigned_abs())
+            } else {
+                format!(". + {}", offset)
+            };
+        };
+
+        let target = pc.wrapping_add(offset as u32);
+        match self.symbols.range(..=target).next_back() {
+            Some((&addr, name)) if addr == target => format!("0x{:x} <{}>", target, name),
+            Some((&addr, name)) => format!("0x{:x} <{}+0x{:x}>", target, name, target - addr),
+            None => format!("0x{:x}", target),
+        }
+    }
+
+    fn render(&self, instruction: &Instruction, pc: Option<u32>) -> String {
+        use Instruction::*;
+        use RegisterIndex::{X0, X1};
+
+        let r = |reg: RegisterIndex| reg.abi_name();
+        let csr = |csr: u16| csr_name(csr).map_or_else(|| format!("0x{:03x}", csr), str::to_string);
+        let rtype = |name: &str, rd, rs1, rs2| format!("{} {}, {}, {}", name, r(rd), r(rs1), r(rs2));
+        let itype = |name: &str, rd, rs1, imm: i32| format!("{} {}, {}, {}", name, r(rd), r(rs1), imm);
+        let shift = |name: &str, rd, rs1, shamt: u32| format!("{} {}, {}, {}", name, r(rd), r(rs1), shamt);
+        let mem = |name: &str, reg, base, imm: i32| format!("{} {}, {}({})", name, r(reg), imm, r(base));
+        let unary = |name: &str, rd, rs1| format!("{} {}, {}", name, r(rd), r(rs1));
+        let aes = |name: &str, rd, rs1, rs2, bs: u32| format!("{} {}, {}, {}, {}", name, r(rd), r(rs1), r(rs2), bs);
+        let amo = |name: &str, rd, rs1, rs2| format!("{} {}, {}, ({})", name, r(rd), r(rs2), r(rs1));
+        let branch = |name: &str, rs1, rs2, imm| format!("{} {}, {}, {}", name, r(rs1), r(rs2), self.target(pc, imm));
+        let csrr = |name: &str, rd, rs1, c| format!("{} {}, {}, {}", name, r(rd), csr(c), r(rs1));
+        let csri = |name: &str, rd, uimm: u32, c| format!("{} {}, {}, {}", name, r(rd), csr(c), uimm);
+        let f = |reg: RegisterIndex| reg.fp_abi_name();
+        let fop = |name: &str, fmt: FloatFormat| format!("{}.{}", name, fmt.suffix());
+        let fmem = |name: &str, reg, base, imm: i32| format!("{} {}, {}({})", name, f(reg), imm, r(base));
+        let frtype = |name: &str, fmt, rd, rs1, rs2| format!("{} {}, {}, {}", fop(name, fmt), f(rd), f(rs1), f(rs2));
+        let fcmp = |name: &str, fmt, rd, rs1, rs2| format!("{} {}, {}, {}", fop(name, fmt), r(rd), f(rs1), f(rs2));
+        // The rounding mode is only shown when it is not the dynamic default
+        let rounded = |text: String, rm: RoundingMode| match rm {
+            RoundingMode::Dyn => text,
+            rm => format!("{}, {}", text, rm.name()),
+        };
+        let fused = |name: &str, fmt, rd, rs1, rs2, rs3: RegisterIndex, rm| {
+            let text = format!("{} {}, {}, {}, {}", fop(name, fmt), f(rd), f(rs1), f(rs2), f(rs3));
+            rounded(text, rm)
+        };
+
+        match *instruction {
+            // Pseudo-instructions
+            Addi { rd: X0, rs1: X0, imm: 0 } => "nop".to_string(),
+            Addi { rd, rs1: X0, imm } => format!("li {}, {}", r(rd), imm),
+            Addi { rd, rs1, imm: 0 } => format!("mv {}, {}", r(rd), r(rs1)),
+            Xori { rd, rs1, imm: -1 } => format!("not {}, {}", r(rd), r(rs1)),
+            Sub { rd, rs1: X0, rs2 } => format!("neg {}, {}", r(rd), r(rs2)),
+            Sltiu { rd, rs1, imm: 1 } => format!("seqz {}, {}", r(rd), r(rs1)),
+            Sltu { rd, rs1: X0, rs2 } => format!("snez {}, {}", r(rd), r(rs2)),
+            Addiw { rd, rs1, imm: 0 } => format!("sext.w {}, {}", r(rd), r(rs1)),
+            Beq { rs1, rs2: X0, imm } => format!("beqz {}, {}", r(rs1), self.target(pc, imm)),
+            Bne { rs1, rs2: X0, imm } => format!("bnez {}, {}", r(rs1), self.target(pc, imm)),
+            Jal { rd: X0, imm } => format!("j {}", self.target(pc, imm)),
+            Jal { rd: X1, imm } => format!("jal {}", self.target(pc, imm)),
+            Jalr { rd: X0, rs1: X1, imm: 0 } => "ret".to_string(),
+            Jalr { rd: X0, rs1, imm: 0 } => format!("jr {}", r(rs1)),
+            Jalr { rd: X1, rs1, imm: 0 } => format!("jalr {}", r(rs1)),
+            Csrrs { rd, rs1: X0, csr: c } => format!("csrr {}, {}", r(rd), csr(c)),
+            Csrrw { rd: X0, rs1, csr: c } => format!("csrw {}, {}", csr(c), r(rs1)),
+            Fsgnj { fmt, rd, rs1, rs2 } if rs1 == rs2 => format!("{} {}, {}", fop("fmv", fmt), f(rd), f(rs1)),
+            Fsgnjn { fmt, rd, rs1, rs2 } if rs1 == rs2 => format!("{} {}, {}", fop("fneg", fmt), f(rd), f(rs1)),
+            Fsgnjx { fmt, rd, rs1, rs2 } if rs1 == rs2 => format!("{} {}, {}", fop("fabs", fmt), f(rd), f(rs1)),
+
+            // Arithmetic operations
+            Add { rd, rs1, rs2 } => rtype("add", rd, rs1, rs2),
+            Sub { rd, rs1, rs2 } => rtype("sub", rd, rs1, rs2),
+            Slt { rd, rs1, rs2 } => rtype("slt", rd, rs1, rs2),
+            Sltu { rd, rs1, rs2 } => rtype("sltu", rd, rs1, rs2),
+            And { rd, rs1, rs2 } => rtype("and", rd, rs1, rs2),
+            Or { rd, rs1, rs2 } => rtype("or", rd, rs1, rs2),
+            Xor { rd, rs1, rs2 } => rtype("xor", rd, rs1, rs2),
+            Sll { rd, rs1, rs2 } => rtype("sll", rd, rs1, rs2),
+            Srl { rd, rs1, rs2 } => rtype("srl", rd, rs1, rs2),
+            Sra { rd, rs1, rs2 } => rtype("sra", rd, rs1, rs2),
+
+            // Multiply/divide operations
+            Mul { rd, rs1, rs2 } => rtype("mul", rd, rs1, rs2),
+            Mulh { rd, rs1, rs2 } => rtype("mulh", rd, rs1, rs2),
+            Mulhsu { rd, rs1, rs2 } => rtype("mulhsu", rd, rs1, rs2),
+            Mulhu { rd, rs1, rs2 } => rtype("mulhu", rd, rs1, rs2),
+            Div { rd, rs1, rs2 } => rtype("div", rd, rs1, rs2),
+            Divu { rd, rs1, rs2 } => rtype("divu", rd, rs1, rs2),
+            Rem { rd, rs1, rs2 } => rtype("rem", rd, rs1, rs2),
+            Remu { rd, rs1, rs2 } => rtype("remu", rd, rs1, rs2),
+
+            // Atomic operations
+            LrW { rd, rs1 } => format!("lr.w {}, ({})", r(rd), r(rs1)),
+            ScW { rd, rs1, rs2 } => amo("sc.w", rd, rs1, rs2),
+            AmoswapW { rd, rs1, rs2 } => amo("amoswap.w", rd, rs1, rs2),
+            AmoaddW { rd, rs1, rs2 } => amo("amoadd.w", rd, rs1, rs2),
+            AmoandW { rd, rs1, rs2 } => amo("amoand.w", rd, rs1, rs2),
+            AmoorW { rd, rs1, rs2 } => amo("amoor.w", rd, rs1, rs2),
+            AmoxorW { rd, rs1, rs2 } => amo("amoxor.w", rd, rs1, rs2),
+            AmominW { rd, rs1, rs2 } => amo("amomin.w", rd, rs1, rs2),
+            AmomaxW { rd, rs1, rs2 } => amo("amomax.w", rd, rs1, rs2),
+            AmominuW { rd, rs1, rs2 } => amo("amominu.w", rd, rs1, rs2),
+            AmomaxuW { rd, rs1, rs2 } => amo("amomaxu.w", rd, rs1, rs2),
+            LrD { rd, rs1 } => format!("lr.d {}, ({})", r(rd), r(rs1)),
+            ScD { rd, rs1, rs2 } => amo("sc.d", rd, rs1, rs2),
+            AmoswapD { rd, rs1, rs2 } => amo("amoswap.d", rd, rs1, rs2),
+            AmoaddD { rd, rs1, rs2 } => amo("amoadd.d", rd, rs1, rs2),
+            AmoandD { rd, rs1, rs2 } => amo("amoand.d", rd, rs1, rs2),
+            AmoorD { rd, rs1, rs2 } => amo("amoor.d", rd, rs1, rs2),
+            AmoxorD { rd, rs1, rs2 } => amo("amoxor.d", rd, rs1, rs2),
+            AmominD { rd, rs1, rs2 } => amo("amomin.d", rd, rs1, rs2),
+            AmomaxD { rd, rs1, rs2 } => amo("amomax.d", rd, rs1, rs2),
+            AmominuD { rd, rs1, rs2 } => amo("amominu.d", rd, rs1, rs2),
+            AmomaxuD { rd, rs1, rs2 } => amo("amomaxu.d", rd, rs1, rs2),
+
+            // Word arithmetic operations
+            Addw { rd, rs1, rs2 } => rtype("addw", rd, rs1, rs2),
+            Subw { rd, rs1, rs2 } => rtype("subw", rd, rs1, rs2),
+            Sllw { rd, rs1, rs2 } => rtype("sllw", rd, rs1, rs2),
+            Srlw { rd, rs1, rs2 } => rtype("srlw", rd, rs1, rs2),
+            Sraw { rd, rs1, rs2 } => rtype("sraw", rd, rs1, rs2),
+            Mulw { rd, rs1, rs2 } => rtype("mulw", rd, rs1, rs2),
+            Divw { rd, rs1, rs2 } => rtype("divw", rd, rs1, rs2),
+            Divuw { rd, rs1, rs2 } => rtype("divuw", rd, rs1, rs2),
+            Remw { rd, rs1, rs2 } => rtype("remw", rd, rs1, rs2),
+            Remuw { rd, rs1, rs2 } => rtype("remuw", rd, rs1, rs2),
+            Addiw { rd, rs1, imm } => itype("addiw", rd, rs1, imm),
+            Slliw { rd, rs1, shamt } => shift("slliw", rd, rs1, shamt),
+            Srliw { rd, rs1, shamt } => shift("srliw", rd, rs1, shamt),
+            Sraiw { rd, rs1, shamt } => shift("sraiw", rd, rs1, shamt),
+
+            // Address generation operations
+            Sh1add { rd, rs1, rs2 } => rtype("sh1add", rd, rs1, rs2),
+            Sh2add { rd, rs1, rs2 } => rtype("sh2add", rd, rs1, rs2),
+            Sh3add { rd, rs1, rs2 } => rtype("sh3add", rd, rs1, rs2),
+
+            // Basic bit manipulation operations
+            Andn { rd, rs1, rs2 } => rtype("andn", rd, rs1, rs2),
+            Orn { rd, rs1, rs2 } => rtype("orn", rd, rs1, rs2),
+            Xnor { rd, rs1, rs2 } => rtype("xnor", rd, rs1, rs2),
+            Clz { rd, rs1 } => unary("clz", rd, rs1),
+            Ctz { rd, rs1 } => unary("ctz", rd, rs1),
+            Cpop { rd, rs1 } => unary("cpop", rd, rs1),
+            Max { rd, rs1, rs2 } => rtype("max", rd, rs1, rs2),
+            Maxu { rd, rs1, rs2 } => rtype("maxu", rd, rs1, rs2),
+            Min { rd, rs1, rs2 } => rtype("min", rd, rs1, rs2),
+            Minu { rd, rs1, rs2 } => rtype("minu", rd, rs1, rs2),
+            SextB { rd, rs1 } => unary("sext.b", rd, rs1),
+            SextH { rd, rs1 } => unary("sext.h", rd, rs1),
+            ZextH { rd, rs1 } => unary("zext.h", rd, rs1),
+            Rol { rd, rs1, rs2 } => rtype("rol", rd, rs1, rs2),
+            Ror { rd, rs1, rs2 } => rtype("ror", rd, rs1, rs2),
+            Rori { rd, rs1, shamt } => shift("rori", rd, rs1, shamt),
+            OrcB { rd, rs1 } => unary("orc.b", rd, rs1),
+            Rev8 { rd, rs1 } => unary("rev8", rd, rs1),
+
+            // Single-bit operations
+            Bclr { rd, rs1, rs2 } => rtype("bclr", rd, rs1, rs2),
+            Bclri { rd, rs1, shamt } => shift("bclri", rd, rs1, shamt),
+            Bext { rd, rs1, rs2 } => rtype("bext", rd, rs1, rs2),
+            Bexti { rd, rs1, shamt } => shift("bexti", rd, rs1, shamt),
+            Binv { rd, rs1, rs2 } => rtype("binv", rd, rs1, rs2),
+            Binvi { rd, rs1, shamt } => shift("binvi", rd, rs1, shamt),
+            Bset { rd, rs1, rs2 } => rtype("bset", rd, rs1, rs2),
+            Bseti { rd, rs1, shamt } => shift("bseti", rd, rs1, shamt),
+
+            // SHA-2 hash operations
+            Sha256Sig0 { rd, rs1 } => unary("sha256sig0", rd, rs1),
+            Sha256Sig1 { rd, rs1 } => unary("sha256sig1", rd, rs1),
+            Sha256Sum0 { rd, rs1 } => unary("sha256sum0", rd, rs1),
+            Sha256Sum1 { rd, rs1 } => unary("sha256sum1", rd, rs1),
+            Sha512Sig0h { rd, rs1, rs2 } => rtype("sha512sig0h", rd, rs1, rs2),
+            Sha512Sig0l { rd, rs1, rs2 } => rtype("sha512sig0l", rd, rs1, rs2),
+            Sha512Sig1h { rd, rs1, rs2 } => rtype("sha512sig1h", rd, rs1, rs2),
+            Sha512Sig1l { rd, rs1, rs2 } => rtype("sha512sig1l", rd, rs1, rs2),
+            Sha512Sum0r { rd, rs1, rs2 } => rtype("sha512sum0r", rd, rs1, rs2),
+            Sha512Sum1r { rd, rs1, rs2 } => rtype("sha512sum1r", rd, rs1, rs2),
+
+            // AES round operations
+            Aes32esi { rd, rs1, rs2, bs } => aes("aes32esi", rd, rs1, rs2, bs),
+            Aes32esmi { rd, rs1, rs2, bs } => aes("aes32esmi", rd, rs1, rs2, bs),
+            Aes32dsi { rd, rs1, rs2, bs } => aes("aes32dsi", rd, rs1, rs2, bs),
+            Aes32dsmi { rd, rs1, rs2, bs } => aes("aes32dsmi", rd, rs1, rs2, bs),
+
+            // Floating-point loads and stores
+            Flw { rd, rs1, imm } => fmem("flw", rd, rs1, imm),
+            Fld { rd, rs1, imm } => fmem("fld", rd, rs1, imm),
+            Fsw { rs1, rs2, imm } => fmem("fsw", rs2, rs1, imm),
+            Fsd { rs1, rs2, imm } => fmem("fsd", rs2, rs1, imm),
+
+            // Floating-point arithmetic operations
+            Fmadd { fmt, rd, rs1, rs2, rs3, rm } => fused("fmadd", fmt, rd, rs1, rs2, rs3, rm),
+            Fmsub { fmt, rd, rs1, rs2, rs3, rm } => fused("fmsub", fmt, rd, rs1, rs2, rs3, rm),
+            Fnmsub { fmt, rd, rs1, rs2, rs3, rm } => fused("fnmsub", fmt, rd, rs1, rs2, rs3, rm),
+            Fnmadd { fmt, rd, rs1, rs2, rs3, rm } => fused("fnmadd", fmt, rd, rs1, rs2, rs3, rm),
+            Fadd { fmt, rd, rs1, rs2, rm } => rounded(frtype("fadd", fmt, rd, rs1, rs2), rm),
+            Fsub { fmt, rd, rs1, rs2, rm } => rounded(frtype("fsub", fmt, rd, rs1, rs2), rm),
+            Fmul { fmt, rd, rs1, rs2, rm } => rounded(frtype("fmul", fmt, rd, rs1, rs2), rm),
+            Fdiv { fmt, rd, rs1, rs2, rm } => rounded(frtype("fdiv", fmt, rd, rs1, rs2), rm),
+            Fsqrt { fmt, rd, rs1, rm } => rounded(format!("{} {}, {}", fop("fsqrt", fmt), f(rd), f(rs1)), rm),
+            Fsgnj { fmt, rd, rs1, rs2 } => frtype("fsgnj", fmt, rd, rs1, rs2),
+            Fsgnjn { fmt, rd, rs1, rs2 } => frtype("fsgnjn", fmt, rd, rs1, rs2),
+            Fsgnjx { fmt, rd, rs1, rs2 } => frtype("fsgnjx", fmt, rd, rs1, rs2),
+            Fmin { fmt, rd, rs1, rs2 } => frtype("fmin", fmt, rd, rs1, rs2),
+            Fmax { fmt, rd, rs1, rs2 } => frtype("fmax", fmt, rd, rs1, rs2),
+
+            // Floating-point comparisons and classification
+            Feq { fmt, rd, rs1, rs2 } => fcmp("feq", fmt, rd, rs1, rs2),
+            Flt { fmt, rd, rs1, rs2 } => fcmp("flt", fmt, rd, rs1, rs2),
+            Fle { fmt, rd, rs1, rs2 } => fcmp("fle", fmt, rd, rs1, rs2),
+            Fclass { fmt, rd, rs1 } => format!("{} {}, {}", fop("fclass", fmt), r(rd), f(rs1)),
+
+            // Floating-point conversions and moves
+            FcvtW { fmt, rd, rs1, rm } => rounded(format!("fcvt.w.{} {}, {}", fmt.suffix(), r(rd), f(rs1)), rm),
+            FcvtWu { fmt, rd, rs1, rm } => rounded(format!("fcvt.wu.{} {}, {}", fmt.suffix(), r(rd), f(rs1)), rm),
+            FcvtFW { fmt, rd, rs1, rm } => rounded(format!("fcvt.{}.w {}, {}", fmt.suffix(), f(rd), r(rs1)), rm),
+            FcvtFWu { fmt, rd, rs1, rm } => rounded(format!("fcvt.{}.wu {}, {}", fmt.suffix(), f(rd), r(rs1)), rm),
+            FcvtSD { rd, rs1, rm } => rounded(format!("fcvt.s.d {}, {}", f(rd), f(rs1)), rm),
+            FcvtDS { rd, rs1, rm } => rounded(format!("fcvt.d.s {}, {}", f(rd), f(rs1)), rm),
+            FmvXW { rd, rs1 } => format!("fmv.x.w {}, {}", r(rd), f(rs1)),
+            FmvWX { rd, rs1 } => format!("fmv.w.x {}, {}", f(rd), r(rs1)),
+            FcvtL { fmt, rd, rs1, rm } => rounded(format!("fcvt.l.{} {}, {}", fmt.suffix(), r(rd), f(rs1)), rm),
+            FcvtLu { fmt, rd, rs1, rm } => rounded(format!("fcvt.lu.{} {}, {}", fmt.suffix(), r(rd), f(rs1)), rm),
+            FcvtFL { fmt, rd, rs1, rm } => rounded(format!("fcvt.{}.l {}, {}", fmt.suffix(), f(rd), r(rs1)), rm),
+            FcvtFLu { fmt, rd, rs1, rm } => rounded(format!("fcvt.{}.lu {}, {}", fmt.suffix(), f(rd), r(rs1)), rm),
+            FmvXD { rd, rs1 } => format!("fmv.x.d {}, {}", r(rd), f(rs1)),
+            FmvDX { rd, rs1 } => format!("fmv.d.x {}, {}", f(rd), r(rs1)),
+
+            // Immediate arithmetic operations
+            Addi { rd, rs1, imm } => itype("addi", rd, rs1, imm),
+            Slti { rd, rs1, imm } => itype("slti", rd, rs1, imm),
+            Sltiu { rd, rs1, imm } => itype("sltiu", rd, rs1, imm),
+            Andi { rd, rs1, imm } => itype("andi", rd, rs1, imm),
+            Ori { rd, rs1, imm } => itype("ori", rd, rs1, imm),
+            Xori { rd, rs1, imm } => itype("xori", rd, rs1, imm),
+            Slli { rd, rs1, shamt } => shift("slli", rd, rs1, shamt),
+            Srli { rd, rs1, shamt } => shift("srli", rd, rs1, shamt),
+            Srai { rd, rs1, shamt } => shift("srai", rd, rs1, shamt),
+
+            // Load operations
+            Lb { rd, rs1, imm } => mem("lb", rd, rs1, imm),
+            Lh { rd, rs1, imm } => mem("lh", rd, rs1, imm),
+            Lw { rd, rs1, imm } => mem("lw", rd, rs1, imm),
+            Lbu { rd, rs1, imm } => mem("lbu", rd, rs1, imm),
+            Lhu { rd, rs1, imm } => mem("lhu", rd, rs1, imm),
+            Lwu { rd, rs1, imm } => mem("lwu", rd, rs1, imm),
+            Ld { rd, rs1, imm } => mem("ld", rd, rs1, imm),
+
+            // Store operations
+            Sb { rs1, rs2, imm } => mem("sb", rs2, rs1, imm),
+            Sh { rs1, rs2, imm } => mem("sh", rs2, rs1, imm),
+            Sw { rs1, rs2, imm } => mem("sw", rs2, rs1, imm),
+            Sd { rs1, rs2, imm } => mem("sd", rs2, rs1, imm),
+
+            // Branch operations
+            Beq { rs1, rs2, imm } => branch("beq", rs1, rs2, imm),
+            Bne { rs1, rs2, imm } => branch("bne", rs1, rs2, imm),
+            Blt { rs1, rs2, imm } => branch("blt", rs1, rs2, imm),
+            Bge { rs1, rs2, imm } => branch("bge", rs1, rs2, imm),
+            Bltu { rs1, rs2, imm } => branch("bltu", rs1, rs2, imm),
+            Bgeu { rs1, rs2, imm } => branch("bgeu", rs1, rs2, imm),
+
+            // Jump operations
+            Jal { rd, imm } => format!("jal {}, {}", r(rd), self.target(pc, imm)),
+            Jalr { rd, rs1, imm } => mem("jalr", rd, rs1, imm),
+
+            // Upper immediate operations
+            Lui { rd, imm } => format!("lui {}, 0x{:x}", r(rd), (imm as u32) >> 12),
+            Auipc { rd, imm } => format!("auipc {}, 0x{:x}", r(rd), (imm as u32) >> 12),
+
+            // Memory ordering
+            Fence { fm: 0, pred: 0xf, succ: 0xf } => "fence".to_string(),
+            Fence { fm: 8, pred: 0x3, succ: 0x3 } => "fence.tso".to_string(),
+            Fence { pred, succ, .. } => format!("fence {}, {}", fence_set(pred), fence_set(succ)),
+            FenceI => "fence.i".to_string(),
+
+            // System operations
+            Ecall => "ecall".to_string(),
+            Ebreak => "ebreak".to_string(),
+            Mret => "mret".to_string(),
+
+            // Control and status register operations
+            Csrrw { rd, rs1, csr } => csrr("csrrw", rd, rs1, csr),
+            Csrrs { rd, rs1, csr } => csrr("csrrs", rd, rs1, csr),
+            Csrrc { rd, rs1, csr } => csrr("csrrc", rd, rs1, csr),
+            Csrrwi { rd, uimm, csr } => csri("csrrwi", rd, uimm, csr),
+            Csrrsi { rd, uimm, csr } => csri("csrrsi", rd, uimm, csr),
+            Csrrci { rd, uimm, csr } => csri("csrrci", rd, uimm, csr),
+        }
+    }
+}
+
+/// Format a FENCE predecessor or successor set as a subset of `iorw`
+fn fence_set(bits: u32) -> String {
+    let set: String = "iorw".chars().enumerate().filter(|(i, _)| bits & (8 >> i) != 0).map(|(_, c)| c).collect();
+    if set.is_empty() { "0".to_string() } else { set }
+}
+
+impl fmt::Display for Instruction {
+    /// Formats the instruction without a known PC, so targets are `.`-relative
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Disassembler::new().render(self, None))
+    }
+}
@@ -0,0 +1,331 @@
+//! RISC-V instruction encoder (the inverse of `Decoder`)
+
+use crate::risc_v::instructions::{FloatFormat, RoundingMode};
+use crate::risc_v::{Instruction, RegisterIndex};
+use crate::utils::VmError;
+
+impl Instruction {
+    /// Encode the instruction into a 32-bit instruction word.
+    ///
+    /// Fails if an immediate, shift amount or CSR address does not fit its
+    /// field, or if a branch/jump offset is not 2-byte aligned.
+    pub fn encode(&self) -> Result<u32, VmError> {
+        use Instruction::*;
+
+        let word = match *self {
+            // Arithmetic operations
+            Add { rd, rs1, rs2 } => r_type(0x33, 0x0, 0x00, rd, rs1, rs2),
+            Sub { rd, rs1, rs2 } => r_type(0x33, 0x0, 0x20, rd, rs1, rs2),
+            Sll { rd, rs1, rs2 } => r_type(0x33, 0x1, 0x00, rd, rs1, rs2),
+            Slt { rd, rs1, rs2 } => r_type(0x33, 0x2, 0x00, rd, rs1, rs2),
+            Sltu { rd, rs1, rs2 } => r_type(0x33, 0x3, 0x00, rd, rs1, rs2),
+            Xor { rd, rs1, rs2 } => r_type(0x33, 0x4, 0x00, rd, rs1, rs2),
+            Srl { rd, rs1, rs2 } => r_type(0x33, 0x5, 0x00, rd, rs1, rs2),
+            Sra { rd, rs1, rs2 } => r_type(0x33, 0x5, 0x20, rd, rs1, rs2),
+            Or { rd, rs1, rs2 } => r_type(0x33, 0x6, 0x00, rd, rs1, rs2),
+            And { rd, rs1, rs2 } => r_type(0x33, 0x7, 0x00, rd, rs1, rs2),
+
+            // Multiply/divide operations
+            Mul { rd, rs1, rs2 } => r_type(0x33, 0x0, 0x01, rd, rs1, rs2),
+            Mulh { rd, rs1, rs2 } => r_type(0x33, 0x1, 0x01, rd, rs1, rs2),
+            Mulhsu { rd, rs1, rs2 } => r_type(0x33, 0x2, 0x01, rd, rs1, rs2),
+            Mulhu { rd, rs1, rs2 } => r_type(0x33, 0x3, 0x01, rd, rs1, rs2),
+            Div { rd, rs1, rs2 } => r_type(0x33, 0x4, 0x01, rd, rs1, rs2),
+            Divu { rd, rs1, rs2 } => r_type(0x33, 0x5, 0x01, rd, rs1, rs2),
+            Rem { rd, rs1, rs2 } => r_type(0x33, 0x6, 0x01, rd, rs1, rs2),
+            Remu { rd, rs1, rs2 } => r_type(0x33, 0x7, 0x01, rd, rs1, rs2),
+
+            // Atomic operations (funct7 holds funct5 with aq = rl = 0)
+            LrW { rd, rs1 } => r_type(0x2f, 0x2, 0x02 << 2, rd, rs1, RegisterIndex::X0),
+            ScW { rd, rs1, rs2 } => r_type(0x2f, 0x2, 0x03 << 2, rd, rs1, rs2),
+            AmoswapW { rd, rs1, rs2 } => r_type(0x2f, 0x2, 0x01 << 2, rd, rs1, rs2),
+            AmoaddW { rd, rs1, rs2 } => r_type(0x2f, 0x2, 0x00, rd, rs1, rs2),
+            AmoxorW { rd, rs1, rs2 } => r_type(0x2f, 0x2, 0x04 << 2, rd, rs1, rs2),
+            AmoandW { rd, rs1, rs2 } => r_type(0x2f, 0x2, 0x0c << 2, rd, rs1, rs2),
+            AmoorW { rd, rs1, rs2 } => r_type(0x2f, 0x2, 0x08 << 2, rd, rs1, rs2),
+            AmominW { rd, rs1, rs2 } => r_type(0x2f, 0x2, 0x10 << 2, rd, rs1, rs2),
+            AmomaxW { rd, rs1, rs2 } => r_type(0x2f, 0x2, 0x14 << 2, rd, rs1, rs2),
+            AmominuW { rd, rs1, rs2 } => r_type(0x2f, 0x2, 0x18 << 2, rd, rs1, rs2),
+            AmomaxuW { rd, rs1, rs2 } => r_type(0x2f, 0x2, 0x1c << 2, rd, rs1, rs2),
+            LrD { rd, rs1 } => r_type(0x2f, 0x3, 0x02 << 2, rd, rs1, RegisterIndex::X0),
+            ScD { rd, rs1, rs2 } => r_type(0x2f, 0x3, 0x03 << 2, rd, rs1, rs2),
+            AmoswapD { rd, rs1, rs2 } => r_type(0x2f, 0x3, 0x01 << 2, rd, rs1, rs2),
+            AmoaddD { rd, rs1, rs2 } => r_type(0x2f, 0x3, 0x00, rd, rs1, rs2),
+            AmoxorD { rd, rs1, rs2 } => r_type(0x2f, 0x3, 0x04 << 2, rd, rs1, rs2),
+            AmoandD { rd, rs1, rs2 } => r_type(0x2f, 0x3, 0x0c << 2, rd, rs1, rs2),
+            AmoorD { rd, rs1, rs2 } => r_type(0x2f, 0x3, 0x08 << 2, rd, rs1, rs2),
+            AmominD { rd, rs1, rs2 } => r_type(0x2f, 0x3, 0x10 << 2, rd, rs1, rs2),
+            AmomaxD { rd, rs1, rs2 } => r_type(0x2f, 0x3, 0x14 << 2, rd, rs1, rs2),
+            AmominuD { rd, rs1, rs2 } => r_type(0x2f, 0x3, 0x18 << 2, rd, rs1, rs2),
+            AmomaxuD { rd, rs1, rs2 } => r_type(0x2f, 0x3, 0x1c << 2, rd, rs1, rs2),
+
+            // Word arithmetic operations (RV64)
+            Addw { rd, rs1, rs2 } => r_type(0x3b, 0x0, 0x00, rd, rs1, rs2),
+            Subw { rd, rs1, rs2 } => r_type(0x3b, 0x0, 0x20, rd, rs1, rs2),
+            Sllw { rd, rs1, rs2 } => r_type(0x3b, 0x1, 0x00, rd, rs1, rs2),
+            Srlw { rd, rs1, rs2 } => r_type(0x3b, 0x5, 0x00, rd, rs1, rs2),
+            Sraw { rd, rs1, rs2 } => r_type(0x3b, 0x5, 0x20, rd, rs1, rs2),
+            Mulw { rd, rs1, rs2 } => r_type(0x3b, 0x0, 0x01, rd, rs1, rs2),
+            Divw { rd, rs1, rs2 } => r_type(0x3b, 0x4, 0x01, rd, rs1, rs2),
+            Divuw { rd, rs1, rs2 } => r_type(0x3b, 0x5, 0x01, rd, rs1, rs2),
+            Remw { rd, rs1, rs2 } => r_type(0x3b, 0x6, 0x01, rd, rs1, rs2),
+            Remuw { rd, rs1, rs2 } => r_type(0x3b, 0x7, 0x01, rd, rs1, rs2),
+            Addiw { rd, rs1, imm } => i_type(0x1b, 0x0, rd, rs1, self.signed(imm, 12, 1)?),
+            Slliw { rd, rs1, shamt } => i_type(0x1b, 0x1, rd, rs1, self.unsigned(shamt, 5)?),
+            Srliw { rd, rs1, shamt } => i_type(0x1b, 0x5, rd, rs1, self.unsigned(shamt, 5)?),
+            Sraiw { rd, rs1, shamt } => i_type(0x1b, 0x5, rd, rs1, 0x400 | self.unsigned(shamt, 5)?),
+
+            // Address generation operations
+            Sh1add { rd, rs1, rs2 } => r_type(0x33, 0x2, 0x10, rd, rs1, rs2),
+            Sh2add { rd, rs1, rs2 } => r_type(0x33, 0x4, 0x10, rd, rs1, rs2),
+            Sh3add { rd, rs1, rs2 } => r_type(0x33, 0x6, 0x10, rd, rs1, rs2),
+
+            // Basic bit manipulation operations
+            Andn { rd, rs1, rs2 } => r_type(0x33, 0x7, 0x20, rd, rs1, rs2),
+            Orn { rd, rs1, rs2 } => r_type(0x33, 0x6, 0x20, rd, rs1, rs2),
+            Xnor { rd, rs1, rs2 } => r_type(0x33, 0x4, 0x20, rd, rs1, rs2),
+            Clz { rd, rs1 } => i_type(0x13, 0x1, rd, rs1, 0x600),
+            Ctz { rd, rs1 } => i_type(0x13, 0x1, rd, rs1, 0x601),
+            Cpop { rd, rs1 } => i_type(0x13, 0x1, rd, rs1, 0x602),
+            Min { rd, rs1, rs2 } => r_type(0x33, 0x4, 0x05, rd, rs1, rs2),
+            Minu { rd, rs1, rs2 } => r_type(0x33, 0x5, 0x05, rd, rs1, rs2),
+            Max { rd, rs1, rs2 } => r_type(0x33, 0x6, 0x05, rd, rs1, rs2),
+            Maxu { rd, rs1, rs2 } => r_type(0x33, 0x7, 0x05, rd, rs1, rs2),
+            SextB { rd, rs1 } => i_type(0x13, 0x1, rd, rs1, 0x604),
+            SextH { rd, rs1 } => i_type(0x13, 0x1, rd, rs1, 0x605),
+            ZextH { rd, rs1 } => r_type(0x33, 0x4, 0x04, rd, rs1, RegisterIndex::X0),
+            Rol { rd, rs1, rs2 } => r_type(0x33, 0x1, 0x30, rd, rs1, rs2),
+            Ror { rd, rs1, rs2 } => r_type(0x33, 0x5, 0x30, rd, rs1, rs2),
+            Rori { rd, rs1, shamt } => i_type(0x13, 0x5, rd, rs1, 0x600 | self.unsigned(shamt, 5)?),
+            OrcB { rd, rs1 } => i_type(0x13, 0x5, rd, rs1, 0x287),
+            Rev8 { rd, rs1 } => i_type(0x13, 0x5, rd, rs1, 0x698),
+
+            // Single-bit operations
+            Bclr { rd, rs1, rs2 } => r_type(0x33, 0x1, 0x24, rd, rs1, rs2),
+            Bext { rd, rs1, rs2 } => r_type(0x33, 0x5, 0x24, rd, rs1, rs2),
+            Binv { rd, rs1, rs2 } => r_type(0x33, 0x1, 0x34, rd, rs1, rs2),
+            Bset { rd, rs1, rs2 } => r_type(0x33, 0x1, 0x14, rd, rs1, rs2),
+            Bclri { rd, rs1, shamt } => i_type(0x13, 0x1, rd, rs1, 0x480 | self.unsigned(shamt, 5)?),
+            Bexti { rd, rs1, shamt } => i_type(0x13, 0x5, rd, rs1, 0x480 | self.unsigned(shamt, 5)?),
+            Binvi { rd, rs1, shamt } => i_type(0x13, 0x1, rd, rs1, 0x680 | self.unsigned(shamt, 5)?),
+            Bseti { rd, rs1, shamt } => i_type(0x13, 0x1, rd, rs1, 0x280 | self.unsigned(shamt, 5)?),
+
+            // SHA-2 hash operations
+            Sha256Sum0 { rd, rs1 } => i_type(0x13, 0x1, rd, rs1, 0x100),
+            Sha256Sum1 { rd, rs1 } => i_type(0x13, 0x1, rd, rs1, 0x101),
+            Sha256Sig0 { rd, rs1 } => i_type(0x13, 0x1, rd, rs1, 0x102),
+            Sha256Sig1 { rd, rs1 } => i_type(0x13, 0x1, rd, rs1, 0x103),
+            Sha512Sum0r { rd, rs1, rs2 } => r_type(0x33, 0x0, 0x28, rd, rs1, rs2),
+            Sha512Sum1r { rd, rs1, rs2 } => r_type(0x33, 0x0, 0x29, rd, rs1, rs2),
+            Sha512Sig0l { rd, rs1, rs2 } => r_type(0x33, 0x0, 0x2a, rd, rs1, rs2),
+            Sha512Sig1l { rd, rs1, rs2 } => r_type(0x33, 0x0, 0x2b, rd, rs1, rs2),
+            Sha512Sig0h { rd, rs1, rs2 } => r_type(0x33, 0x0, 0x2e, rd, rs1, rs2),
+            Sha512Sig1h { rd, rs1, rs2 } => r_type(0x33, 0x0, 0x2f, rd, rs1, rs2),
+
+            // AES round operations
+            Aes32esi { rd, rs1, rs2, bs } => r_type(0x33, 0x0, self.unsigned(bs, 2)? << 5 | 0x11, rd, rs1, rs2),
+            Aes32esmi { rd, rs1, rs2, bs } => r_type(0x33, 0x0, self.unsigned(bs, 2)? << 5 | 0x13, rd, rs1, rs2),
+            Aes32dsi { rd, rs1, rs2, bs } => r_type(0x33, 0x0, self.unsigned(bs, 2)? << 5 | 0x15, rd, rs1, rs2),
+            Aes32dsmi { rd, rs1, rs2, bs } => r_type(0x33, 0x0, self.unsigned(bs, 2)? << 5 | 0x17, rd, rs1, rs2),
+
+            // Floating-point loads and stores
+            Flw { rd, rs1, imm } => i_type(0x07, 0x2, rd, rs1, self.signed(imm, 12, 1)?),
+            Fld { rd, rs1, imm } => i_type(0x07, 0x3, rd, rs1, self.signed(imm, 12, 1)?),
+            Fsw { rs1, rs2, imm } => s_type(0x27, 0x2, rs1, rs2, self.signed(imm, 12, 1)?),
+            Fsd { rs1, rs2, imm } => s_type(0x27, 0x3, rs1, rs2, self.signed(imm, 12, 1)?),
+
+            // Floating-point arithmetic operations
+            Fmadd { fmt, rd, rs1, rs2, rs3, rm } => r4_type(0x43, fmt, rm, rd, rs1, rs2, rs3),
+            Fmsub { fmt, rd, rs1, rs2, rs3, rm } => r4_type(0x47, fmt, rm, rd, rs1, rs2, rs3),
+            Fnmsub { fmt, rd, rs1, rs2, rs3, rm } => r4_type(0x4b, fmt, rm, rd, rs1, rs2, rs3),
+            Fnmadd { fmt, rd, rs1, rs2, rs3, rm } => r4_type(0x4f, fmt, rm, rd, rs1, rs2, rs3),
+            Fadd { fmt, rd, rs1, rs2, rm } => fp_type(0x00, fmt, rm as u32, rd, rs1, rs2 as u32),
+            Fsub { fmt, rd, rs1, rs2, rm } => fp_type(0x01, fmt, rm as u32, rd, rs1, rs2 as u32),
+            Fmul { fmt, rd, rs1, rs2, rm } => fp_type(0x02, fmt, rm as u32, rd, rs1, rs2 as u32),
+            Fdiv { fmt, rd, rs1, rs2, rm } => fp_type(0x03, fmt, rm as u32, rd, rs1, rs2 as u32),
+            Fsqrt { fmt, rd, rs1, rm } => fp_type(0x0b, fmt, rm as u32, rd, rs1, 0),
+            Fsgnj { fmt, rd, rs1, rs2 } => fp_type(0x04, fmt, 0x0, rd, rs1, rs2 as u32),
+            Fsgnjn { fmt, rd, rs1, rs2 } => fp_type(0x04, fmt, 0x1, rd, rs1, rs2 as u32),
+            Fsgnjx { fmt, rd, rs1, rs2 } => fp_type(0x04, fmt, 0x2, rd, rs1, rs2 as u32),
+            Fmin { fmt, rd, rs1, rs2 } => fp_type(0x05, fmt, 0x0, rd, rs1, rs2 as u32),
+            Fmax { fmt, rd, rs1, rs2 } => fp_type(0x05, fmt, 0x1, rd, rs1, rs2 as u32),
+
+            // Floating-point comparisons and classification
+            Feq { fmt, rd, rs1, rs2 } => fp_type(0x14, fmt, 0x2, rd, rs1, rs2 as u32),
+            Flt { fmt, rd, rs1, rs2 } => fp_type(0x14, fmt, 0x1, rd, rs1, rs2 as u32),
+            Fle { fmt, rd, rs1, rs2 } => fp_type(0x14, fmt, 0x0, rd, rs1, rs2 as u32),
+            Fclass { fmt, rd, rs1 } => fp_type(0x1c, fmt, 0x1, rd, rs1, 0),
+
+            // Floating-point conversions and moves
+            FcvtW { fmt, rd, rs1, rm } => fp_type(0x18, fmt, rm as u32, rd, rs1, 0),
+            FcvtWu { fmt, rd, rs1, rm } => fp_type(0x18, fmt, rm as u32, rd, rs1, 1),
+            FcvtFW { fmt, rd, rs1, rm } => fp_type(0x1a, fmt, rm as u32, rd, rs1, 0),
+            FcvtFWu { fmt, rd, rs1, rm } => fp_type(0x1a, fmt, rm as u32, rd, rs1, 1),
+            FcvtSD { rd, rs1, rm } => fp_type(0x08, FloatFormat::Single, rm as u32, rd, rs1, 1),
+            FcvtDS { rd, rs1, rm } => fp_type(0x08, FloatFormat::Double, rm as u32, rd, rs1, 0),
+            FmvXW { rd, rs1 } => fp_type(0x1c, FloatFormat::Single, 0x0, rd, rs1, 0),
+            FmvWX { rd, rs1 } => fp_type(0x1e, FloatFormat::Single, 0x0, rd, rs1, 0),
+            FcvtL { fmt, rd, rs1, rm } => fp_type(0x18, fmt, rm as u32, rd, rs1, 2),
+            FcvtLu { fmt, rd, rs1, rm } => fp_type(0x18, fmt, rm as u32, rd, rs1, 3),
+            FcvtFL { fmt, rd, rs1, rm } => fp_type(0x1a, fmt, rm as u32, rd, rs1, 2),
+            FcvtFLu { fmt, rd, rs1, rm } => fp_type(0x1a, fmt, rm as u32, rd, rs1, 3),
+            FmvXD { rd, rs1 } => fp_type(0x1c, FloatFormat::Double, 0x0, rd, rs1, 0),
+            FmvDX { rd, rs1 } => fp_type(0x1e, FloatFormat::Double, 0x0, rd, rs1, 0),
+
+            // Immediate arithmetic operations
+            Addi { rd, rs1, imm } => i_type(0x13, 0x0, rd, rs1, self.signed(imm, 12, 1)?),
+            Slti { rd, rs1, imm } => i_type(0x13, 0x2, rd, rs1, self.signed(imm, 12, 1)?),
+            Sltiu { rd, rs1, imm } => i_type(0x13, 0x3, rd, rs1, self.signed(imm, 12, 1)?),
+            Xori { rd, rs1, imm } => i_type(0x13, 0x4, rd, rs1, self.signed(imm, 12, 1)?),
+            Ori { rd, rs1, imm } => i_type(0x13, 0x6, rd, rs1, self.signed(imm, 12, 1)?),
+            Andi { rd, rs1, imm } => i_type(0x13, 0x7, rd, rs1, self.signed(imm, 12, 1)?),
+            // Shift amounts of 32 or more are only valid on RV64
+            Slli { rd, rs1, shamt } => i_type(0x13, 0x1, rd, rs1, self.unsigned(shamt, 6)?),
+            Srli { rd, rs1, shamt } => i_type(0x13, 0x5, rd, rs1, self.unsigned(shamt, 6)?),
+            Srai { rd, rs1, shamt } => i_type(0x13, 0x5, rd, rs1, 0x400 | self.unsigned(shamt, 6)?),
+
+            // Load operations
+            Lb { rd, rs1, imm } => i_type(0x03, 0x0, rd, rs1, self.signed(imm, 12, 1)?),
+            Lh { rd, rs1, imm } => i_type(0x03, 0x1, rd, rs1, self.signed(imm, 12, 1)?),
+            Lw { rd, rs1, imm } => i_type(0x03, 0x2, rd, rs1, self.signed(imm, 12, 1)?),
+            Lbu { rd, rs1, imm } => i_type(0x03, 0x4, rd, rs1, self.signed(imm, 12, 1)?),
+            Lhu { rd, rs1, imm } => i_type(0x03, 0x5, rd, rs1, self.signed(imm, 12, 1)?),
+            Lwu { rd, rs1, imm } => i_type(0x03, 0x6, rd, rs1, self.signed(imm, 12, 1)?),
+            Ld { rd, rs1, imm } => i_type(0x03, 0x3, rd, rs1, self.signed(imm, 12, 1)?),
+
+            // Store operations
+            Sb { rs1, rs2, imm } => s_type(0x23, 0x0, rs1, rs2, self.signed(imm, 12, 1)?),
+            Sh { rs1, rs2, imm } => s_type(0x23, 0x1, rs1, rs2, self.signed(imm, 12, 1)?),
+            Sw { rs1, rs2, imm } => s_type(0x23, 0x2, rs1, rs2, self.signed(imm, 12, 1)?),
+            Sd { rs1, rs2, imm } => s_type(0x23, 0x3, rs1, rs2, self.signed(imm, 12, 1)?),
+
+            // Branch operations
+            Beq { rs1, rs2, imm } => b_type(0x0, rs1, rs2, self.signed(imm, 13, 2)?),
+            Bne { rs1, rs2, imm } => b_type(0x1, rs1, rs2, self.signed(imm, 13, 2)?),
+            Blt { rs1, rs2, imm } => b_type(0x4, rs1, rs2, self.signed(imm, 13, 2)?),
+            Bge { rs1, rs2, imm } => b_type(0x5, rs1, rs2, self.signed(imm, 13, 2)?),
+            Bltu { rs1, rs2, imm } => b_type(0x6, rs1, rs2, self.signed(imm, 13, 2)?),
+            Bgeu { rs1, rs2, imm } => b_type(0x7, rs1, rs2, self.signed(imm, 13, 2)?),
+
+            // Jump operations
+            Jal { rd, imm } => {
+                let imm = self.signed(imm, 21, 2)?;
+                ((imm >> 20) & 1) << 31
+                    | ((imm >> 1) & 0x3ff) << 21
+                    | ((imm >> 11) & 1) << 20
+                    | ((imm >> 12) & 0xff) << 12
+                    | (rd as u32) << 7
+                    | 0x6f
+            },
+            Jalr { rd, rs1, imm } => i_type(0x67, 0x0, rd, rs1, self.signed(imm, 12, 1)?),
+
+            // Upper immediate operations
+            Lui { rd, imm } => self.upper(imm)? | (rd as u32) << 7 | 0x37,
+            Auipc { rd, imm } => self.upper(imm)? | (rd as u32) << 7 | 0x17,
+
+            // Memory ordering
+            Fence { fm, pred, succ } => {
+                self.unsigned(fm, 4)? << 28 | self.unsigned(pred, 4)? << 24 | self.unsigned(succ, 4)? << 20 | 0x0f
+            },
+            FenceI => 0x0000100f,
+
+            // System operations
+            Ecall => 0x00000073,
+            Ebreak => 0x00100073,
+            Mret => 0x30200073,
+
+            // Control and status register operations
+            Csrrw { rd, rs1, csr } => csr_type(0x1, rd, rs1 as u32, self.csr(csr)?),
+            Csrrs { rd, rs1, csr } => csr_type(0x2, rd, rs1 as u32, self.csr(csr)?),
+            Csrrc { rd, rs1, csr } => csr_type(0x3, rd, rs1 as u32, self.csr(csr)?),
+            Csrrwi { rd, uimm, csr } => csr_type(0x5, rd, self.unsigned(uimm, 5)?, self.csr(csr)?),
+            Csrrsi { rd, uimm, csr } => csr_type(0x6, rd, self.unsigned(uimm, 5)?, self.csr(csr)?),
+            Csrrci { rd, uimm, csr } => csr_type(0x7, rd, self.unsigned(uimm, 5)?, self.csr(csr)?),
+        };
+
+        Ok(word)
+    }
+
+    /// Check that `imm` is a `bits`-wide signed multiple of `align`
+    fn signed(&self, imm: i32, bits: u32, align: i32) -> Result<u32, VmError> {
+        let min = -(1i64 << (bits - 1));
+        let max = (1i64 << (bits - 1)) - 1;
+        if (imm as i64) < min || (imm as i64) > max {
+            return Err(self.encoding_error(format!("immediate {} out of range", imm)));
+        }
+        if imm % align != 0 {
+            return Err(self.encoding_error(format!("immediate {} is not {}-byte aligned", imm, align)));
+        }
+        Ok(imm as u32 & ((1 << bits) - 1))
+    }
+
+    /// Check that `value` fits in a `bits`-wide unsigned field
+    fn unsigned(&self, value: u32, bits: u32) -> Result<u32, VmError> {
+        if value >> bits != 0 {
+            return Err(self.encoding_error(format!("value {} out of range", value)));
+        }
+        Ok(value)
+    }
+
+    /// Check that a U-type immediate has its low 12 bits clear
+    fn upper(&self, imm: i32) -> Result<u32, VmError> {
+        if imm & 0xfff != 0 {
+            return Err(self.encoding_error(format!("upper immediate {:#x} has low bits set", imm)));
+        }
+        Ok(imm as u32)
+    }
+
+    /// Check that a CSR address fits in 12 bits
+    fn csr(&self, csr: u16) -> Result<u32, VmError> {
+        self.unsigned(csr as u32, 12)
+    }
+
+    fn encoding_error(&self, reason: String) -> VmError {
+        VmError::EncodingError(format!("{:?}: {}", self, reason))
+    }
+}
+
+fn r_type(opcode: u32, funct3: u32, funct7: u32, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex) -> u32 {
+    funct7 << 25 | (rs2 as u32) << 20 | (rs1 as u32) << 15 | funct3 << 12 | (rd as u32) << 7 | opcode
+}
+
+fn i_type(opcode: u32, funct3: u32, rd: RegisterIndex, rs1: RegisterIndex, imm: u32) -> u32 {
+    imm << 20 | (rs1 as u32) << 15 | funct3 << 12 | (rd as u32) << 7 | opcode
+}
+
+fn s_type(opcode: u32, funct3: u32, rs1: RegisterIndex, rs2: RegisterIndex, imm: u32) -> u32 {
+    (imm >> 5) << 25 | (rs2 as u32) << 20 | (rs1 as u32) << 15 | funct3 << 12 | (imm & 0x1f) << 7 | opcode
+}
+
+fn b_type(funct3: u32, rs1: RegisterIndex, rs2: RegisterIndex, imm: u32) -> u32 {
+    ((imm >> 12) & 1) << 31
+        | ((imm >> 5) & 0x3f) << 25
+        | (rs2 as u32) << 20
+        | (rs1 as u32) << 15
+        | funct3 << 12
+        | ((imm >> 1) & 0xf) << 8
+        | ((imm >> 11) & 1) << 7
+        | 0x63
+}
+
+/// OP-FP instruction; funct7 holds `funct5` and the format, and `funct3`
+/// holds either the rounding mode or a sub-operation
+fn fp_type(funct5: u32, fmt: FloatFormat, funct3: u32, rd: RegisterIndex, rs1: RegisterIndex, rs2: u32) -> u32 {
+    (funct5 << 2 | fmt as u32) << 25 | rs2 << 20 | (rs1 as u32) << 15 | funct3 << 12 | (rd as u32) << 7 | 0x53
+}
+
+fn r4_type(
+    opcode: u32,
+    fmt: FloatFormat,
+    rm: RoundingMode,
+    rd: RegisterIndex,
+    rs1: RegisterIndex,
+    rs2: RegisterIndex,
+    rs3: RegisterIndex,
+) -> u32 {
+    (rs3 as u32) << 27 | (fmt as u32) << 25 | r_type(opcode, rm as u32, 0, rd, rs1, rs2)
+}
+
+fn csr_type(funct3: u32, rd: RegisterIndex, rs1: u32, csr: u32) -> u32 {
+    csr << 20 | rs1 << 15 | funct3 << 12 | (rd as u32) << 7 | 0x73
+}
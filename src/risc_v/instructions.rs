@@ -1,8 +1,67 @@
 //! RISC-V instruction definitions
 
+use crate::risc_v::isa::Extension;
 use crate::risc_v::registers::RegisterIndex;
 
-/// RISC-V RV32I instruction types
+/// Floating-point operand format (the `fmt` field of F and D instructions)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// IEEE 754 binary32 (F extension)
+    Single = 0,
+    /// IEEE 754 binary64 (D extension)
+    Double = 1,
+}
+
+impl FloatFormat {
+    /// Assembly suffix of the format (`s` or `d`)
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::Single => "s",
+            Self::Double => "d",
+        }
+    }
+}
+
+/// Floating-point rounding mode (the `rm` field of F and D instructions)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Round to nearest, ties to even
+    Rne = 0,
+    /// Round towards zero
+    Rtz = 1,
+    /// Round down (towards negative infinity)
+    Rdn = 2,
+    /// Round up (towards positive infinity)
+    Rup = 3,
+    /// Round to nearest, ties to max magnitude
+    Rmm = 4,
+    /// Use the dynamic rounding mode in `frm`
+    Dyn = 7,
+}
+
+impl RoundingMode {
+    /// All rounding modes, in encoding order
+    pub const ALL: [RoundingMode; 6] = [Self::Rne, Self::Rtz, Self::Rdn, Self::Rup, Self::Rmm, Self::Dyn];
+
+    /// Decode a 3-bit `rm` field; the reserved values 5 and 6 are invalid
+    pub fn from_u32(value: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|rm| *rm as u32 == value)
+    }
+
+    /// Lowercase name as it appears in assembly
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rne => "rne",
+            Self::Rtz => "rtz",
+            Self::Rdn => "rdn",
+            Self::Rup => "rup",
+            Self::Rmm => "rmm",
+            Self::Dyn => "dyn",
+        }
+    }
+}
+
+/// RISC-V RV32IMAFD / RV64IMA instruction types with the Zicsr, bit-manipulation and scalar crypto extensions
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Instruction {
     // Arithmetic operations
@@ -17,6 +76,159 @@ pub enum Instruction {
     Srl { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
     Sra { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
 
+    // Multiply/divide operations (M extension)
+    Mul { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Mulh { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Mulhsu { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Mulhu { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Div { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Divu { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Rem { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Remu { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+
+    // Atomic operations (A extension)
+    LrW { rd: RegisterIndex, rs1: RegisterIndex },
+    ScW { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmoswapW { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmoaddW { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmoandW { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmoorW { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmoxorW { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmominW { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmomaxW { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmominuW { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmomaxuW { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+
+    // Word arithmetic operations (RV64I, RV64M); results are sign-extended from 32 bits
+    Addw { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Subw { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Sllw { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Srlw { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Sraw { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Mulw { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Divw { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Divuw { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Remw { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Remuw { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Addiw { rd: RegisterIndex, rs1: RegisterIndex, imm: i32 },
+    Slliw { rd: RegisterIndex, rs1: RegisterIndex, shamt: u32 },
+    Srliw { rd: RegisterIndex, rs1: RegisterIndex, shamt: u32 },
+    Sraiw { rd: RegisterIndex, rs1: RegisterIndex, shamt: u32 },
+
+    // Double-word atomic operations (RV64A)
+    LrD { rd: RegisterIndex, rs1: RegisterIndex },
+    ScD { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmoswapD { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmoaddD { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmoandD { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmoorD { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmoxorD { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmominD { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmomaxD { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmominuD { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    AmomaxuD { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+
+    // Address generation operations (Zba)
+    Sh1add { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Sh2add { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Sh3add { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+
+    // Basic bit manipulation operations (Zbb)
+    Andn { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Orn { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Xnor { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Clz { rd: RegisterIndex, rs1: RegisterIndex },
+    Ctz { rd: RegisterIndex, rs1: RegisterIndex },
+    Cpop { rd: RegisterIndex, rs1: RegisterIndex },
+    Max { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Maxu { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Min { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Minu { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    SextB { rd: RegisterIndex, rs1: RegisterIndex },
+    SextH { rd: RegisterIndex, rs1: RegisterIndex },
+    ZextH { rd: RegisterIndex, rs1: RegisterIndex },
+    Rol { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Ror { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Rori { rd: RegisterIndex, rs1: RegisterIndex, shamt: u32 },
+    OrcB { rd: RegisterIndex, rs1: RegisterIndex },
+    Rev8 { rd: RegisterIndex, rs1: RegisterIndex },
+
+    // Single-bit operations (Zbs)
+    Bclr { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Bclri { rd: RegisterIndex, rs1: RegisterIndex, shamt: u32 },
+    Bext { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Bexti { rd: RegisterIndex, rs1: RegisterIndex, shamt: u32 },
+    Binv { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Binvi { rd: RegisterIndex, rs1: RegisterIndex, shamt: u32 },
+    Bset { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Bseti { rd: RegisterIndex, rs1: RegisterIndex, shamt: u32 },
+
+    // SHA-2 hash operations (Zknh)
+    Sha256Sig0 { rd: RegisterIndex, rs1: RegisterIndex },
+    Sha256Sig1 { rd: RegisterIndex, rs1: RegisterIndex },
+    Sha256Sum0 { rd: RegisterIndex, rs1: RegisterIndex },
+    Sha256Sum1 { rd: RegisterIndex, rs1: RegisterIndex },
+    Sha512Sig0h { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Sha512Sig0l { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Sha512Sig1h { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Sha512Sig1l { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Sha512Sum0r { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Sha512Sum1r { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+
+    // AES round operations (Zkne, Zknd); `bs` selects the byte of rs2
+    Aes32esi { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, bs: u32 },
+    Aes32esmi { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, bs: u32 },
+    Aes32dsi { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, bs: u32 },
+    Aes32dsmi { rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, bs: u32 },
+
+    // Floating-point loads and stores (F, D); `rd` and `rs2` are FP registers
+    Flw { rd: RegisterIndex, rs1: RegisterIndex, imm: i32 },
+    Fld { rd: RegisterIndex, rs1: RegisterIndex, imm: i32 },
+    Fsw { rs1: RegisterIndex, rs2: RegisterIndex, imm: i32 },
+    Fsd { rs1: RegisterIndex, rs2: RegisterIndex, imm: i32 },
+
+    // Floating-point arithmetic operations (F, D); all operands are FP registers
+    Fmadd { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, rs3: RegisterIndex, rm: RoundingMode },
+    Fmsub { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, rs3: RegisterIndex, rm: RoundingMode },
+    Fnmsub { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, rs3: RegisterIndex, rm: RoundingMode },
+    Fnmadd { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, rs3: RegisterIndex, rm: RoundingMode },
+    Fadd { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, rm: RoundingMode },
+    Fsub { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, rm: RoundingMode },
+    Fmul { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, rm: RoundingMode },
+    Fdiv { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, rm: RoundingMode },
+    Fsqrt { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    Fsgnj { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Fsgnjn { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Fsgnjx { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Fmin { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Fmax { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+
+    // Floating-point comparisons and classification; `rd` is an integer register
+    Feq { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Flt { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Fle { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex },
+    Fclass { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex },
+
+    // Floating-point conversions and moves; `FcvtW`/`FcvtWu`/`FmvXW` write an
+    // integer register and `FcvtFW`/`FcvtFWu`/`FmvWX` read one
+    FcvtW { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    FcvtWu { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    FcvtFW { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    FcvtFWu { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    FcvtSD { rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    FcvtDS { rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    FmvXW { rd: RegisterIndex, rs1: RegisterIndex },
+    FmvWX { rd: RegisterIndex, rs1: RegisterIndex },
+
+    // RV64 floating-point conversions to and from 64-bit integers, and
+    // double-precision moves to and from an integer register
+    FcvtL { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    FcvtLu { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    FcvtFL { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    FcvtFLu { fmt: FloatFormat, rd: RegisterIndex, rs1: RegisterIndex, rm: RoundingMode },
+    FmvXD { rd: RegisterIndex, rs1: RegisterIndex },
+    FmvDX { rd: RegisterIndex, rs1: RegisterIndex },
+
     // Immediate arithmetic operations
     Addi { rd: RegisterIndex, rs1: RegisterIndex, imm: i32 },
     Slti { rd: RegisterIndex, rs1: RegisterIndex, imm: i32 },
@@ -34,11 +246,14 @@ pub enum Instruction {
     Lw { rd: RegisterIndex, rs1: RegisterIndex, imm: i32 },
     Lbu { rd: RegisterIndex, rs1: RegisterIndex, imm: i32 },
     Lhu { rd: RegisterIndex, rs1: RegisterIndex, imm: i32 },
+    Lwu { rd: RegisterIndex, rs1: RegisterIndex, imm: i32 },
+    Ld { rd: RegisterIndex, rs1: RegisterIndex, imm: i32 },
 
     // Store operations
     Sb { rs1: RegisterIndex, rs2: RegisterIndex, imm: i32 },
     Sh { rs1: RegisterIndex, rs2: RegisterIndex, imm: i32 },
     Sw { rs1: RegisterIndex, rs2: RegisterIndex, imm: i32 },
+    Sd { rs1: RegisterIndex, rs2: RegisterIndex, imm: i32 },
 
     // Branch operations
     Beq { rs1: RegisterIndex, rs2: RegisterIndex, imm: i32 },
@@ -56,15 +271,30 @@ pub enum Instruction {
     Lui { rd: RegisterIndex, imm: i32 },
     Auipc { rd: RegisterIndex, imm: i32 },
 
+    // Memory ordering; `pred`/`succ` hold the I, O, R, W bits (8, 4, 2, 1) and
+    // `fm` is 8 for FENCE.TSO. Both are no-ops on the VM's single hart.
+    Fence { fm: u32, pred: u32, succ: u32 },
+    FenceI,
+
     // System operations
     Ecall,
     Ebreak,
+    Mret,
+
+    // Control and status register operations (Zicsr)
+    Csrrw { rd: RegisterIndex, rs1: RegisterIndex, csr: u16 },
+    Csrrs { rd: RegisterIndex, rs1: RegisterIndex, csr: u16 },
+    Csrrc { rd: RegisterIndex, rs1: RegisterIndex, csr: u16 },
+    Csrrwi { rd: RegisterIndex, uimm: u32, csr: u16 },
+    Csrrsi { rd: RegisterIndex, uimm: u32, csr: u16 },
+    Csrrci { rd: RegisterIndex, uimm: u32, csr: u16 },
 }
 
 /// Instruction format types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InstructionFormat {
     R,    // Register format
+    R4,   // Fused multiply-add format (three source registers)
     I,    // Immediate format
     S,    // Store format
     B,    // Branch format
@@ -73,21 +303,158 @@ pub enum InstructionFormat {
 }
 
 impl Instruction {
+    /// Check whether the instruction writes memory (stores, SC and AMOs)
+    pub fn is_store(&self) -> bool {
+        matches!(
+            self,
+            Self::Sb { .. } | Self::Sh { .. } | Self::Sw { .. } | Self::Sd { .. }
+            | Self::Fsw { .. } | Self::Fsd { .. }
+            | Self::ScW { .. } | Self::AmoswapW { .. } | Self::AmoaddW { .. }
+            | Self::AmoandW { .. } | Self::AmoorW { .. } | Self::AmoxorW { .. }
+            | Self::AmominW { .. } | Self::AmomaxW { .. }
+            | Self::AmominuW { .. } | Self::AmomaxuW { .. }
+            | Self::ScD { .. } | Self::AmoswapD { .. } | Self::AmoaddD { .. }
+            | Self::AmoandD { .. } | Self::AmoorD { .. } | Self::AmoxorD { .. }
+            | Self::AmominD { .. } | Self::AmomaxD { .. }
+            | Self::AmominuD { .. } | Self::AmomaxuD { .. }
+        )
+    }
+
+    /// Check whether the instruction only exists on RV64: the word and
+    /// double-word operations, 64-bit FP conversions and moves, and shifts
+    /// by 32 or more
+    pub fn requires_rv64(&self) -> bool {
+        match self {
+            Self::Slli { shamt, .. } | Self::Srli { shamt, .. } | Self::Srai { shamt, .. } => *shamt >= 32,
+
+            Self::Addw { .. } | Self::Subw { .. } | Self::Sllw { .. } | Self::Srlw { .. } | Self::Sraw { .. }
+            | Self::Mulw { .. } | Self::Divw { .. } | Self::Divuw { .. } | Self::Remw { .. } | Self::Remuw { .. }
+            | Self::Addiw { .. } | Self::Slliw { .. } | Self::Srliw { .. } | Self::Sraiw { .. }
+            | Self::Lwu { .. } | Self::Ld { .. } | Self::Sd { .. }
+            | Self::LrD { .. } | Self::ScD { .. } | Self::AmoswapD { .. } | Self::AmoaddD { .. }
+            | Self::AmoandD { .. } | Self::AmoorD { .. } | Self::AmoxorD { .. }
+            | Self::AmominD { .. } | Self::AmomaxD { .. }
+            | Self::AmominuD { .. } | Self::AmomaxuD { .. }
+            | Self::FcvtL { .. } | Self::FcvtLu { .. } | Self::FcvtFL { .. } | Self::FcvtFLu { .. }
+            | Self::FmvXD { .. } | Self::FmvDX { .. } => true,
+
+            _ => false,
+        }
+    }
+
+    /// ISA extension the instruction belongs to
+    pub fn extension(&self) -> Extension {
+        match self {
+            Self::Mul { .. } | Self::Mulh { .. } | Self::Mulhsu { .. } | Self::Mulhu { .. }
+            | Self::Div { .. } | Self::Divu { .. } | Self::Rem { .. } | Self::Remu { .. }
+            | Self::Mulw { .. } | Self::Divw { .. } | Self::Divuw { .. }
+            | Self::Remw { .. } | Self::Remuw { .. } => Extension::M,
+
+            Self::LrW { .. } | Self::ScW { .. } | Self::AmoswapW { .. } | Self::AmoaddW { .. }
+            | Self::AmoandW { .. } | Self::AmoorW { .. } | Self::AmoxorW { .. }
+            | Self::AmominW { .. } | Self::AmomaxW { .. }
+            | Self::AmominuW { .. } | Self::AmomaxuW { .. }
+            | Self::LrD { .. } | Self::ScD { .. } | Self::AmoswapD { .. } | Self::AmoaddD { .. }
+            | Self::AmoandD { .. } | Self::AmoorD { .. } | Self::AmoxorD { .. }
+            | Self::AmominD { .. } | Self::AmomaxD { .. }
+            | Self::AmominuD { .. } | Self::AmomaxuD { .. } => Extension::A,
+
+            Self::Csrrw { .. } | Self::Csrrs { .. } | Self::Csrrc { .. }
+            | Self::Csrrwi { .. } | Self::Csrrsi { .. } | Self::Csrrci { .. } => Extension::Zicsr,
+
+            Self::Sh1add { .. } | Self::Sh2add { .. } | Self::Sh3add { .. } => Extension::Zba,
+
+            Self::Andn { .. } | Self::Orn { .. } | Self::Xnor { .. }
+            | Self::Clz { .. } | Self::Ctz { .. } | Self::Cpop { .. }
+            | Self::Max { .. } | Self::Maxu { .. } | Self::Min { .. } | Self::Minu { .. }
+            | Self::SextB { .. } | Self::SextH { .. } | Self::ZextH { .. }
+            | Self::Rol { .. } | Self::Ror { .. } | Self::Rori { .. }
+            | Self::OrcB { .. } | Self::Rev8 { .. } => Extension::Zbb,
+
+            Self::Bclr { .. } | Self::Bclri { .. } | Self::Bext { .. } | Self::Bexti { .. }
+            | Self::Binv { .. } | Self::Binvi { .. } | Self::Bset { .. } | Self::Bseti { .. } => Extension::Zbs,
+
+            Self::Sha256Sig0 { .. } | Self::Sha256Sig1 { .. } | Self::Sha256Sum0 { .. } | Self::Sha256Sum1 { .. }
+            | Self::Sha512Sig0h { .. } | Self::Sha512Sig0l { .. } | Self::Sha512Sig1h { .. }
+            | Self::Sha512Sig1l { .. } | Self::Sha512Sum0r { .. } | Self::Sha512Sum1r { .. } => Extension::Zknh,
+
+            Self::Aes32esi { .. } | Self::Aes32esmi { .. } => Extension::Zkne,
+            Self::Aes32dsi { .. } | Self::Aes32dsmi { .. } => Extension::Zknd,
+
+            Self::Fld { .. } | Self::Fsd { .. } | Self::FcvtSD { .. } | Self::FcvtDS { .. }
+            | Self::FmvXD { .. } | Self::FmvDX { .. } => Extension::D,
+            Self::Flw { .. } | Self::Fsw { .. } | Self::FmvXW { .. } | Self::FmvWX { .. } => Extension::F,
+
+            Self::Fmadd { fmt, .. } | Self::Fmsub { fmt, .. } | Self::Fnmsub { fmt, .. } | Self::Fnmadd { fmt, .. }
+            | Self::Fadd { fmt, .. } | Self::Fsub { fmt, .. } | Self::Fmul { fmt, .. } | Self::Fdiv { fmt, .. }
+            | Self::Fsqrt { fmt, .. } | Self::Fsgnj { fmt, .. } | Self::Fsgnjn { fmt, .. } | Self::Fsgnjx { fmt, .. }
+            | Self::Fmin { fmt, .. } | Self::Fmax { fmt, .. }
+            | Self::Feq { fmt, .. } | Self::Flt { fmt, .. } | Self::Fle { fmt, .. } | Self::Fclass { fmt, .. }
+            | Self::FcvtW { fmt, .. } | Self::FcvtWu { fmt, .. }
+            | Self::FcvtFW { fmt, .. } | Self::FcvtFWu { fmt, .. }
+            | Self::FcvtL { fmt, .. } | Self::FcvtLu { fmt, .. }
+            | Self::FcvtFL { fmt, .. } | Self::FcvtFLu { fmt, .. } => match fmt {
+                FloatFormat::Single => Extension::F,
+                FloatFormat::Double => Extension::D,
+            },
+
+            _ => Extension::I,
+        }
+    }
+
     /// Get the instruction format
     pub fn format(&self) -> InstructionFormat {
         match self {
             Self::Add { .. } | Self::Sub { .. } | Self::Slt { .. } | Self::Sltu { .. }
             | Self::And { .. } | Self::Or { .. } | Self::Xor { .. }
-            | Self::Sll { .. } | Self::Srl { .. } | Self::Sra { .. } => InstructionFormat::R,
+            | Self::Sll { .. } | Self::Srl { .. } | Self::Sra { .. }
+            | Self::Mul { .. } | Self::Mulh { .. } | Self::Mulhsu { .. } | Self::Mulhu { .. }
+            | Self::Div { .. } | Self::Divu { .. } | Self::Rem { .. } | Self::Remu { .. }
+            | Self::LrW { .. } | Self::ScW { .. } | Self::AmoswapW { .. } | Self::AmoaddW { .. }
+            | Self::AmoandW { .. } | Self::AmoorW { .. } | Self::AmoxorW { .. }
+            | Self::AmominW { .. } | Self::AmomaxW { .. }
+            | Self::AmominuW { .. } | Self::AmomaxuW { .. }
+            | Self::Addw { .. } | Self::Subw { .. } | Self::Sllw { .. } | Self::Srlw { .. } | Self::Sraw { .. }
+            | Self::Mulw { .. } | Self::Divw { .. } | Self::Divuw { .. } | Self::Remw { .. } | Self::Remuw { .. }
+            | Self::LrD { .. } | Self::ScD { .. } | Self::AmoswapD { .. } | Self::AmoaddD { .. }
+            | Self::AmoandD { .. } | Self::AmoorD { .. } | Self::AmoxorD { .. }
+            | Self::AmominD { .. } | Self::AmomaxD { .. }
+            | Self::AmominuD { .. } | Self::AmomaxuD { .. }
+            | Self::Sh1add { .. } | Self::Sh2add { .. } | Self::Sh3add { .. }
+            | Self::Andn { .. } | Self::Orn { .. } | Self::Xnor { .. }
+            | Self::Max { .. } | Self::Maxu { .. } | Self::Min { .. } | Self::Minu { .. }
+            | Self::ZextH { .. } | Self::Rol { .. } | Self::Ror { .. }
+            | Self::Bclr { .. } | Self::Bext { .. } | Self::Binv { .. } | Self::Bset { .. }
+            | Self::Sha512Sig0h { .. } | Self::Sha512Sig0l { .. } | Self::Sha512Sig1h { .. }
+            | Self::Sha512Sig1l { .. } | Self::Sha512Sum0r { .. } | Self::Sha512Sum1r { .. }
+            | Self::Aes32esi { .. } | Self::Aes32esmi { .. }
+            | Self::Aes32dsi { .. } | Self::Aes32dsmi { .. }
+            | Self::Fadd { .. } | Self::Fsub { .. } | Self::Fmul { .. } | Self::Fdiv { .. } | Self::Fsqrt { .. }
+            | Self::Fsgnj { .. } | Self::Fsgnjn { .. } | Self::Fsgnjx { .. } | Self::Fmin { .. } | Self::Fmax { .. }
+            | Self::Feq { .. } | Self::Flt { .. } | Self::Fle { .. } | Self::Fclass { .. }
+            | Self::FcvtW { .. } | Self::FcvtWu { .. } | Self::FcvtFW { .. } | Self::FcvtFWu { .. }
+            | Self::FcvtSD { .. } | Self::FcvtDS { .. } | Self::FmvXW { .. } | Self::FmvWX { .. }
+            | Self::FcvtL { .. } | Self::FcvtLu { .. } | Self::FcvtFL { .. } | Self::FcvtFLu { .. }
+            | Self::FmvXD { .. } | Self::FmvDX { .. } => InstructionFormat::R,
+
+            Self::Fmadd { .. } | Self::Fmsub { .. } | Self::Fnmsub { .. } | Self::Fnmadd { .. } => InstructionFormat::R4,
 
             Self::Addi { .. } | Self::Slti { .. } | Self::Sltiu { .. }
             | Self::Andi { .. } | Self::Ori { .. } | Self::Xori { .. }
             | Self::Slli { .. } | Self::Srli { .. } | Self::Srai { .. }
+            | Self::Addiw { .. } | Self::Slliw { .. } | Self::Srliw { .. } | Self::Sraiw { .. }
+            | Self::Clz { .. } | Self::Ctz { .. } | Self::Cpop { .. }
+            | Self::SextB { .. } | Self::SextH { .. } | Self::Rori { .. }
+            | Self::OrcB { .. } | Self::Rev8 { .. }
+            | Self::Bclri { .. } | Self::Bexti { .. } | Self::Binvi { .. } | Self::Bseti { .. }
+            | Self::Sha256Sig0 { .. } | Self::Sha256Sig1 { .. } | Self::Sha256Sum0 { .. } | Self::Sha256Sum1 { .. }
             | Self::Lb { .. } | Self::Lh { .. } | Self::Lw { .. }
-            | Self::Lbu { .. } | Self::Lhu { .. }
+            | Self::Lbu { .. } | Self::Lhu { .. } | Self::Lwu { .. } | Self::Ld { .. }
+            | Self::Flw { .. } | Self::Fld { .. }
             | Self::Jalr { .. } => InstructionFormat::I,
 
-            Self::Sb { .. } | Self::Sh { .. } | Self::Sw { .. } => InstructionFormat::S,
+            Self::Sb { .. } | Self::Sh { .. } | Self::Sw { .. } | Self::Sd { .. }
+            | Self::Fsw { .. } | Self::Fsd { .. } => InstructionFormat::S,
 
             Self::Beq { .. } | Self::Bne { .. } | Self::Blt { .. }
             | Self::Bge { .. } | Self::Bltu { .. } | Self::Bgeu { .. } => InstructionFormat::B,
@@ -96,7 +463,10 @@ impl Instruction {
 
             Self::Jal { .. } => InstructionFormat::J,
 
-            Self::Ecall | Self::Ebreak => InstructionFormat::I,
+            Self::Fence { .. } | Self::FenceI
+            | Self::Ecall | Self::Ebreak | Self::Mret
+            | Self::Csrrw { .. } | Self::Csrrs { .. } | Self::Csrrc { .. }
+            | Self::Csrrwi { .. } | Self::Csrrsi { .. } | Self::Csrrci { .. } => InstructionFormat::I,
         }
     }
 }
\ No newline at end of file
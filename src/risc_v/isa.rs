@@ -2,4 +2,267 @@
 
 pub use crate::risc_v::instructions::{Instruction, InstructionFormat};
 pub use crate::risc_v::registers::{RegisterIndex, RegisterFile};
-pub use crate::risc_v::decoder::Decoder;
\ No newline at end of file
+pub use crate::risc_v::decoder::Decoder;
+
+use crate::utils::VmError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Native register width of the base integer ISA
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Xlen {
+    /// 32-bit registers (RV32I)
+    #[default]
+    Rv32,
+    /// 64-bit registers (RV64I)
+    Rv64,
+}
+
+impl Xlen {
+    /// Register width in bits
+    pub fn bits(self) -> u32 {
+        match self {
+            Self::Rv32 => 32,
+            Self::Rv64 => 64,
+        }
+    }
+
+    /// Mask selecting the low XLEN bits of a value
+    pub fn mask(self) -> u64 {
+        u64::MAX >> (64 - self.bits())
+    }
+}
+
+/// ISA extensions that can be enabled or disabled at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Extension {
+    /// Base integer instruction set
+    I,
+    /// Integer multiplication and division
+    M,
+    /// Atomic instructions
+    A,
+    /// Single-precision floating point
+    F,
+    /// Double-precision floating point
+    D,
+    /// Compressed instructions
+    C,
+    /// Control and status register instructions
+    Zicsr,
+    /// Address generation bit manipulation
+    Zba,
+    /// Basic bit manipulation
+    Zbb,
+    /// Single-bit instructions
+    Zbs,
+    /// AES decryption instructions
+    Zknd,
+    /// AES encryption instructions
+    Zkne,
+    /// SHA-2 hash function instructions
+    Zknh,
+}
+
+impl Extension {
+    /// All extensions in canonical ISA string order
+    pub const ALL: [Extension; 13] = [
+        Self::I, Self::M, Self::A, Self::F, Self::D, Self::C,
+        Self::Zicsr, Self::Zba, Self::Zbb, Self::Zbs, Self::Zknd, Self::Zkne, Self::Zknh,
+    ];
+
+    /// Lowercase name as it appears in an ISA string
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::I => "i",
+            Self::M => "m",
+            Self::A => "a",
+            Self::F => "f",
+            Self::D => "d",
+            Self::C => "c",
+            Self::Zicsr => "zicsr",
+            Self::Zba => "zba",
+            Self::Zbb => "zbb",
+            Self::Zbs => "zbs",
+            Self::Zknd => "zknd",
+            Self::Zkne => "zkne",
+            Self::Zknh => "zknh",
+        }
+    }
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    /// Check whether the extension is implemented for `xlen`; the
+    /// bit-manipulation and scalar crypto extensions are RV32-only
+    pub fn supports(self, xlen: Xlen) -> bool {
+        xlen == Xlen::Rv32 || matches!(self, Self::I | Self::M | Self::A | Self::F | Self::D | Self::C | Self::Zicsr)
+    }
+}
+
+/// Register width and set of enabled ISA extensions, parsed from and
+/// displayed as an ISA string such as `rv32imac_zicsr` or `rv64imac`
+///
+/// Instructions from disabled extensions, and RV64-only instructions on RV32,
+/// decode as illegal instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IsaConfig {
+    xlen: Xlen,
+    extensions: u32,
+}
+
+impl IsaConfig {
+    /// Every extension the VM implements for `xlen`
+    pub fn full(xlen: Xlen) -> Self {
+        let mut isa = Self { xlen, extensions: 0 };
+        for ext in Extension::ALL.into_iter().filter(|ext| ext.supports(xlen)) {
+            isa.insert(ext);
+        }
+        isa
+    }
+
+    /// Register width of the base ISA
+    pub fn xlen(&self) -> Xlen {
+        self.xlen
+    }
+
+    /// Check whether an extension is enabled
+    pub fn has(&self, extension: Extension) -> bool {
+        self.extensions & extension.bit() != 0
+    }
+
+    /// Enable an extension, if it is implemented at this register width
+    pub fn enable(&mut self, extension: Extension) -> Result<(), VmError> {
+        if !extension.supports(self.xlen) {
+            return Err(VmError::InvalidIsa(format!("`{}` is not supported on RV64", extension.name())));
+        }
+        self.insert(extension);
+        Ok(())
+    }
+
+    fn insert(&mut self, extension: Extension) {
+        self.extensions |= extension.bit();
+    }
+
+    /// Disable an extension
+    pub fn disable(&mut self, extension: Extension) {
+        self.extensions &= !extension.bit();
+    }
+
+    /// Check whether an instruction belongs to an enabled extension and
+    /// exists at this register width
+    pub fn allows(&self, instruction: &Instruction) -> bool {
+        let extension = instruction.extension();
+        self.has(extension)
+            && extension.supports(self.xlen)
+            && (self.xlen == Xlen::Rv64 || !instruction.requires_rv64())
+    }
+
+    /// Enabled extensions in canonical order
+    pub fn extensions(&self) -> impl Iterator<Item = Extension> + '_ {
+        Extension::ALL.into_iter().filter(|&ext| self.has(ext))
+    }
+}
+
+impl Default for IsaConfig {
+    /// Every extension the VM implements on RV32
+    fn default() -> Self {
+        Self::full(Xlen::Rv32)
+    }
+}
+
+impl FromStr for IsaConfig {
+    type Err = VmError;
+
+    /// Parse an ISA string: `rv32` or `rv64`, the base `i` (or `g` for `imafd_zicsr`),
+    /// single-letter extensions, then `_`-separated multi-letter extensions
+    fn from_str(isa: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| VmError::InvalidIsa(format!("{}: {}", isa, reason));
+
+        let lower = isa.to_ascii_lowercase();
+        let (xlen, rest) = if let Some(rest) = lower.strip_prefix("rv32") {
+            (Xlen::Rv32, rest)
+        } else if let Some(rest) = lower.strip_prefix("rv64") {
+            (Xlen::Rv64, rest)
+        } else {
+            return Err(invalid("expected `rv32` or `rv64` prefix".to_string()));
+        };
+
+        // The first multi-letter extension may directly follow the single letters
+        let mut parts = rest.split('_');
+        let head = parts.next().unwrap_or_default();
+        let (letters, first) = match head.find('z') {
+            Some(i) => (&head[..i], Some(&head[i..])),
+            None => (head, None),
+        };
+
+        let mut config = Self { xlen, extensions: 0 };
+        let mut chars = letters.chars();
+        match chars.next() {
+            Some('i') => config.insert(Extension::I),
+            Some('g') => {
+                for ext in [Extension::I, Extension::M, Extension::A, Extension::F, Extension::D, Extension::Zicsr] {
+                    config.insert(ext);
+                }
+            },
+            Some('e') => return Err(invalid("RV32E/RV64E is not supported".to_string())),
+            _ => return Err(invalid("base ISA must be `i` or `g`".to_string())),
+        }
+
+        for letter in chars {
+            let ext = Extension::ALL[1..6]
+                .iter()
+                .find(|ext| ext.name().starts_with(letter))
+                .ok_or_else(|| invalid(format!("unsupported extension `{}`", letter)))?;
+            config.insert(*ext);
+        }
+
+        for name in first.into_iter().chain(parts) {
+            let ext = Extension::ALL[6..]
+                .iter()
+                .find(|ext| ext.name() == name)
+                .ok_or_else(|| invalid(format!("unsupported extension `{}`", name)))?;
+            config.insert(*ext);
+        }
+
+        if config.has(Extension::D) && !config.has(Extension::F) {
+            return Err(invalid("`d` requires `f`".to_string()));
+        }
+        if let Some(ext) = config.extensions().find(|ext| !ext.supports(xlen)) {
+            return Err(invalid(format!("`{}` is not supported on RV64", ext.name())));
+        }
+
+        Ok(config)
+    }
+}
+
+impl fmt::Display for IsaConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rv{}", self.xlen.bits())?;
+        for (i, ext) in self.extensions().enumerate() {
+            // Multi-letter extensions are separated by underscores
+            if ext.name().len() > 1 && i > 0 {
+                write!(f, "_")?;
+            }
+            write!(f, "{}", ext.name())?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for IsaConfig {
+    type Error = VmError;
+
+    fn try_from(isa: String) -> Result<Self, Self::Error> {
+        isa.parse()
+    }
+}
+
+impl From<IsaConfig> for String {
+    fn from(isa: IsaConfig) -> Self {
+        isa.to_string()
+    }
+}
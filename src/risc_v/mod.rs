@@ -3,8 +3,13 @@
 pub mod instructions;
 pub mod registers;
 pub mod decoder;
+pub mod encoder;
+pub mod disasm;
+pub mod asm;
 pub mod isa;
 
-pub use instructions::{Instruction, InstructionFormat};
-pub use registers::{RegisterIndex, RegisterFile};
-pub use decoder::Decoder;
\ No newline at end of file
+pub use instructions::{FloatFormat, Instruction, InstructionFormat, RoundingMode};
+pub use registers::{FloatRegisterFile, RegisterIndex, RegisterFile};
+pub use decoder::Decoder;
+pub use disasm::Disassembler;
+pub use asm::{Assembler, Program};
\ No newline at end of file
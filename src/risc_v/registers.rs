@@ -1,5 +1,24 @@
 //! RISC-V register definitions
 
+use crate::utils::VmError;
+use std::str::FromStr;
+
+/// ABI names of the integer registers, indexed by register number
+const ABI_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2",
+    "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7",
+    "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6",
+];
+
+/// ABI names of the floating-point registers, indexed by register number
+const FP_ABI_NAMES: [&str; 32] = [
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7",
+    "fs0", "fs1", "fa0", "fa1", "fa2", "fa3", "fa4", "fa5",
+    "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7",
+    "fs8", "fs9", "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
 /// RISC-V register indices
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -42,17 +61,79 @@ impl RegisterIndex {
     /// Convert a u32 to a register index
     pub fn from_u32(val: u32) -> Option<Self> {
         if val <= 31 {
-            Some(unsafe { std::mem::transmute(val as u8) })
+            Some(unsafe { std::mem::transmute::<u8, RegisterIndex>(val as u8) })
         } else {
             None
         }
     }
+
+    /// ABI name of the register (`zero`, `ra`, `sp`, `a0`, ...)
+    pub fn abi_name(self) -> &'static str {
+        ABI_NAMES[self as usize]
+    }
+
+    /// ABI name of the register when it names a floating-point register
+    /// (`ft0`, `fs0`, `fa0`, ...)
+    pub fn fp_abi_name(self) -> &'static str {
+        FP_ABI_NAMES[self as usize]
+    }
+
+    /// Parse a floating-point register name: `f0`-`f31` or an ABI name
+    pub fn parse_fp(name: &str) -> Result<Self, VmError> {
+        let index = FP_ABI_NAMES.iter().position(|&abi| abi == name).or_else(|| {
+            name.strip_prefix('f')
+                .filter(|n| n == &"0" || !n.starts_with('0'))
+                .and_then(|n| n.parse().ok())
+        });
+        index
+            .and_then(|i| Self::from_u32(i as u32))
+            .ok_or_else(|| VmError::InvalidRegisterName(name.to_string()))
+    }
+
+    /// Whether the register is an argument register (`a0`-`a7`)
+    pub fn is_argument(self) -> bool {
+        (10..=17).contains(&(self as u8))
+    }
+
+    /// Whether the calling convention requires a caller to save the register
+    /// across calls (`ra`, `t0`-`t6`, `a0`-`a7`)
+    pub fn is_caller_saved(self) -> bool {
+        matches!(self as u8, 1 | 5..=7 | 10..=17 | 28..=31)
+    }
+
+    /// Whether the calling convention requires a callee to preserve the
+    /// register (`sp`, `s0`-`s11`)
+    pub fn is_callee_saved(self) -> bool {
+        matches!(self as u8, 2 | 8 | 9 | 18..=27)
+    }
+}
+
+impl FromStr for RegisterIndex {
+    type Err = VmError;
+
+    /// Parse `x0`-`x31` or an ABI name, including the `fp` alias of `s0`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let index = match name {
+            "fp" => Some(8),
+            _ => ABI_NAMES.iter().position(|&abi| abi == name).or_else(|| {
+                name.strip_prefix('x')
+                    .filter(|n| n == &"0" || !n.starts_with('0'))
+                    .and_then(|n| n.parse().ok())
+            }),
+        };
+        index
+            .and_then(|i| Self::from_u32(i as u32))
+            .ok_or_else(|| VmError::InvalidRegisterName(name.to_string()))
+    }
 }
 
-/// Register file containing 32 general-purpose registers
+/// Register file containing 32 general-purpose registers, 64 bits wide
+///
+/// On RV32 only the low 32 bits are architecturally visible; 32-bit writes
+/// keep the upper half sign-extended so both widths compare the same way.
 #[derive(Debug, Clone)]
 pub struct RegisterFile {
-    registers: [u32; 32],
+    registers: [u64; 32],
 }
 
 impl RegisterFile {
@@ -63,16 +144,26 @@ impl RegisterFile {
         }
     }
 
-    /// Read a register value
+    /// Read the low 32 bits of a register
     pub fn read(&self, index: RegisterIndex) -> u32 {
+        self.read64(index) as u32
+    }
+
+    /// Write a 32-bit value to a register, sign-extending it to 64 bits
+    pub fn write(&mut self, index: RegisterIndex, value: u32) {
+        self.write64(index, value as i32 as u64);
+    }
+
+    /// Read the full 64-bit register value
+    pub fn read64(&self, index: RegisterIndex) -> u64 {
         match index {
             RegisterIndex::X0 => 0, // x0 is always 0
             _ => self.registers[index as usize],
         }
     }
 
-    /// Write a value to a register
-    pub fn write(&mut self, index: RegisterIndex, value: u32) {
+    /// Write a 64-bit value to a register
+    pub fn write64(&mut self, index: RegisterIndex, value: u64) {
         match index {
             RegisterIndex::X0 => {}, // x0 is always 0, writes are ignored
             _ => self.registers[index as usize] = value,
@@ -89,4 +180,42 @@ impl Default for RegisterFile {
     fn default() -> Self {
         Self::new()
     }
+}
+
+/// Floating-point register file containing 32 64-bit registers
+///
+/// Single-precision values are stored NaN-boxed in the low 32 bits.
+#[derive(Debug, Clone)]
+pub struct FloatRegisterFile {
+    registers: [u64; 32],
+}
+
+impl FloatRegisterFile {
+    /// Create a new register file with all registers initialized to 0
+    pub fn new() -> Self {
+        Self {
+            registers: [0; 32],
+        }
+    }
+
+    /// Read the raw bits of a register
+    pub fn read(&self, index: RegisterIndex) -> u64 {
+        self.registers[index as usize]
+    }
+
+    /// Write the raw bits of a register
+    pub fn write(&mut self, index: RegisterIndex, value: u64) {
+        self.registers[index as usize] = value;
+    }
+
+    /// Reset all registers to 0
+    pub fn reset(&mut self) {
+        self.registers = [0; 32];
+    }
+}
+
+impl Default for FloatRegisterFile {
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file
@@ -1,15 +1,50 @@
 //! Configuration management
 
+use crate::risc_v::isa::IsaConfig;
+use crate::utils::VmError;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VmConfig {
-    /// Memory size in bytes (default 4MB)
+    /// Limit on resident memory in bytes, allocated in 4 KiB pages on first write (default 4MB)
     pub memory_size: usize,
     /// Enable zero-knowledge proof generation
     pub enable_proofs: bool,
     /// Maximum number of execution cycles
     pub max_cycles: u64,
+    /// How exceptions raised by the guest are handled
+    pub trap_mode: TrapMode,
+    /// Enabled ISA extensions; instructions outside them are illegal. Physical
+    /// addresses are 32 bits at either XLEN, so RV64 guests address the low 4 GiB.
+    pub isa: IsaConfig,
+    /// How misaligned loads, stores and instruction fetches are handled
+    pub misaligned: MisalignedPolicy,
+}
+
+/// Exception handling policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrapMode {
+    /// Abort execution and report the fault to the host as a `VmError`
+    #[default]
+    Abort,
+    /// Trap into the guest handler at `mtvec`, setting `mepc`, `mcause` and `mtval`
+    Guest,
+}
+
+/// Policy for memory accesses that are not naturally aligned, and for
+/// instruction fetches not aligned to IALIGN (2 bytes with C, otherwise 4)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MisalignedPolicy {
+    /// Perform the access as a single unaligned access
+    #[default]
+    Allow,
+    /// Raise an address-misaligned exception
+    Trap,
+    /// Split the access into naturally aligned accesses of at most its width
+    Split,
 }
 
 impl Default for VmConfig {
@@ -18,6 +53,40 @@ impl Default for VmConfig {
             memory_size: 4 * 1024 * 1024, // 4MB
             enable_proofs: false,
             max_cycles: 1_000_000,
+            trap_mode: TrapMode::default(),
+            isa: IsaConfig::default(),
+            misaligned: MisalignedPolicy::default(),
+        }
+    }
+}
+
+impl VmConfig {
+    /// Override fields of this configuration with those set in a configuration
+    /// file. Files ending in `.toml` are parsed as TOML, anything else as JSON;
+    /// fields the file leaves out keep their current values.
+    pub fn with_file<P: AsRef<Path>>(self, path: P) -> Result<Self, VmError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let error = |message: String| VmError::ConfigError(format!("{}: {}", path.display(), message));
+
+        let overrides: serde_json::Value = if path.extension().is_some_and(|ext| ext == "toml") {
+            let table: toml::Table = toml::from_str(&text).map_err(|e| error(e.to_string()))?;
+            serde_json::to_value(table)?
+        } else {
+            serde_json::from_str(&text).map_err(|e| error(e.to_string()))?
+        };
+        let serde_json::Value::Object(overrides) = overrides else {
+            return Err(error("expected a table of settings".to_string()));
+        };
+
+        let mut merged = serde_json::to_value(self)?;
+        let fields = merged.as_object_mut().expect("VmConfig serializes as an object");
+        for (key, value) in overrides {
+            if !fields.contains_key(&key) {
+                return Err(error(format!("unknown setting `{}`", key)));
+            }
+            fields.insert(key, value);
         }
+        serde_json::from_value(merged).map_err(|e| error(e.to_string()))
     }
 }
\ No newline at end of file
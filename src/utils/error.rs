@@ -1,5 +1,6 @@
 //! Error handling for the ZK RISC-V VM
 
+use crate::vm::memory::AccessKind;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -10,9 +11,39 @@ pub enum VmError {
     #[error("Memory access violation at address {address:08x}")]
     MemoryViolation { address: u32 },
     
+    #[error("Access fault: {access} at address {address:08x}")]
+    AccessFault { address: u32, access: AccessKind },
+    
+    #[error("Misaligned {access} at address {address:08x}")]
+    MisalignedAccess { address: u32, access: AccessKind },
+    
+    #[error("Out of memory at address {address:08x}: limit of {limit} pages reached")]
+    OutOfMemory { address: u32, limit: usize },
+    
+    #[error("Device error: {0}")]
+    DeviceError(String),
+    
+    #[error("Invalid CSR access: {0:03x}")]
+    InvalidCsr(u16),
+    
+    #[error("Instruction encoding error: {0}")]
+    EncodingError(String),
+    
+    #[error("Assembly error on line {line}: {message}")]
+    AssemblyError { line: usize, message: String },
+    
     #[error("Invalid register index: {0}")]
     InvalidRegister(u32),
     
+    #[error("Invalid register name: {0}")]
+    InvalidRegisterName(String),
+    
+    #[error("Invalid ISA string: {0}")]
+    InvalidIsa(String),
+    
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+    
     #[error("ELF parsing error: {0}")]
     ElfError(String),
     
@@ -28,6 +59,9 @@ pub enum VmError {
     #[error("Verification failed: {0}")]
     VerificationError(String),
     
+    #[error("Breakpoint at {pc:08x}")]
+    Breakpoint { pc: u32 },
+    
     #[error("System call error: {0}")]
     SyscallError(String),
     
@@ -1,48 +1,183 @@
 //! CPU state management
 
-use crate::risc_v::{RegisterFile, RegisterIndex};
+use crate::risc_v::isa::Xlen;
+use crate::risc_v::{FloatFormat, FloatRegisterFile, RegisterFile, RegisterIndex};
+use crate::utils::VmError;
+use crate::vm::csr::{MSTATUS_MIE, MSTATUS_MPIE};
+use crate::vm::memory::AccessKind;
+use crate::vm::softfloat::Format;
+use crate::vm::{CsrFile, Trap};
 
 /// CPU state including registers and program counter
 #[derive(Debug, Clone)]
 pub struct CpuState {
     /// General-purpose registers
     pub registers: RegisterFile,
+    /// Floating-point registers
+    pub fp_registers: FloatRegisterFile,
     /// Program counter
     pub pc: u32,
     /// Cycle counter
     pub cycles: u64,
+    /// Number of instructions retired, excluding those that trapped
+    pub instret: u64,
+    /// Control and status registers
+    pub csrs: CsrFile,
+    /// Address reserved by the last LR.W/LR.D, cleared by SC.W/SC.D
+    pub reservation: Option<u32>,
+    /// Register width; RV64 programs still address the low 4 GiB
+    pub xlen: Xlen,
+    /// Code passed to the exit syscall, once the guest has exited
+    pub exit_code: Option<u32>,
 }
 
 impl CpuState {
-    /// Create a new CPU state with default values
+    /// Create a new RV32 CPU state with default values
     pub fn new() -> Self {
+        Self::with_xlen(Xlen::Rv32)
+    }
+
+    /// Create a new CPU state with the given register width
+    pub fn with_xlen(xlen: Xlen) -> Self {
         Self {
             registers: RegisterFile::new(),
+            fp_registers: FloatRegisterFile::new(),
             pc: 0,
             cycles: 0,
+            instret: 0,
+            csrs: CsrFile::new(),
+            reservation: None,
+            xlen,
+            exit_code: None,
         }
     }
 
     /// Reset the CPU state
     pub fn reset(&mut self) {
         self.registers.reset();
+        self.fp_registers.reset();
         self.pc = 0;
         self.cycles = 0;
+        self.instret = 0;
+        self.csrs.reset();
+        self.reservation = None;
+        self.exit_code = None;
     }
 
-    /// Read a register value
+    /// Read the low 32 bits of a register
     pub fn read_register(&self, index: RegisterIndex) -> u32 {
         self.registers.read(index)
     }
 
-    /// Write a value to a register
+    /// Write a 32-bit value to a register, sign-extending it on RV64
     pub fn write_register(&mut self, index: RegisterIndex, value: u32) {
         self.registers.write(index, value);
     }
 
+    /// Read an XLEN-wide register value, sign-extended to 64 bits on RV32
+    pub fn read_xreg(&self, index: RegisterIndex) -> u64 {
+        self.registers.read64(index)
+    }
+
+    /// Read an XLEN-wide register value, zero-extended to 64 bits on RV32
+    pub fn read_xreg_unsigned(&self, index: RegisterIndex) -> u64 {
+        match self.xlen {
+            Xlen::Rv32 => self.registers.read(index) as u64,
+            Xlen::Rv64 => self.registers.read64(index),
+        }
+    }
+
+    /// Write an XLEN-wide register value, truncating it to 32 bits on RV32
+    pub fn write_xreg(&mut self, index: RegisterIndex, value: u64) {
+        match self.xlen {
+            Xlen::Rv32 => self.registers.write(index, value as u32),
+            Xlen::Rv64 => self.registers.write64(index, value),
+        }
+    }
+
+    /// All integer registers, zero-extended from XLEN
+    pub fn xregs(&self) -> [u64; 32] {
+        std::array::from_fn(|i| self.read_xreg_unsigned(RegisterIndex::from_u32(i as u32).unwrap()))
+    }
+
+    /// Read the raw bits of a floating-point register
+    pub fn read_fp_register(&self, index: RegisterIndex) -> u64 {
+        self.fp_registers.read(index)
+    }
+
+    /// Write the raw bits of a floating-point register
+    pub fn write_fp_register(&mut self, index: RegisterIndex, value: u64) {
+        self.fp_registers.write(index, value);
+    }
+
+    /// Read a floating-point register as a value of format `fmt`; improperly
+    /// NaN-boxed single-precision values read as the canonical NaN
+    pub fn read_float(&self, fmt: FloatFormat, index: RegisterIndex) -> u64 {
+        Format::from(fmt).unbox(self.fp_registers.read(index))
+    }
+
+    /// Write a value of format `fmt` to a floating-point register, NaN-boxing
+    /// single-precision values
+    pub fn write_float(&mut self, fmt: FloatFormat, index: RegisterIndex, value: u64) {
+        self.fp_registers.write(index, Format::from(fmt).nan_box(value));
+    }
+
+    /// Read a control and status register
+    pub fn read_csr(&self, csr: u16) -> Result<u64, VmError> {
+        self.csrs.read(csr, self.xlen, self.cycles, self.instret)
+    }
+
+    /// Write a control and status register
+    pub fn write_csr(&mut self, csr: u16, value: u64) -> Result<(), VmError> {
+        self.csrs.write(csr, self.xlen, value)
+    }
+
+    /// Take a trap: record the faulting PC and cause, then jump to `mtvec`.
+    /// The caller checks that `mtvec` lies in the 32-bit physical address space.
+    pub fn enter_trap(&mut self, trap: Trap) {
+        let csrs = &mut self.csrs;
+        csrs.mepc = self.pc as u64;
+        csrs.mcause = trap.cause.code() as u64;
+        csrs.mtval = trap.tval as u64;
+
+        // Save and clear the interrupt-enable bit
+        let mie = csrs.mstatus & MSTATUS_MIE != 0;
+        csrs.mstatus &= !(MSTATUS_MIE | MSTATUS_MPIE);
+        if mie {
+            csrs.mstatus |= MSTATUS_MPIE;
+        }
+
+        let handler = csrs.mtvec as u32;
+        self.set_pc(handler);
+    }
+
+    /// Return from a machine-mode trap handler to `mepc`. A return address
+    /// outside the 32-bit physical address space raises an access fault.
+    pub fn return_from_trap(&mut self) -> Result<(), VmError> {
+        let mepc = u32::try_from(self.csrs.mepc)
+            .map_err(|_| VmError::AccessFault { address: self.csrs.mepc as u32, access: AccessKind::Execute })?;
+        let csrs = &mut self.csrs;
+
+        // Restore the interrupt-enable bit
+        let mpie = csrs.mstatus & MSTATUS_MPIE != 0;
+        csrs.mstatus &= !MSTATUS_MIE;
+        if mpie {
+            csrs.mstatus |= MSTATUS_MIE;
+        }
+        csrs.mstatus |= MSTATUS_MPIE;
+
+        self.set_pc(mepc);
+        Ok(())
+    }
+
     /// Increment the program counter by 4 (size of a RISC-V instruction)
     pub fn increment_pc(&mut self) {
-        self.pc = self.pc.wrapping_add(4);
+        self.advance_pc(4);
+    }
+
+    /// Advance the program counter past an instruction of `len` bytes (2 or 4)
+    pub fn advance_pc(&mut self, len: u32) {
+        self.pc = self.pc.wrapping_add(len);
         self.cycles += 1;
     }
 
@@ -61,6 +196,11 @@ impl CpuState {
     pub fn get_cycles(&self) -> u64 {
         self.cycles
     }
+
+    /// Get the number of instructions retired
+    pub fn get_instret(&self) -> u64 {
+        self.instret
+    }
 }
 
 impl Default for CpuState {
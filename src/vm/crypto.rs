@@ -0,0 +1,126 @@
+//! Scalar cryptography primitives for the Zknh, Zkne and Zknd extensions
+
+/// AES forward S-box
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+/// AES inverse S-box, derived from `SBOX`
+const INV_SBOX: [u8; 256] = {
+    let mut inv = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        inv[SBOX[i] as usize] = i as u8;
+        i += 1;
+    }
+    inv
+};
+
+/// SHA-256 σ0 message schedule function
+pub fn sha256_sig0(x: u32) -> u32 {
+    x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3)
+}
+
+/// SHA-256 σ1 message schedule function
+pub fn sha256_sig1(x: u32) -> u32 {
+    x.rotate_right(17) ^ x.rotate_right(19) ^ (x >> 10)
+}
+
+/// SHA-256 Σ0 compression function
+pub fn sha256_sum0(x: u32) -> u32 {
+    x.rotate_right(2) ^ x.rotate_right(13) ^ x.rotate_right(22)
+}
+
+/// SHA-256 Σ1 compression function
+pub fn sha256_sum1(x: u32) -> u32 {
+    x.rotate_right(6) ^ x.rotate_right(11) ^ x.rotate_right(25)
+}
+
+// The RV32 SHA-512 instructions each compute one 32-bit half of a 64-bit
+// sigma/sum function from the two halves of its input.
+
+/// High half of the SHA-512 σ0 message schedule function
+pub fn sha512_sig0h(rs1: u32, rs2: u32) -> u32 {
+    (rs1 >> 1) ^ (rs1 >> 7) ^ (rs1 >> 8) ^ (rs2 << 31) ^ (rs2 << 24)
+}
+
+/// Low half of the SHA-512 σ0 message schedule function
+pub fn sha512_sig0l(rs1: u32, rs2: u32) -> u32 {
+    (rs1 >> 1) ^ (rs1 >> 7) ^ (rs1 >> 8) ^ (rs2 << 31) ^ (rs2 << 25) ^ (rs2 << 24)
+}
+
+/// High half of the SHA-512 σ1 message schedule function
+pub fn sha512_sig1h(rs1: u32, rs2: u32) -> u32 {
+    (rs1 << 3) ^ (rs1 >> 6) ^ (rs1 >> 19) ^ (rs2 >> 29) ^ (rs2 << 13)
+}
+
+/// Low half of the SHA-512 σ1 message schedule function
+pub fn sha512_sig1l(rs1: u32, rs2: u32) -> u32 {
+    (rs1 << 3) ^ (rs1 >> 6) ^ (rs1 >> 19) ^ (rs2 >> 29) ^ (rs2 << 26) ^ (rs2 << 13)
+}
+
+/// Either half of the SHA-512 Σ0 compression function, given that half in `rs1`
+pub fn sha512_sum0r(rs1: u32, rs2: u32) -> u32 {
+    (rs1 << 25) ^ (rs1 << 30) ^ (rs1 >> 28) ^ (rs2 >> 7) ^ (rs2 >> 2) ^ (rs2 << 4)
+}
+
+/// Either half of the SHA-512 Σ1 compression function, given that half in `rs1`
+pub fn sha512_sum1r(rs1: u32, rs2: u32) -> u32 {
+    (rs1 << 23) ^ (rs1 >> 14) ^ (rs1 >> 18) ^ (rs2 >> 9) ^ (rs2 << 18) ^ (rs2 << 14)
+}
+
+/// Multiply two elements of GF(2^8) modulo the AES polynomial
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 };
+        b >>= 1;
+    }
+    product
+}
+
+/// One AES round step on byte `bs` of `rs2`, accumulated into `rs1`.
+///
+/// The byte is substituted (forward or inverse S-box), optionally expanded to
+/// its (Inv)MixColumns column contribution, rotated into row `bs` and XORed
+/// into `rs1`.
+pub fn aes32(rs1: u32, rs2: u32, bs: u32, decrypt: bool, mix: bool) -> u32 {
+    let shamt = bs * 8;
+    let si = (rs2 >> shamt) as u8;
+
+    let mixed = if decrypt {
+        let so = INV_SBOX[si as usize];
+        if mix {
+            u32::from_le_bytes([gf_mul(so, 0x0e), gf_mul(so, 0x09), gf_mul(so, 0x0d), gf_mul(so, 0x0b)])
+        } else {
+            so as u32
+        }
+    } else {
+        let so = SBOX[si as usize];
+        if mix {
+            u32::from_le_bytes([gf_mul(so, 2), so, so, gf_mul(so, 3)])
+        } else {
+            so as u32
+        }
+    };
+
+    rs1 ^ mixed.rotate_left(shamt)
+}
@@ -0,0 +1,166 @@
+//! Control and status registers (Zicsr)
+
+use crate::risc_v::isa::Xlen;
+use crate::utils::VmError;
+
+/// Floating-point accrued exception flags
+pub const FFLAGS: u16 = 0x001;
+/// Floating-point dynamic rounding mode
+pub const FRM: u16 = 0x002;
+/// Floating-point control and status register (`frm` and `fflags`)
+pub const FCSR: u16 = 0x003;
+
+/// Cycle counter (read-only shadow of the VM cycle count)
+pub const CYCLE: u16 = 0xc00;
+/// Timer (read-only; the VM has no wall clock, so it tracks cycles)
+pub const TIME: u16 = 0xc01;
+/// Instructions-retired counter (read-only)
+pub const INSTRET: u16 = 0xc02;
+/// Upper 32 bits of `cycle` (RV32 only)
+pub const CYCLEH: u16 = 0xc80;
+/// Upper 32 bits of `time` (RV32 only)
+pub const TIMEH: u16 = 0xc81;
+/// Upper 32 bits of `instret` (RV32 only)
+pub const INSTRETH: u16 = 0xc82;
+
+/// Vendor ID
+pub const MVENDORID: u16 = 0xf11;
+/// Architecture ID
+pub const MARCHID: u16 = 0xf12;
+/// Implementation ID
+pub const MIMPID: u16 = 0xf13;
+/// Hardware thread ID
+pub const MHARTID: u16 = 0xf14;
+
+/// Machine status register
+pub const MSTATUS: u16 = 0x300;
+/// Machine trap-handler base address
+pub const MTVEC: u16 = 0x305;
+/// Machine scratch register
+pub const MSCRATCH: u16 = 0x340;
+/// Machine exception program counter
+pub const MEPC: u16 = 0x341;
+/// Machine trap cause
+pub const MCAUSE: u16 = 0x342;
+/// Machine bad address or instruction
+pub const MTVAL: u16 = 0x343;
+
+/// `mstatus` machine interrupt-enable bit
+pub const MSTATUS_MIE: u32 = 1 << 3;
+/// `mstatus` previous interrupt-enable bit
+pub const MSTATUS_MPIE: u32 = 1 << 7;
+/// `mstatus` previous privilege mode (hardwired to machine mode)
+pub const MSTATUS_MPP: u32 = 0x3 << 11;
+
+/// Names of the CSRs implemented by the VM
+const CSR_NAMES: [(u16, &str); 19] = [
+    (FFLAGS, "fflags"),
+    (FRM, "frm"),
+    (FCSR, "fcsr"),
+    (CYCLE, "cycle"),
+    (TIME, "time"),
+    (INSTRET, "instret"),
+    (CYCLEH, "cycleh"),
+    (TIMEH, "timeh"),
+    (INSTRETH, "instreth"),
+    (MVENDORID, "mvendorid"),
+    (MARCHID, "marchid"),
+    (MIMPID, "mimpid"),
+    (MHARTID, "mhartid"),
+    (MSTATUS, "mstatus"),
+    (MTVEC, "mtvec"),
+    (MSCRATCH, "mscratch"),
+    (MEPC, "mepc"),
+    (MCAUSE, "mcause"),
+    (MTVAL, "mtval"),
+];
+
+/// Get the name of a CSR implemented by the VM
+pub fn csr_name(csr: u16) -> Option<&'static str> {
+    CSR_NAMES.iter().find(|(addr, _)| *addr == csr).map(|(_, name)| *name)
+}
+
+/// Look up a CSR address by name
+pub fn csr_address(name: &str) -> Option<u16> {
+    CSR_NAMES.iter().find(|(_, n)| *n == name).map(|(addr, _)| *addr)
+}
+
+/// Control and status register file. Address and scratch CSRs are XLEN
+/// wide; on RV32 only their low 32 bits are kept.
+#[derive(Debug, Clone, Default)]
+pub struct CsrFile {
+    /// Floating-point control and status (`frm` in bits 7:5, `fflags` in bits 4:0)
+    pub fcsr: u32,
+    /// Machine status register (only MIE and MPIE are writable)
+    pub mstatus: u32,
+    /// Machine trap-handler base address
+    pub mtvec: u64,
+    /// Machine scratch register
+    pub mscratch: u64,
+    /// Machine exception program counter
+    pub mepc: u64,
+    /// Machine trap cause
+    pub mcause: u64,
+    /// Machine bad address or instruction
+    pub mtval: u64,
+}
+
+impl CsrFile {
+    /// Create a new CSR file with all registers initialized to 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a CSR; the counter CSRs are derived from `cycles` and `instret`.
+    /// The upper-half counters only exist on RV32.
+    pub fn read(&self, csr: u16, xlen: Xlen, cycles: u64, instret: u64) -> Result<u64, VmError> {
+        let value = match csr {
+            FFLAGS => (self.fcsr & 0x1f) as u64,
+            FRM => (self.fcsr >> 5) as u64,
+            FCSR => self.fcsr as u64,
+            CYCLE | TIME => cycles,
+            INSTRET => instret,
+            CYCLEH | TIMEH if xlen == Xlen::Rv32 => cycles >> 32,
+            INSTRETH if xlen == Xlen::Rv32 => instret >> 32,
+            MVENDORID | MARCHID | MIMPID | MHARTID => 0,
+            MSTATUS => (self.mstatus | MSTATUS_MPP) as u64,
+            MTVEC => self.mtvec,
+            MSCRATCH => self.mscratch,
+            MEPC => self.mepc,
+            MCAUSE => self.mcause,
+            MTVAL => self.mtval,
+            _ => return Err(VmError::InvalidCsr(csr)),
+        };
+        Ok(value & xlen.mask())
+    }
+
+    /// Write a CSR; writes to read-only or unknown CSRs are rejected
+    pub fn write(&mut self, csr: u16, xlen: Xlen, value: u64) -> Result<(), VmError> {
+        // CSRs with address bits [11:10] set are read-only
+        if csr >> 10 == 0x3 {
+            return Err(VmError::InvalidCsr(csr));
+        }
+
+        let value = value & xlen.mask();
+        match csr {
+            FFLAGS => self.fcsr = (self.fcsr & !0x1f) | (value as u32 & 0x1f),
+            FRM => self.fcsr = (self.fcsr & 0x1f) | (value as u32 & 0x7) << 5,
+            FCSR => self.fcsr = value as u32 & 0xff,
+            MSTATUS => self.mstatus = value as u32 & (MSTATUS_MIE | MSTATUS_MPIE),
+            // Only direct mode is supported, so the mode bits are ignored
+            MTVEC => self.mtvec = value & !0x3,
+            MSCRATCH => self.mscratch = value,
+            // mepc[0] is always zero since instructions are 2-byte aligned
+            MEPC => self.mepc = value & !0x1,
+            MCAUSE => self.mcause = value,
+            MTVAL => self.mtval = value,
+            _ => return Err(VmError::InvalidCsr(csr)),
+        }
+        Ok(())
+    }
+
+    /// Reset all CSRs to 0
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
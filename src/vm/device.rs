@@ -0,0 +1,20 @@
+//! Memory-mapped I/O devices
+
+use crate::utils::VmError;
+use std::sync::{Arc, Mutex};
+
+/// A host-side device attached to a range of guest physical addresses.
+///
+/// Loads and stores that fall inside the range are forwarded to the device
+/// instead of RAM. Accesses are 1, 2, 4 or 8 bytes wide and must lie
+/// entirely within the range; `offset` is relative to the range base.
+pub trait Device: Send {
+    /// Handle a load of `size` bytes, returning the value zero-extended to 64 bits
+    fn read(&mut self, offset: u32, size: u8) -> Result<u64, VmError>;
+
+    /// Handle a store of the low `size` bytes of `value`
+    fn write(&mut self, offset: u32, size: u8, value: u64) -> Result<(), VmError>;
+}
+
+/// A device shared between the memory bus and the host
+pub type SharedDevice = Arc<Mutex<dyn Device>>;
@@ -1,242 +1,498 @@
 //! Instruction execution engine
 
-use crate::risc_v::{Instruction, Decoder};
-use crate::vm::{CpuState, Memory, SyscallHandler};
+use crate::risc_v::isa::{Extension, Xlen};
+use crate::risc_v::{FloatFormat, Instruction, Decoder, RegisterIndex, RoundingMode};
+use crate::vm::memory::AccessKind;
+use crate::vm::softfloat::{self, SoftFloat, DOUBLE, SINGLE};
+use crate::vm::{crypto, CpuState, Memory, SyscallHandler, Trap};
 use crate::utils::VmError;
+use crate::utils::config::{MisalignedPolicy, TrapMode, VmConfig};
 
 /// Instruction executor
 pub struct Executor;
 
 impl Executor {
-    /// Execute a single instruction
+    /// Execute a single instruction of `len` bytes (4, or 2 if compressed)
     pub fn execute_instruction(
         instruction: &Instruction,
+        len: u32,
         cpu: &mut CpuState,
         memory: &mut Memory,
+        config: &VmConfig,
     ) -> Result<bool, VmError> {
         match instruction {
-            // Arithmetic operations
+            // Arithmetic operations; registers hold XLEN-wide values, which are
+            // sign-extended to 64 bits on RV32 so comparisons work at either width
             Instruction::Add { rd, rs1, rs2 } => {
-                let val1 = cpu.read_register(*rs1);
-                let val2 = cpu.read_register(*rs2);
-                cpu.write_register(*rd, val1.wrapping_add(val2));
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
+                cpu.write_xreg(*rd, val1.wrapping_add(val2));
             },
 
             Instruction::Sub { rd, rs1, rs2 } => {
-                let val1 = cpu.read_register(*rs1);
-                let val2 = cpu.read_register(*rs2);
-                cpu.write_register(*rd, val1.wrapping_sub(val2));
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
+                cpu.write_xreg(*rd, val1.wrapping_sub(val2));
             },
 
             Instruction::Slt { rd, rs1, rs2 } => {
-                let val1 = cpu.read_register(*rs1) as i32;
-                let val2 = cpu.read_register(*rs2) as i32;
-                cpu.write_register(*rd, if val1 < val2 { 1 } else { 0 });
+                let val1 = cpu.read_xreg(*rs1) as i64;
+                let val2 = cpu.read_xreg(*rs2) as i64;
+                cpu.write_xreg(*rd, if val1 < val2 { 1 } else { 0 });
             },
 
             Instruction::Sltu { rd, rs1, rs2 } => {
-                let val1 = cpu.read_register(*rs1);
-                let val2 = cpu.read_register(*rs2);
-                cpu.write_register(*rd, if val1 < val2 { 1 } else { 0 });
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
+                cpu.write_xreg(*rd, if val1 < val2 { 1 } else { 0 });
             },
 
             Instruction::And { rd, rs1, rs2 } => {
-                let val1 = cpu.read_register(*rs1);
-                let val2 = cpu.read_register(*rs2);
-                cpu.write_register(*rd, val1 & val2);
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
+                cpu.write_xreg(*rd, val1 & val2);
             },
 
             Instruction::Or { rd, rs1, rs2 } => {
-                let val1 = cpu.read_register(*rs1);
-                let val2 = cpu.read_register(*rs2);
-                cpu.write_register(*rd, val1 | val2);
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
+                cpu.write_xreg(*rd, val1 | val2);
             },
 
             Instruction::Xor { rd, rs1, rs2 } => {
-                let val1 = cpu.read_register(*rs1);
-                let val2 = cpu.read_register(*rs2);
-                cpu.write_register(*rd, val1 ^ val2);
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
+                cpu.write_xreg(*rd, val1 ^ val2);
             },
 
             Instruction::Sll { rd, rs1, rs2 } => {
-                let val1 = cpu.read_register(*rs1);
-                let shift = cpu.read_register(*rs2) & 0x1f;
-                cpu.write_register(*rd, val1 << shift);
+                let val1 = cpu.read_xreg(*rs1);
+                let shift = cpu.read_register(*rs2) & Self::shift_mask(cpu);
+                cpu.write_xreg(*rd, val1 << shift);
             },
 
             Instruction::Srl { rd, rs1, rs2 } => {
-                let val1 = cpu.read_register(*rs1);
-                let shift = cpu.read_register(*rs2) & 0x1f;
-                cpu.write_register(*rd, val1 >> shift);
+                let val1 = cpu.read_xreg_unsigned(*rs1);
+                let shift = cpu.read_register(*rs2) & Self::shift_mask(cpu);
+                cpu.write_xreg(*rd, val1 >> shift);
             },
 
             Instruction::Sra { rd, rs1, rs2 } => {
-                let val1 = cpu.read_register(*rs1) as i32;
-                let shift = cpu.read_register(*rs2) & 0x1f;
-                cpu.write_register(*rd, (val1 >> shift) as u32);
+                let val1 = cpu.read_xreg(*rs1) as i64;
+                let shift = cpu.read_register(*rs2) & Self::shift_mask(cpu);
+                cpu.write_xreg(*rd, (val1 >> shift) as u64);
+            },
+
+            // Multiply/divide operations (M extension)
+            Instruction::Mul { rd, rs1, rs2 } => {
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
+                cpu.write_xreg(*rd, val1.wrapping_mul(val2));
+            },
+
+            // The high multiplications compute the full 2*XLEN-bit product
+            Instruction::Mulh { rd, rs1, rs2 } => {
+                let val1 = cpu.read_xreg(*rs1) as i64 as i128;
+                let val2 = cpu.read_xreg(*rs2) as i64 as i128;
+                cpu.write_xreg(*rd, ((val1 * val2) >> cpu.xlen.bits()) as u64);
+            },
+
+            Instruction::Mulhsu { rd, rs1, rs2 } => {
+                let val1 = cpu.read_xreg(*rs1) as i64 as i128;
+                let val2 = cpu.read_xreg_unsigned(*rs2) as i128;
+                cpu.write_xreg(*rd, ((val1 * val2) >> cpu.xlen.bits()) as u64);
+            },
+
+            Instruction::Mulhu { rd, rs1, rs2 } => {
+                let val1 = cpu.read_xreg_unsigned(*rs1) as u128;
+                let val2 = cpu.read_xreg_unsigned(*rs2) as u128;
+                cpu.write_xreg(*rd, ((val1 * val2) >> cpu.xlen.bits()) as u64);
+            },
+
+            Instruction::Div { rd, rs1, rs2 } => {
+                let val1 = cpu.read_xreg(*rs1) as i64;
+                let val2 = cpu.read_xreg(*rs2) as i64;
+                // Division by zero yields -1; overflow (MIN / -1) yields MIN
+                let result = if val2 == 0 { -1 } else { val1.wrapping_div(val2) };
+                cpu.write_xreg(*rd, result as u64);
+            },
+
+            Instruction::Divu { rd, rs1, rs2 } => {
+                let val1 = cpu.read_xreg_unsigned(*rs1);
+                let val2 = cpu.read_xreg_unsigned(*rs2);
+                let result = val1.checked_div(val2).unwrap_or(u64::MAX);
+                cpu.write_xreg(*rd, result);
+            },
+
+            Instruction::Rem { rd, rs1, rs2 } => {
+                let val1 = cpu.read_xreg(*rs1) as i64;
+                let val2 = cpu.read_xreg(*rs2) as i64;
+                // Remainder by zero yields the dividend; overflow (MIN % -1) yields 0
+                let result = if val2 == 0 { val1 } else { val1.wrapping_rem(val2) };
+                cpu.write_xreg(*rd, result as u64);
+            },
+
+            Instruction::Remu { rd, rs1, rs2 } => {
+                let val1 = cpu.read_xreg_unsigned(*rs1);
+                let val2 = cpu.read_xreg_unsigned(*rs2);
+                let result = val1.checked_rem(val2).unwrap_or(val1);
+                cpu.write_xreg(*rd, result);
+            },
+
+            // Word operations (RV64) compute on the low 32 bits; writing the
+            // 32-bit result sign-extends it
+            Instruction::Addw { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, u32::wrapping_add),
+            Instruction::Subw { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, u32::wrapping_sub),
+            Instruction::Sllw { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a << (b & 0x1f)),
+            Instruction::Srlw { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a >> (b & 0x1f)),
+            Instruction::Sraw { rd, rs1, rs2 } => {
+                Self::binary(cpu, *rd, *rs1, *rs2, |a, b| ((a as i32) >> (b & 0x1f)) as u32)
+            },
+            Instruction::Mulw { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, u32::wrapping_mul),
+            Instruction::Divw { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| {
+                if b == 0 { u32::MAX } else { (a as i32).wrapping_div(b as i32) as u32 }
+            }),
+            Instruction::Divuw { rd, rs1, rs2 } => {
+                Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a.checked_div(b).unwrap_or(u32::MAX))
+            },
+            Instruction::Remw { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| {
+                if b == 0 { a } else { (a as i32).wrapping_rem(b as i32) as u32 }
+            }),
+            Instruction::Remuw { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a.checked_rem(b).unwrap_or(a)),
+            Instruction::Addiw { rd, rs1, imm } => Self::unary(cpu, *rd, *rs1, |a| a.wrapping_add(*imm as u32)),
+            Instruction::Slliw { rd, rs1, shamt } => Self::unary(cpu, *rd, *rs1, |a| a << shamt),
+            Instruction::Srliw { rd, rs1, shamt } => Self::unary(cpu, *rd, *rs1, |a| a >> shamt),
+            Instruction::Sraiw { rd, rs1, shamt } => Self::unary(cpu, *rd, *rs1, |a| ((a as i32) >> shamt) as u32),
+
+            // Atomic operations
+            Instruction::LrW { rd, rs1 } => {
+                let addr = Self::address(cpu, *rs1, 0, AccessKind::Read)?;
+                let val = memory.load_word(addr)?;
+                cpu.reservation = Some(addr);
+                cpu.write_register(*rd, val);
+            },
+
+            Instruction::ScW { rd, rs1, rs2 } => {
+                let addr = Self::address(cpu, *rs1, 0, AccessKind::Write)?;
+                // SC.W succeeds only on the reserved address; either way the reservation is consumed
+                if cpu.reservation.take() == Some(addr) {
+                    memory.store_word(addr, cpu.read_register(*rs2))?;
+                    cpu.write_register(*rd, 0);
+                } else {
+                    cpu.write_register(*rd, 1);
+                }
+            },
+
+            Instruction::AmoswapW { rd, rs1, rs2 } => Self::amo(cpu, memory, *rd, *rs1, *rs2, |_, b| b)?,
+            Instruction::AmoaddW { rd, rs1, rs2 } => Self::amo(cpu, memory, *rd, *rs1, *rs2, u32::wrapping_add)?,
+            Instruction::AmoandW { rd, rs1, rs2 } => Self::amo(cpu, memory, *rd, *rs1, *rs2, |a, b| a & b)?,
+            Instruction::AmoorW { rd, rs1, rs2 } => Self::amo(cpu, memory, *rd, *rs1, *rs2, |a, b| a | b)?,
+            Instruction::AmoxorW { rd, rs1, rs2 } => Self::amo(cpu, memory, *rd, *rs1, *rs2, |a, b| a ^ b)?,
+            Instruction::AmominW { rd, rs1, rs2 } => {
+                Self::amo(cpu, memory, *rd, *rs1, *rs2, |a, b| (a as i32).min(b as i32) as u32)?
+            },
+            Instruction::AmomaxW { rd, rs1, rs2 } => {
+                Self::amo(cpu, memory, *rd, *rs1, *rs2, |a, b| (a as i32).max(b as i32) as u32)?
+            },
+            Instruction::AmominuW { rd, rs1, rs2 } => Self::amo(cpu, memory, *rd, *rs1, *rs2, u32::min)?,
+            Instruction::AmomaxuW { rd, rs1, rs2 } => Self::amo(cpu, memory, *rd, *rs1, *rs2, u32::max)?,
+
+            // Double-word atomic operations (RV64A)
+            Instruction::LrD { rd, rs1 } => {
+                let addr = Self::address(cpu, *rs1, 0, AccessKind::Read)?;
+                let val = memory.load_doubleword(addr)?;
+                cpu.reservation = Some(addr);
+                cpu.write_xreg(*rd, val);
+            },
+
+            Instruction::ScD { rd, rs1, rs2 } => {
+                let addr = Self::address(cpu, *rs1, 0, AccessKind::Write)?;
+                if cpu.reservation.take() == Some(addr) {
+                    memory.store_doubleword(addr, cpu.read_xreg(*rs2))?;
+                    cpu.write_xreg(*rd, 0);
+                } else {
+                    cpu.write_xreg(*rd, 1);
+                }
+            },
+
+            Instruction::AmoswapD { rd, rs1, rs2 } => Self::amo_double(cpu, memory, *rd, *rs1, *rs2, |_, b| b)?,
+            Instruction::AmoaddD { rd, rs1, rs2 } => Self::amo_double(cpu, memory, *rd, *rs1, *rs2, u64::wrapping_add)?,
+            Instruction::AmoandD { rd, rs1, rs2 } => Self::amo_double(cpu, memory, *rd, *rs1, *rs2, |a, b| a & b)?,
+            Instruction::AmoorD { rd, rs1, rs2 } => Self::amo_double(cpu, memory, *rd, *rs1, *rs2, |a, b| a | b)?,
+            Instruction::AmoxorD { rd, rs1, rs2 } => Self::amo_double(cpu, memory, *rd, *rs1, *rs2, |a, b| a ^ b)?,
+            Instruction::AmominD { rd, rs1, rs2 } => {
+                Self::amo_double(cpu, memory, *rd, *rs1, *rs2, |a, b| (a as i64).min(b as i64) as u64)?
+            },
+            Instruction::AmomaxD { rd, rs1, rs2 } => {
+                Self::amo_double(cpu, memory, *rd, *rs1, *rs2, |a, b| (a as i64).max(b as i64) as u64)?
+            },
+            Instruction::AmominuD { rd, rs1, rs2 } => Self::amo_double(cpu, memory, *rd, *rs1, *rs2, u64::min)?,
+            Instruction::AmomaxuD { rd, rs1, rs2 } => Self::amo_double(cpu, memory, *rd, *rs1, *rs2, u64::max)?,
+
+            // Address generation operations (Zba)
+            Instruction::Sh1add { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| (a << 1).wrapping_add(b)),
+            Instruction::Sh2add { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| (a << 2).wrapping_add(b)),
+            Instruction::Sh3add { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| (a << 3).wrapping_add(b)),
+
+            // Basic bit manipulation operations (Zbb)
+            Instruction::Andn { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a & !b),
+            Instruction::Orn { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a | !b),
+            Instruction::Xnor { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| !(a ^ b)),
+            Instruction::Clz { rd, rs1 } => Self::unary(cpu, *rd, *rs1, u32::leading_zeros),
+            Instruction::Ctz { rd, rs1 } => Self::unary(cpu, *rd, *rs1, u32::trailing_zeros),
+            Instruction::Cpop { rd, rs1 } => Self::unary(cpu, *rd, *rs1, u32::count_ones),
+            Instruction::Max { rd, rs1, rs2 } => {
+                Self::binary(cpu, *rd, *rs1, *rs2, |a, b| (a as i32).max(b as i32) as u32)
+            },
+            Instruction::Maxu { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, u32::max),
+            Instruction::Min { rd, rs1, rs2 } => {
+                Self::binary(cpu, *rd, *rs1, *rs2, |a, b| (a as i32).min(b as i32) as u32)
+            },
+            Instruction::Minu { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, u32::min),
+            Instruction::SextB { rd, rs1 } => Self::unary(cpu, *rd, *rs1, |a| a as i8 as i32 as u32),
+            Instruction::SextH { rd, rs1 } => Self::unary(cpu, *rd, *rs1, |a| a as i16 as i32 as u32),
+            Instruction::ZextH { rd, rs1 } => Self::unary(cpu, *rd, *rs1, |a| a & 0xffff),
+            Instruction::Rol { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a.rotate_left(b & 0x1f)),
+            Instruction::Ror { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a.rotate_right(b & 0x1f)),
+            Instruction::Rori { rd, rs1, shamt } => Self::unary(cpu, *rd, *rs1, |a| a.rotate_right(*shamt)),
+            Instruction::OrcB { rd, rs1 } => {
+                // Each byte becomes 0xff if any of its bits are set, otherwise 0x00
+                Self::unary(cpu, *rd, *rs1, |a| {
+                    u32::from_le_bytes(a.to_le_bytes().map(|b| if b != 0 { 0xff } else { 0 }))
+                })
+            },
+            Instruction::Rev8 { rd, rs1 } => Self::unary(cpu, *rd, *rs1, u32::swap_bytes),
+
+            // Single-bit operations (Zbs)
+            Instruction::Bclr { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a & !(1 << (b & 0x1f))),
+            Instruction::Bext { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| (a >> (b & 0x1f)) & 1),
+            Instruction::Binv { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a ^ (1 << (b & 0x1f))),
+            Instruction::Bset { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, |a, b| a | (1 << (b & 0x1f))),
+            Instruction::Bclri { rd, rs1, shamt } => Self::unary(cpu, *rd, *rs1, |a| a & !(1 << shamt)),
+            Instruction::Bexti { rd, rs1, shamt } => Self::unary(cpu, *rd, *rs1, |a| (a >> shamt) & 1),
+            Instruction::Binvi { rd, rs1, shamt } => Self::unary(cpu, *rd, *rs1, |a| a ^ (1 << shamt)),
+            Instruction::Bseti { rd, rs1, shamt } => Self::unary(cpu, *rd, *rs1, |a| a | (1 << shamt)),
+
+            // SHA-2 hash operations (Zknh)
+            Instruction::Sha256Sig0 { rd, rs1 } => Self::unary(cpu, *rd, *rs1, crypto::sha256_sig0),
+            Instruction::Sha256Sig1 { rd, rs1 } => Self::unary(cpu, *rd, *rs1, crypto::sha256_sig1),
+            Instruction::Sha256Sum0 { rd, rs1 } => Self::unary(cpu, *rd, *rs1, crypto::sha256_sum0),
+            Instruction::Sha256Sum1 { rd, rs1 } => Self::unary(cpu, *rd, *rs1, crypto::sha256_sum1),
+            Instruction::Sha512Sig0h { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, crypto::sha512_sig0h),
+            Instruction::Sha512Sig0l { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, crypto::sha512_sig0l),
+            Instruction::Sha512Sig1h { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, crypto::sha512_sig1h),
+            Instruction::Sha512Sig1l { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, crypto::sha512_sig1l),
+            Instruction::Sha512Sum0r { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, crypto::sha512_sum0r),
+            Instruction::Sha512Sum1r { rd, rs1, rs2 } => Self::binary(cpu, *rd, *rs1, *rs2, crypto::sha512_sum1r),
+
+            // AES round operations (Zkne, Zknd)
+            Instruction::Aes32esi { rd, rs1, rs2, bs } => {
+                Self::binary(cpu, *rd, *rs1, *rs2, |a, b| crypto::aes32(a, b, *bs, false, false))
+            },
+            Instruction::Aes32esmi { rd, rs1, rs2, bs } => {
+                Self::binary(cpu, *rd, *rs1, *rs2, |a, b| crypto::aes32(a, b, *bs, false, true))
+            },
+            Instruction::Aes32dsi { rd, rs1, rs2, bs } => {
+                Self::binary(cpu, *rd, *rs1, *rs2, |a, b| crypto::aes32(a, b, *bs, true, false))
+            },
+            Instruction::Aes32dsmi { rd, rs1, rs2, bs } => {
+                Self::binary(cpu, *rd, *rs1, *rs2, |a, b| crypto::aes32(a, b, *bs, true, true))
             },
 
             // Immediate arithmetic operations
             Instruction::Addi { rd, rs1, imm } => {
-                let val = cpu.read_register(*rs1);
-                cpu.write_register(*rd, val.wrapping_add(*imm as u32));
+                let val = cpu.read_xreg(*rs1);
+                cpu.write_xreg(*rd, val.wrapping_add(*imm as i64 as u64));
             },
 
             Instruction::Slti { rd, rs1, imm } => {
-                let val = cpu.read_register(*rs1) as i32;
-                cpu.write_register(*rd, if val < *imm { 1 } else { 0 });
+                let val = cpu.read_xreg(*rs1) as i64;
+                cpu.write_xreg(*rd, if val < *imm as i64 { 1 } else { 0 });
             },
 
             Instruction::Sltiu { rd, rs1, imm } => {
-                let val = cpu.read_register(*rs1);
-                cpu.write_register(*rd, if val < (*imm as u32) { 1 } else { 0 });
+                let val = cpu.read_xreg(*rs1);
+                cpu.write_xreg(*rd, if val < (*imm as i64 as u64) { 1 } else { 0 });
             },
 
             Instruction::Andi { rd, rs1, imm } => {
-                let val = cpu.read_register(*rs1);
-                cpu.write_register(*rd, val & (*imm as u32));
+                let val = cpu.read_xreg(*rs1);
+                cpu.write_xreg(*rd, val & (*imm as i64 as u64));
             },
 
             Instruction::Ori { rd, rs1, imm } => {
-                let val = cpu.read_register(*rs1);
-                cpu.write_register(*rd, val | (*imm as u32));
+                let val = cpu.read_xreg(*rs1);
+                cpu.write_xreg(*rd, val | (*imm as i64 as u64));
             },
 
             Instruction::Xori { rd, rs1, imm } => {
-                let val = cpu.read_register(*rs1);
-                cpu.write_register(*rd, val ^ (*imm as u32));
+                let val = cpu.read_xreg(*rs1);
+                cpu.write_xreg(*rd, val ^ (*imm as i64 as u64));
             },
 
             Instruction::Slli { rd, rs1, shamt } => {
-                let val = cpu.read_register(*rs1);
-                cpu.write_register(*rd, val << shamt);
+                let val = cpu.read_xreg(*rs1);
+                cpu.write_xreg(*rd, val << shamt);
             },
 
             Instruction::Srli { rd, rs1, shamt } => {
-                let val = cpu.read_register(*rs1);
-                cpu.write_register(*rd, val >> shamt);
+                let val = cpu.read_xreg_unsigned(*rs1);
+                cpu.write_xreg(*rd, val >> shamt);
             },
 
             Instruction::Srai { rd, rs1, shamt } => {
-                let val = cpu.read_register(*rs1) as i32;
-                cpu.write_register(*rd, (val >> shamt) as u32);
+                let val = cpu.read_xreg(*rs1) as i64;
+                cpu.write_xreg(*rd, (val >> shamt) as u64);
             },
 
-            // Load operations
+            // Load operations; writing a 32-bit value sign-extends it on RV64
             Instruction::Lb { rd, rs1, imm } => {
-                let addr = cpu.read_register(*rs1).wrapping_add(*imm as u32);
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Read)?;
                 let val = memory.load_byte(addr)? as i8 as i32 as u32;
                 cpu.write_register(*rd, val);
             },
 
             Instruction::Lh { rd, rs1, imm } => {
-                let addr = cpu.read_register(*rs1).wrapping_add(*imm as u32);
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Read)?;
                 let val = memory.load_halfword(addr)? as i16 as i32 as u32;
                 cpu.write_register(*rd, val);
             },
 
             Instruction::Lw { rd, rs1, imm } => {
-                let addr = cpu.read_register(*rs1).wrapping_add(*imm as u32);
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Read)?;
                 let val = memory.load_word(addr)?;
                 cpu.write_register(*rd, val);
             },
 
             Instruction::Lbu { rd, rs1, imm } => {
-                let addr = cpu.read_register(*rs1).wrapping_add(*imm as u32);
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Read)?;
                 let val = memory.load_byte(addr)? as u32;
                 cpu.write_register(*rd, val);
             },
 
             Instruction::Lhu { rd, rs1, imm } => {
-                let addr = cpu.read_register(*rs1).wrapping_add(*imm as u32);
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Read)?;
                 let val = memory.load_halfword(addr)? as u32;
                 cpu.write_register(*rd, val);
             },
 
+            Instruction::Lwu { rd, rs1, imm } => {
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Read)?;
+                let val = memory.load_word(addr)? as u64;
+                cpu.write_xreg(*rd, val);
+            },
+
+            Instruction::Ld { rd, rs1, imm } => {
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Read)?;
+                let val = memory.load_doubleword(addr)?;
+                cpu.write_xreg(*rd, val);
+            },
+
             // Store operations
             Instruction::Sb { rs1, rs2, imm } => {
-                let addr = cpu.read_register(*rs1).wrapping_add(*imm as u32);
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Write)?;
                 let val = cpu.read_register(*rs2) as u8;
                 memory.store_byte(addr, val)?;
             },
 
             Instruction::Sh { rs1, rs2, imm } => {
-                let addr = cpu.read_register(*rs1).wrapping_add(*imm as u32);
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Write)?;
                 let val = cpu.read_register(*rs2) as u16;
                 memory.store_halfword(addr, val)?;
             },
 
             Instruction::Sw { rs1, rs2, imm } => {
-                let addr = cpu.read_register(*rs1).wrapping_add(*imm as u32);
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Write)?;
                 let val = cpu.read_register(*rs2);
                 memory.store_word(addr, val)?;
             },
 
+            Instruction::Sd { rs1, rs2, imm } => {
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Write)?;
+                let val = cpu.read_xreg(*rs2);
+                memory.store_doubleword(addr, val)?;
+            },
+
             // Branch operations
             Instruction::Beq { rs1, rs2, imm } => {
-                let val1 = cpu.read_register(*rs1);
-                let val2 = cpu.read_register(*rs2);
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
                 if val1 == val2 {
-                    cpu.set_pc(cpu.get_pc().wrapping_add(*imm as u32));
+                    let target = cpu.get_pc().wrapping_add(*imm as u32);
+                    Self::check_target(target, memory, config)?;
+                    cpu.set_pc(target);
                     return Ok(false);
                 }
             },
 
             Instruction::Bne { rs1, rs2, imm } => {
-                let val1 = cpu.read_register(*rs1);
-                let val2 = cpu.read_register(*rs2);
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
                 if val1 != val2 {
-                    cpu.set_pc(cpu.get_pc().wrapping_add(*imm as u32));
+                    let target = cpu.get_pc().wrapping_add(*imm as u32);
+                    Self::check_target(target, memory, config)?;
+                    cpu.set_pc(target);
                     return Ok(false);
                 }
             },
 
             Instruction::Blt { rs1, rs2, imm } => {
-                let val1 = cpu.read_register(*rs1) as i32;
-                let val2 = cpu.read_register(*rs2) as i32;
+                let val1 = cpu.read_xreg(*rs1) as i64;
+                let val2 = cpu.read_xreg(*rs2) as i64;
                 if val1 < val2 {
-                    cpu.set_pc(cpu.get_pc().wrapping_add(*imm as u32));
+                    let target = cpu.get_pc().wrapping_add(*imm as u32);
+                    Self::check_target(target, memory, config)?;
+                    cpu.set_pc(target);
                     return Ok(false);
                 }
             },
 
             Instruction::Bge { rs1, rs2, imm } => {
-                let val1 = cpu.read_register(*rs1) as i32;
-                let val2 = cpu.read_register(*rs2) as i32;
+                let val1 = cpu.read_xreg(*rs1) as i64;
+                let val2 = cpu.read_xreg(*rs2) as i64;
                 if val1 >= val2 {
-                    cpu.set_pc(cpu.get_pc().wrapping_add(*imm as u32));
+                    let target = cpu.get_pc().wrapping_add(*imm as u32);
+                    Self::check_target(target, memory, config)?;
+                    cpu.set_pc(target);
                     return Ok(false);
                 }
             },
 
             Instruction::Bltu { rs1, rs2, imm } => {
-                let val1 = cpu.read_register(*rs1);
-                let val2 = cpu.read_register(*rs2);
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
                 if val1 < val2 {
-                    cpu.set_pc(cpu.get_pc().wrapping_add(*imm as u32));
+                    let target = cpu.get_pc().wrapping_add(*imm as u32);
+                    Self::check_target(target, memory, config)?;
+                    cpu.set_pc(target);
                     return Ok(false);
                 }
             },
 
             Instruction::Bgeu { rs1, rs2, imm } => {
-                let val1 = cpu.read_register(*rs1);
-                let val2 = cpu.read_register(*rs2);
+                let val1 = cpu.read_xreg(*rs1);
+                let val2 = cpu.read_xreg(*rs2);
                 if val1 >= val2 {
-                    cpu.set_pc(cpu.get_pc().wrapping_add(*imm as u32));
+                    let target = cpu.get_pc().wrapping_add(*imm as u32);
+                    Self::check_target(target, memory, config)?;
+                    cpu.set_pc(target);
                     return Ok(false);
                 }
             },
 
             // Jump operations
             Instruction::Jal { rd, imm } => {
-                cpu.write_register(*rd, cpu.get_pc() + 4);
-                cpu.set_pc(cpu.get_pc().wrapping_add(*imm as u32));
+                let target = cpu.get_pc().wrapping_add(*imm as u32);
+                Self::check_target(target, memory, config)?;
+                cpu.write_xreg(*rd, cpu.get_pc().wrapping_add(len) as u64);
+                cpu.set_pc(target);
                 return Ok(false);
             },
 
             Instruction::Jalr { rd, rs1, imm } => {
-                let target = cpu.read_register(*rs1).wrapping_add(*imm as u32) & !1;
-                cpu.write_register(*rd, cpu.get_pc() + 4);
+                let target = Self::address(cpu, *rs1, *imm, AccessKind::Execute)? & !1;
+                Self::check_target(target, memory, config)?;
+                cpu.write_xreg(*rd, cpu.get_pc().wrapping_add(len) as u64);
                 cpu.set_pc(target);
                 return Ok(false);
             },
@@ -247,36 +503,424 @@ impl Executor {
             },
 
             Instruction::Auipc { rd, imm } => {
-                cpu.write_register(*rd, cpu.get_pc().wrapping_add(*imm as u32));
+                cpu.write_xreg(*rd, (cpu.get_pc() as u64).wrapping_add(*imm as i64 as u64));
+            },
+
+            // Floating-point loads and stores
+            Instruction::Flw { rd, rs1, imm } => {
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Read)?;
+                let val = memory.load_word(addr)?;
+                cpu.write_float(FloatFormat::Single, *rd, val as u64);
+            },
+
+            Instruction::Fld { rd, rs1, imm } => {
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Read)?;
+                let val = memory.load_doubleword(addr)?;
+                cpu.write_float(FloatFormat::Double, *rd, val);
+            },
+
+            Instruction::Fsw { rs1, rs2, imm } => {
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Write)?;
+                // FSW stores the low bits unmodified, without checking the NaN-boxing
+                let val = cpu.read_fp_register(*rs2) as u32;
+                memory.store_word(addr, val)?;
+            },
+
+            Instruction::Fsd { rs1, rs2, imm } => {
+                let addr = Self::address(cpu, *rs1, *imm, AccessKind::Write)?;
+                let val = cpu.read_fp_register(*rs2);
+                memory.store_doubleword(addr, val)?;
+            },
+
+            // Floating-point arithmetic operations
+            Instruction::Fmadd { fmt, rd, rs1, rs2, rs3, rm }
+            | Instruction::Fmsub { fmt, rd, rs1, rs2, rs3, rm }
+            | Instruction::Fnmsub { fmt, rd, rs1, rs2, rs3, rm }
+            | Instruction::Fnmadd { fmt, rd, rs1, rs2, rs3, rm } => {
+                let negate_product = matches!(instruction, Instruction::Fnmsub { .. } | Instruction::Fnmadd { .. });
+                let negate_addend = matches!(instruction, Instruction::Fmsub { .. } | Instruction::Fnmadd { .. });
+                let result = Self::float(cpu, instruction, *fmt, *rm, |fpu, cpu| {
+                    let (a, b, c) = (cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2), cpu.read_float(*fmt, *rs3));
+                    fpu.fma(a, b, c, negate_product, negate_addend)
+                })?;
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            Instruction::Fadd { fmt, rd, rs1, rs2, rm } => {
+                let result = Self::float(cpu, instruction, *fmt, *rm, |fpu, cpu| {
+                    fpu.add(cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2))
+                })?;
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            Instruction::Fsub { fmt, rd, rs1, rs2, rm } => {
+                let result = Self::float(cpu, instruction, *fmt, *rm, |fpu, cpu| {
+                    fpu.sub(cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2))
+                })?;
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            Instruction::Fmul { fmt, rd, rs1, rs2, rm } => {
+                let result = Self::float(cpu, instruction, *fmt, *rm, |fpu, cpu| {
+                    fpu.mul(cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2))
+                })?;
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            Instruction::Fdiv { fmt, rd, rs1, rs2, rm } => {
+                let result = Self::float(cpu, instruction, *fmt, *rm, |fpu, cpu| {
+                    fpu.div(cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2))
+                })?;
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            Instruction::Fsqrt { fmt, rd, rs1, rm } => {
+                let result = Self::float(cpu, instruction, *fmt, *rm, |fpu, cpu| fpu.sqrt(cpu.read_float(*fmt, *rs1)))?;
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            // Sign injection only manipulates the sign bit and never raises exceptions
+            Instruction::Fsgnj { fmt, rd, rs1, rs2 }
+            | Instruction::Fsgnjn { fmt, rd, rs1, rs2 }
+            | Instruction::Fsgnjx { fmt, rd, rs1, rs2 } => {
+                let sign = softfloat::Format::from(*fmt).sign_mask();
+                let (a, b) = (cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2));
+                let result = match instruction {
+                    Instruction::Fsgnj { .. } => (a & !sign) | (b & sign),
+                    Instruction::Fsgnjn { .. } => (a & !sign) | (!b & sign),
+                    _ => a ^ (b & sign),
+                };
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            // The remaining operations do not round, so the rounding mode is unused
+            Instruction::Fmin { fmt, rd, rs1, rs2 } => {
+                let result = Self::float(cpu, instruction, *fmt, RoundingMode::Rne, |fpu, cpu| {
+                    fpu.min(cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2))
+                })?;
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            Instruction::Fmax { fmt, rd, rs1, rs2 } => {
+                let result = Self::float(cpu, instruction, *fmt, RoundingMode::Rne, |fpu, cpu| {
+                    fpu.max(cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2))
+                })?;
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            // Floating-point comparisons and classification
+            Instruction::Feq { fmt, rd, rs1, rs2 } => {
+                let result = Self::float(cpu, instruction, *fmt, RoundingMode::Rne, |fpu, cpu| {
+                    fpu.eq(cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2))
+                })?;
+                cpu.write_register(*rd, result as u32);
+            },
+
+            Instruction::Flt { fmt, rd, rs1, rs2 } => {
+                let result = Self::float(cpu, instruction, *fmt, RoundingMode::Rne, |fpu, cpu| {
+                    fpu.lt(cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2))
+                })?;
+                cpu.write_register(*rd, result as u32);
+            },
+
+            Instruction::Fle { fmt, rd, rs1, rs2 } => {
+                let result = Self::float(cpu, instruction, *fmt, RoundingMode::Rne, |fpu, cpu| {
+                    fpu.le(cpu.read_float(*fmt, *rs1), cpu.read_float(*fmt, *rs2))
+                })?;
+                cpu.write_register(*rd, result as u32);
+            },
+
+            Instruction::Fclass { fmt, rd, rs1 } => {
+                let fpu = SoftFloat::new((*fmt).into(), RoundingMode::Rne);
+                cpu.write_register(*rd, fpu.classify(cpu.read_float(*fmt, *rs1)));
+            },
+
+            // Floating-point conversions and moves
+            Instruction::FcvtW { fmt, rd, rs1, rm } | Instruction::FcvtWu { fmt, rd, rs1, rm } => {
+                let signed = matches!(instruction, Instruction::FcvtW { .. });
+                let result = Self::float(cpu, instruction, *fmt, *rm, |fpu, cpu| {
+                    fpu.to_int(cpu.read_float(*fmt, *rs1), signed)
+                })?;
+                cpu.write_register(*rd, result);
+            },
+
+            Instruction::FcvtFW { fmt, rd, rs1, rm } | Instruction::FcvtFWu { fmt, rd, rs1, rm } => {
+                let signed = matches!(instruction, Instruction::FcvtFW { .. });
+                let result = Self::float(cpu, instruction, *fmt, *rm, |fpu, cpu| {
+                    fpu.from_int(cpu.read_register(*rs1), signed)
+                })?;
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            Instruction::FcvtSD { rd, rs1, rm } => {
+                let result = Self::float(cpu, instruction, FloatFormat::Single, *rm, |fpu, cpu| {
+                    fpu.convert(cpu.read_float(FloatFormat::Double, *rs1), DOUBLE)
+                })?;
+                cpu.write_float(FloatFormat::Single, *rd, result);
+            },
+
+            Instruction::FcvtDS { rd, rs1, rm } => {
+                let result = Self::float(cpu, instruction, FloatFormat::Double, *rm, |fpu, cpu| {
+                    fpu.convert(cpu.read_float(FloatFormat::Single, *rs1), SINGLE)
+                })?;
+                cpu.write_float(FloatFormat::Double, *rd, result);
+            },
+
+            // Moves copy the raw bits, so no NaN-boxing check is made on FMV.X.W
+            Instruction::FmvXW { rd, rs1 } => {
+                cpu.write_register(*rd, cpu.read_fp_register(*rs1) as u32);
+            },
+
+            Instruction::FmvWX { rd, rs1 } => {
+                cpu.write_float(FloatFormat::Single, *rd, cpu.read_register(*rs1) as u64);
+            },
+
+            Instruction::FcvtL { fmt, rd, rs1, rm } | Instruction::FcvtLu { fmt, rd, rs1, rm } => {
+                let signed = matches!(instruction, Instruction::FcvtL { .. });
+                let result = Self::float(cpu, instruction, *fmt, *rm, |fpu, cpu| {
+                    fpu.to_long(cpu.read_float(*fmt, *rs1), signed)
+                })?;
+                cpu.write_xreg(*rd, result);
+            },
+
+            Instruction::FcvtFL { fmt, rd, rs1, rm } | Instruction::FcvtFLu { fmt, rd, rs1, rm } => {
+                let signed = matches!(instruction, Instruction::FcvtFL { .. });
+                let result = Self::float(cpu, instruction, *fmt, *rm, |fpu, cpu| {
+                    fpu.from_long(cpu.read_xreg(*rs1), signed)
+                })?;
+                cpu.write_float(*fmt, *rd, result);
+            },
+
+            Instruction::FmvXD { rd, rs1 } => {
+                cpu.write_xreg(*rd, cpu.read_fp_register(*rs1));
+            },
+
+            Instruction::FmvDX { rd, rs1 } => {
+                cpu.write_float(FloatFormat::Double, *rd, cpu.read_xreg(*rs1));
             },
 
             // System operations
+            // A single hart with no caches has nothing to order or flush
+            Instruction::Fence { .. } | Instruction::FenceI => {},
+
             Instruction::Ecall => {
-                return SyscallHandler::handle_syscall(cpu, memory);
+                // The exit syscall stops at the ECALL; others continue after it
+                if SyscallHandler::handle_syscall(cpu, memory)? {
+                    return Ok(true);
+                }
             },
 
             Instruction::Ebreak => {
-                return Err(VmError::ExecutionError("Breakpoint encountered".to_string()));
+                return Err(VmError::Breakpoint { pc: cpu.get_pc() });
+            },
+
+            Instruction::Mret => {
+                cpu.return_from_trap()?;
+                return Ok(false);
+            },
+
+            // Control and status register operations
+            Instruction::Csrrw { rd, rs1, csr } => {
+                let val = cpu.read_xreg(*rs1);
+                // Reads are skipped when rd is x0 so they have no side effects
+                let old = if *rd != RegisterIndex::X0 { cpu.read_csr(*csr)? } else { 0 };
+                cpu.write_csr(*csr, val)?;
+                cpu.write_xreg(*rd, old);
+            },
+
+            Instruction::Csrrs { rd, rs1, csr } => {
+                let old = cpu.read_csr(*csr)?;
+                // Writes are skipped when rs1 is x0 so read-only CSRs can be read
+                if *rs1 != RegisterIndex::X0 {
+                    cpu.write_csr(*csr, old | cpu.read_xreg(*rs1))?;
+                }
+                cpu.write_xreg(*rd, old);
+            },
+
+            Instruction::Csrrc { rd, rs1, csr } => {
+                let old = cpu.read_csr(*csr)?;
+                if *rs1 != RegisterIndex::X0 {
+                    cpu.write_csr(*csr, old & !cpu.read_xreg(*rs1))?;
+                }
+                cpu.write_xreg(*rd, old);
+            },
+
+            Instruction::Csrrwi { rd, uimm, csr } => {
+                let old = if *rd != RegisterIndex::X0 { cpu.read_csr(*csr)? } else { 0 };
+                cpu.write_csr(*csr, *uimm as u64)?;
+                cpu.write_xreg(*rd, old);
+            },
+
+            Instruction::Csrrsi { rd, uimm, csr } => {
+                let old = cpu.read_csr(*csr)?;
+                if *uimm != 0 {
+                    cpu.write_csr(*csr, old | *uimm as u64)?;
+                }
+                cpu.write_xreg(*rd, old);
+            },
+
+            Instruction::Csrrci { rd, uimm, csr } => {
+                let old = cpu.read_csr(*csr)?;
+                if *uimm != 0 {
+                    cpu.write_csr(*csr, old & !(*uimm as u64))?;
+                }
+                cpu.write_xreg(*rd, old);
             },
         }
 
-        // Normal execution continues - increment PC
-        cpu.increment_pc();
+        // Normal execution continues - advance PC past this instruction
+        cpu.advance_pc(len);
         Ok(false)
     }
 
+    /// Mask applied to register shift amounts: 5 bits on RV32, 6 on RV64
+    fn shift_mask(cpu: &CpuState) -> u32 {
+        cpu.xlen.bits() - 1
+    }
+
+    /// Check a jump or taken-branch target against IALIGN (32 bits without C,
+    /// 16 with it). Under `MisalignedPolicy::Trap` a misaligned target raises
+    /// the exception on the jump itself, before `rd` or the PC change.
+    fn check_target(target: u32, memory: &Memory, config: &VmConfig) -> Result<(), VmError> {
+        let ialign = if config.isa.has(Extension::C) { 2 } else { 4 };
+        if !target.is_multiple_of(ialign) && memory.misaligned_policy() == MisalignedPolicy::Trap {
+            return Err(VmError::MisalignedAccess { address: target, access: AccessKind::Execute });
+        }
+        Ok(())
+    }
+
+    /// Compute the effective address `rs1 + imm` of an `access`. On RV64 it must
+    /// lie in the 32-bit physical address space, or it raises an access fault;
+    /// on RV32 it wraps around.
+    fn address(cpu: &CpuState, rs1: RegisterIndex, imm: i32, access: AccessKind) -> Result<u32, VmError> {
+        let addr = cpu.read_xreg(rs1).wrapping_add(imm as i64 as u64);
+        match cpu.xlen {
+            Xlen::Rv32 => Ok(addr as u32),
+            Xlen::Rv64 => u32::try_from(addr).map_err(|_| VmError::AccessFault { address: addr as u32, access }),
+        }
+    }
+
+    /// Apply `op` to `rs1` and `rs2` and write the result to `rd`
+    fn binary(cpu: &mut CpuState, rd: RegisterIndex, rs1: RegisterIndex, rs2: RegisterIndex, op: impl Fn(u32, u32) -> u32) {
+        let result = op(cpu.read_register(rs1), cpu.read_register(rs2));
+        cpu.write_register(rd, result);
+    }
+
+    /// Apply `op` to `rs1` and write the result to `rd`
+    fn unary(cpu: &mut CpuState, rd: RegisterIndex, rs1: RegisterIndex, op: impl Fn(u32) -> u32) {
+        let result = op(cpu.read_register(rs1));
+        cpu.write_register(rd, result);
+    }
+
+    /// Run a floating-point `op` in format `fmt`, resolving a dynamic rounding
+    /// mode from `frm` and accruing the exception flags it raises in `fflags`
+    fn float<T>(
+        cpu: &mut CpuState,
+        instruction: &Instruction,
+        fmt: FloatFormat,
+        rm: RoundingMode,
+        op: impl FnOnce(&mut SoftFloat, &CpuState) -> T,
+    ) -> Result<T, VmError> {
+        let rm = match rm {
+            // A reserved or dynamic value in `frm` makes the instruction illegal
+            RoundingMode::Dyn => match RoundingMode::from_u32(cpu.csrs.fcsr >> 5) {
+                Some(RoundingMode::Dyn) | None => return Err(VmError::InvalidInstruction(instruction.encode()?)),
+                Some(rm) => rm,
+            },
+            rm => rm,
+        };
+
+        let mut fpu = SoftFloat::new(fmt.into(), rm);
+        let result = op(&mut fpu, cpu);
+        cpu.csrs.fcsr |= fpu.flags;
+        Ok(result)
+    }
+
+    /// Atomically apply `op` to the word at `rs1` and `rs2`, returning the old value in `rd`
+    fn amo(
+        cpu: &mut CpuState,
+        memory: &mut Memory,
+        rd: RegisterIndex,
+        rs1: RegisterIndex,
+        rs2: RegisterIndex,
+        op: impl Fn(u32, u32) -> u32,
+    ) -> Result<(), VmError> {
+        let addr = Self::address(cpu, rs1, 0, AccessKind::Write)?;
+        let old = memory.load_word(addr)?;
+        memory.store_word(addr, op(old, cpu.read_register(rs2)))?;
+        cpu.write_register(rd, old);
+        Ok(())
+    }
+
+    /// Atomically apply `op` to the double-word at `rs1` and `rs2`, returning the old value in `rd`
+    fn amo_double(
+        cpu: &mut CpuState,
+        memory: &mut Memory,
+        rd: RegisterIndex,
+        rs1: RegisterIndex,
+        rs2: RegisterIndex,
+        op: impl Fn(u64, u64) -> u64,
+    ) -> Result<(), VmError> {
+        let addr = Self::address(cpu, rs1, 0, AccessKind::Write)?;
+        let old = memory.load_doubleword(addr)?;
+        memory.store_doubleword(addr, op(old, cpu.read_xreg(rs2)))?;
+        cpu.write_xreg(rd, old);
+        Ok(())
+    }
+
     /// Execute a single cycle: fetch, decode, execute
     pub fn execute_cycle(
         cpu: &mut CpuState,
         memory: &mut Memory,
+        config: &VmConfig,
     ) -> Result<bool, VmError> {
-        // Fetch
-        let instruction_word = memory.load_word(cpu.get_pc())?;
-        
-        // Decode
-        let instruction = Decoder::decode(instruction_word)?;
-        
+        // Fetch and decode
+        let (instruction, len) = match Self::fetch(cpu, memory, config) {
+            Ok(fetched) => fetched,
+            Err(err) => return Self::handle_fault(err, None, cpu, config),
+        };
+
         // Execute
-        Self::execute_instruction(&instruction, cpu, memory)
+        match Self::execute_instruction(&instruction, len, cpu, memory, config) {
+            Err(err) => Self::handle_fault(err, Some(&instruction), cpu, config),
+            result => {
+                cpu.instret += 1;
+                result
+            },
+        }
+    }
+
+    /// Fetch and decode the instruction at the PC, returning it with its length
+    fn fetch(cpu: &CpuState, memory: &Memory, config: &VmConfig) -> Result<(Instruction, u32), VmError> {
+        // Fetch the first 16-bit parcel to determine the instruction length
+        let pc = cpu.get_pc();
+        let parcel = memory.fetch_halfword(pc)?;
+        if Decoder::is_compressed(parcel) {
+            return Ok((Decoder::decode_compressed_with(parcel, &config.isa)?, 2));
+        }
+
+        let word = if !pc.is_multiple_of(4) && memory.misaligned_policy() == MisalignedPolicy::Split {
+            parcel as u32 | (memory.fetch_halfword(pc.wrapping_add(2))? as u32) << 16
+        } else {
+            memory.fetch_word(pc)?
+        };
+        Ok((Decoder::decode_with(word, &config.isa)?, 4))
+    }
+
+    /// Either trap into the guest handler or abort with the original error
+    fn handle_fault(
+        err: VmError,
+        instruction: Option<&Instruction>,
+        cpu: &mut CpuState,
+        config: &VmConfig,
+    ) -> Result<bool, VmError> {
+        // A handler outside the 32-bit physical address space cannot be entered
+        if config.trap_mode == TrapMode::Guest && cpu.csrs.mtvec <= u32::MAX as u64 {
+            if let Some(trap) = Trap::from_error(&err, instruction) {
+                cpu.enter_trap(trap);
+                return Ok(false);
+            }
+        }
+        Err(err)
     }
 }
\ No newline at end of file
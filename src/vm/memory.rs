@@ -1,107 +1,568 @@
 //! Memory management for the RISC-V VM
 
+use crate::utils::config::MisalignedPolicy;
 use crate::utils::VmError;
+use crate::vm::device::SharedDevice;
+use crate::vm::merkle::{self, Hash, MerkleTree, WordProof};
+use crate::zk::MemoryAccess;
 use byteorder::{LittleEndian, ByteOrder};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
 
-/// Memory implementation with 32-bit address space
+/// Size of a memory page in bytes
+pub const PAGE_SIZE: usize = 4096;
+
+/// log2 of `PAGE_SIZE`
+pub const PAGE_SHIFT: u32 = 12;
+
+/// Pages are reference counted so that clones of a `Memory` share them until
+/// one side writes (copy-on-write)
+type Page = Arc<[u8; PAGE_SIZE]>;
+
+/// Kind of memory access, for permission checks and fault reporting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+    Execute,
+}
+
+impl fmt::Display for AccessKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Execute => "execute",
+        })
+    }
+}
+
+/// Access permissions of a memory region
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Permissions {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl Permissions {
+    pub const READ_ONLY: Self = Self { read: true, write: false, execute: false };
+    pub const READ_WRITE: Self = Self { read: true, write: true, execute: false };
+    pub const READ_EXECUTE: Self = Self { read: true, write: false, execute: true };
+    pub const ALL: Self = Self { read: true, write: true, execute: true };
+
+    /// Check whether an access of the given kind is permitted
+    pub fn allows(self, access: AccessKind) -> bool {
+        match access {
+            AccessKind::Read => self.read,
+            AccessKind::Write => self.write,
+            AccessKind::Execute => self.execute,
+        }
+    }
+}
+
+/// A contiguous range of addresses with uniform permissions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub start: u32,
+    pub size: u32,
+    pub permissions: Permissions,
+}
+
+impl Region {
+    /// Exclusive end address; may be `1 << 32`
+    pub fn end(&self) -> u64 {
+        self.start as u64 + self.size as u64
+    }
+
+    /// Check whether the region contains an address
+    pub fn contains(&self, addr: u32) -> bool {
+        addr >= self.start && (addr as u64) < self.end()
+    }
+}
+
+/// A device attached to an address range
+#[derive(Clone)]
+struct MappedDevice {
+    base: u32,
+    size: u32,
+    device: SharedDevice,
+}
+
+impl MappedDevice {
+    fn end(&self) -> u64 {
+        self.base as u64 + self.size as u64
+    }
+}
+
+impl fmt::Debug for MappedDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedDevice").field("base", &self.base).field("size", &self.size).finish()
+    }
+}
+
+/// Sparse memory covering the full 32-bit address space.
+///
+/// Pages are allocated on the first store that touches them; loads from
+/// untouched pages read zero without allocating. The number of resident
+/// pages is capped, so a runaway guest cannot exhaust host memory.
+/// Cloning is cheap: pages are copied only when one of the clones writes them.
+///
+/// While no region is mapped every address may be read, written and
+/// executed. Once regions exist (e.g. from ELF program headers), accesses
+/// outside them or without the required permission raise
+/// `VmError::AccessFault`.
+///
+/// Attached devices take precedence over RAM and regions: loads and stores
+/// in their range are forwarded to the device.
 #[derive(Debug, Clone)]
 pub struct Memory {
-    data: Vec<u8>,
-    size: usize,
+    /// Resident pages keyed by page number (`address >> PAGE_SHIFT`)
+    pages: BTreeMap<u32, Page>,
+    max_pages: usize,
+    /// Non-overlapping regions sorted by start address
+    regions: Vec<Region>,
+    /// Memory-mapped devices, with non-overlapping ranges
+    devices: Vec<MappedDevice>,
+    /// Handling of misaligned loads and stores
+    misaligned: MisalignedPolicy,
+    /// Commitment to the page contents, up to date except for `dirty` pages
+    merkle: MerkleTree,
+    /// Pages written since the Merkle tree was last updated
+    dirty: BTreeSet<u32>,
+    /// Loads and stores made while recording; loads take `&self`, hence the `RefCell`
+    accesses: RefCell<Option<Vec<MemoryAccess>>>,
 }
 
 impl Memory {
-    /// Create a new memory instance with the specified size
+    /// Create a new memory instance that may hold up to `size` bytes of resident pages
     pub fn new(size: usize) -> Self {
         Self {
-            data: vec![0; size],
-            size,
+            pages: BTreeMap::new(),
+            max_pages: size.div_ceil(PAGE_SIZE),
+            regions: Vec::new(),
+            devices: Vec::new(),
+            misaligned: MisalignedPolicy::default(),
+            merkle: MerkleTree::new(),
+            dirty: BTreeSet::new(),
+            accesses: RefCell::new(None),
         }
     }
 
     /// Load a byte from memory
     pub fn load_byte(&self, addr: u32) -> Result<u8, VmError> {
-        let addr = addr as usize;
-        if addr >= self.size {
-            return Err(VmError::MemoryViolation { address: addr as u32 });
-        }
-        Ok(self.data[addr])
+        let mut buf = [0; 1];
+        self.load(addr, &mut buf)?;
+        Ok(buf[0])
     }
 
     /// Load a half-word (16-bit) from memory (little-endian)
     pub fn load_halfword(&self, addr: u32) -> Result<u16, VmError> {
-        let addr = addr as usize;
-        if addr + 1 >= self.size {
-            return Err(VmError::MemoryViolation { address: addr as u32 });
-        }
-        Ok(LittleEndian::read_u16(&self.data[addr..addr + 2]))
+        let mut buf = [0; 2];
+        self.load(addr, &mut buf)?;
+        Ok(LittleEndian::read_u16(&buf))
     }
 
     /// Load a word (32-bit) from memory (little-endian)
     pub fn load_word(&self, addr: u32) -> Result<u32, VmError> {
-        let addr = addr as usize;
-        if addr + 3 >= self.size {
-            return Err(VmError::MemoryViolation { address: addr as u32 });
-        }
-        Ok(LittleEndian::read_u32(&self.data[addr..addr + 4]))
+        let mut buf = [0; 4];
+        self.load(addr, &mut buf)?;
+        Ok(LittleEndian::read_u32(&buf))
+    }
+
+    /// Load a double-word (64-bit) from memory (little-endian)
+    pub fn load_doubleword(&self, addr: u32) -> Result<u64, VmError> {
+        let mut buf = [0; 8];
+        self.load(addr, &mut buf)?;
+        Ok(LittleEndian::read_u64(&buf))
     }
 
     /// Store a byte to memory
     pub fn store_byte(&mut self, addr: u32, value: u8) -> Result<(), VmError> {
-        let addr = addr as usize;
-        if addr >= self.size {
-            return Err(VmError::MemoryViolation { address: addr as u32 });
-        }
-        self.data[addr] = value;
-        Ok(())
+        self.store(addr, &[value])
     }
 
     /// Store a half-word (16-bit) to memory (little-endian)
     pub fn store_halfword(&mut self, addr: u32, value: u16) -> Result<(), VmError> {
-        let addr = addr as usize;
-        if addr + 1 >= self.size {
-            return Err(VmError::MemoryViolation { address: addr as u32 });
-        }
-        LittleEndian::write_u16(&mut self.data[addr..addr + 2], value);
-        Ok(())
+        self.store(addr, &value.to_le_bytes())
     }
 
     /// Store a word (32-bit) to memory (little-endian)
     pub fn store_word(&mut self, addr: u32, value: u32) -> Result<(), VmError> {
-        let addr = addr as usize;
-        if addr + 3 >= self.size {
-            return Err(VmError::MemoryViolation { address: addr as u32 });
-        }
-        LittleEndian::write_u32(&mut self.data[addr..addr + 4], value);
-        Ok(())
+        self.store(addr, &value.to_le_bytes())
+    }
+
+    /// Store a double-word (64-bit) to memory (little-endian)
+    pub fn store_doubleword(&mut self, addr: u32, value: u64) -> Result<(), VmError> {
+        self.store(addr, &value.to_le_bytes())
     }
 
     /// Load raw bytes from memory
-    pub fn load_bytes(&self, addr: u32, len: usize) -> Result<&[u8], VmError> {
-        let addr = addr as usize;
-        if addr + len > self.size {
-            return Err(VmError::MemoryViolation { address: addr as u32 });
-        }
-        Ok(&self.data[addr..addr + len])
+    pub fn load_bytes(&self, addr: u32, len: usize) -> Result<Vec<u8>, VmError> {
+        let mut buf = vec![0; len];
+        self.read(addr, &mut buf, AccessKind::Read)?;
+        Ok(buf)
     }
 
     /// Store raw bytes to memory
     pub fn store_bytes(&mut self, addr: u32, data: &[u8]) -> Result<(), VmError> {
-        let addr = addr as usize;
-        if addr + data.len() > self.size {
-            return Err(VmError::MemoryViolation { address: addr as u32 });
+        self.store_unaligned(addr, data)
+    }
+
+    /// Get the memory size limit in bytes
+    pub fn size(&self) -> usize {
+        self.max_pages * PAGE_SIZE
+    }
+
+    /// Maximum number of resident pages
+    pub fn max_pages(&self) -> usize {
+        self.max_pages
+    }
+
+    /// Number of pages allocated so far
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Iterate over allocated pages in address order as `(base address, contents)`
+    pub fn pages(&self) -> impl Iterator<Item = (u32, &[u8; PAGE_SIZE])> + '_ {
+        self.pages.iter().map(|(&number, page)| (number << PAGE_SHIFT, &**page))
+    }
+
+    /// Set how misaligned `load_*`/`store_*` accesses are handled. Raw byte
+    /// accesses and instruction fetches are not affected.
+    pub fn set_misaligned_policy(&mut self, policy: MisalignedPolicy) {
+        self.misaligned = policy;
+    }
+
+    /// Current misaligned-access policy
+    pub fn misaligned_policy(&self) -> MisalignedPolicy {
+        self.misaligned
+    }
+
+    /// Fetch a half-word (16-bit) for execution; requires execute permission
+    pub fn fetch_halfword(&self, addr: u32) -> Result<u16, VmError> {
+        let mut buf = [0; 2];
+        self.read(addr, &mut buf, AccessKind::Execute)?;
+        Ok(LittleEndian::read_u16(&buf))
+    }
+
+    /// Fetch a word (32-bit) for execution; requires execute permission
+    pub fn fetch_word(&self, addr: u32) -> Result<u32, VmError> {
+        let mut buf = [0; 4];
+        self.read(addr, &mut buf, AccessKind::Execute)?;
+        Ok(LittleEndian::read_u32(&buf))
+    }
+
+    /// Map a region with the given permissions, replacing those of any overlapping
+    /// part of existing regions
+    pub fn map(&mut self, start: u32, size: u32, permissions: Permissions) {
+        if size == 0 {
+            return;
+        }
+        let region = Region { start, size, permissions };
+        let (new_start, new_end) = (start as u64, region.end());
+
+        let mut regions = Vec::with_capacity(self.regions.len() + 2);
+        for r in self.regions.drain(..) {
+            if r.end() <= new_start || r.start as u64 >= new_end {
+                regions.push(r);
+                continue;
+            }
+            if (r.start as u64) < new_start {
+                regions.push(Region { size: (new_start - r.start as u64) as u32, ..r });
+            }
+            if r.end() > new_end {
+                regions.push(Region { start: new_end as u32, size: (r.end() - new_end) as u32, ..r });
+            }
+        }
+        regions.push(region);
+        regions.sort_by_key(|r| r.start);
+        self.regions = regions;
+    }
+
+    /// Mapped regions sorted by start address
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// Attach a memory-mapped device to `size` bytes starting at `base`
+    pub fn attach_device(&mut self, base: u32, size: u32, device: SharedDevice) -> Result<(), VmError> {
+        let mapped = MappedDevice { base, size, device };
+        if size == 0 || mapped.end() > 1 << 32 {
+            return Err(VmError::DeviceError(format!("Invalid device range {:08x}+{:x}", base, size)));
         }
-        self.data[addr..addr + data.len()].copy_from_slice(data);
+        if self.devices.iter().any(|d| (d.base as u64) < mapped.end() && (base as u64) < d.end()) {
+            return Err(VmError::DeviceError(format!("Device range at {:08x} overlaps another device", base)));
+        }
+        self.devices.push(mapped);
         Ok(())
     }
 
-    /// Get the memory size
-    pub fn size(&self) -> usize {
-        self.size
+    /// Write `data` at `addr`, zero-fill up to `size` bytes and map the result with
+    /// `permissions`. Used by loaders, so existing permissions are not checked.
+    pub fn load_segment(&mut self, addr: u32, data: &[u8], size: u32, permissions: Permissions) -> Result<(), VmError> {
+        let size = size.max(data.len() as u32);
+        Self::check_range(addr, size as usize)?;
+        self.write(addr, data)?;
+
+        // Zero the .bss tail only where it overlaps resident pages; the rest
+        // already reads as zero and is allocated on first store
+        let bss_addr = addr.wrapping_add(data.len() as u32);
+        for (number, offset, _, len) in Self::chunks(bss_addr, size as usize - data.len()) {
+            if let Some(page) = self.pages.get_mut(&number) {
+                Arc::make_mut(page)[offset..offset + len].fill(0);
+                self.dirty.insert(number);
+            }
+        }
+
+        self.map(addr, size, permissions);
+        Ok(())
+    }
+
+    /// Start or stop recording `load_*`/`store_*` accesses. Split misaligned
+    /// accesses are recorded piece by piece; fetches and raw byte accesses
+    /// are not recorded.
+    pub fn record_accesses(&mut self, enabled: bool) {
+        *self.accesses.get_mut() = enabled.then(Vec::new);
+    }
+
+    /// Take the accesses recorded so far, with `cycle` left at zero
+    pub fn take_accesses(&mut self) -> Vec<MemoryAccess> {
+        self.accesses.get_mut().as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Merkle root committing to the contents of the whole address space.
+    /// Only pages written since the last call are rehashed.
+    pub fn root(&mut self) -> Hash {
+        self.update_merkle();
+        self.merkle.root()
+    }
+
+    /// Inclusion proof for the word at a word-aligned address, against `root()`
+    pub fn prove_word(&mut self, addr: u32) -> Result<WordProof, VmError> {
+        if !addr.is_multiple_of(4) {
+            return Err(VmError::MisalignedAccess { address: addr, access: AccessKind::Read });
+        }
+        self.update_merkle();
+        let page = self.pages.get(&(addr >> PAGE_SHIFT)).map(|page| &**page);
+        Ok(self.merkle.prove_word(addr, page))
+    }
+
+    /// Rehash dirty pages into the Merkle tree
+    fn update_merkle(&mut self) {
+        for number in std::mem::take(&mut self.dirty) {
+            let root = match self.pages.get(&number) {
+                Some(page) => merkle::page_root(page),
+                None => merkle::zero_page_root(),
+            };
+            self.merkle.set_page_root(number, root);
+        }
     }
 
-    /// Clear all memory
+    /// Clear all memory and region mappings
     pub fn clear(&mut self) {
-        self.data.fill(0);
+        self.pages.clear();
+        self.regions.clear();
+        self.merkle = MerkleTree::new();
+        self.dirty.clear();
+    }
+
+    /// Check that `len` bytes starting at `addr` lie inside the address space
+    fn check_range(addr: u32, len: usize) -> Result<(), VmError> {
+        if addr as u64 + len as u64 > 1 << 32 {
+            return Err(VmError::MemoryViolation { address: addr });
+        }
+        Ok(())
+    }
+
+    /// Split an access into `(page number, offset in page, offset in buffer, length)` chunks
+    fn chunks(addr: u32, len: usize) -> impl Iterator<Item = (u32, usize, usize, usize)> {
+        let mut done = 0;
+        std::iter::from_fn(move || {
+            if done == len {
+                return None;
+            }
+            let current = addr.wrapping_add(done as u32);
+            let offset = current as usize & (PAGE_SIZE - 1);
+            let chunk = (PAGE_SIZE - offset).min(len - done);
+            let item = (current >> PAGE_SHIFT, offset, done, chunk);
+            done += chunk;
+            Some(item)
+        })
+    }
+
+    /// Find the device handling an access, if it touches one
+    fn device_for(&self, addr: u32, len: usize, access: AccessKind) -> Result<Option<&MappedDevice>, VmError> {
+        let end = addr as u64 + len as u64;
+        let Some(mapped) = self.devices.iter().find(|d| (d.base as u64) < end && (addr as u64) < d.end()) else {
+            return Ok(None);
+        };
+        let contained = addr >= mapped.base && end <= mapped.end();
+        if !contained || !matches!(len, 1 | 2 | 4 | 8) || access == AccessKind::Execute {
+            return Err(VmError::AccessFault { address: addr, access });
+        }
+        Ok(Some(mapped))
+    }
+
+    /// Check that every byte of an access lies in a region permitting it
+    fn check_access(&self, addr: u32, len: usize, access: AccessKind) -> Result<(), VmError> {
+        if self.regions.is_empty() {
+            return Ok(());
+        }
+
+        let mut pos = addr as u64;
+        let end = pos + len as u64;
+        while pos < end {
+            let index = self.regions.partition_point(|r| r.start as u64 <= pos);
+            let region = index
+                .checked_sub(1)
+                .map(|i| &self.regions[i])
+                .filter(|r| pos < r.end() && r.permissions.allows(access))
+                .ok_or(VmError::AccessFault { address: pos as u32, access })?;
+            pos = region.end();
+        }
+        Ok(())
+    }
+
+    fn read(&self, addr: u32, buf: &mut [u8], access: AccessKind) -> Result<(), VmError> {
+        Self::check_range(addr, buf.len())?;
+        if let Some(mapped) = self.device_for(addr, buf.len(), access)? {
+            let mut device = mapped.device.lock().unwrap_or_else(|e| e.into_inner());
+            let value = device.read(addr - mapped.base, buf.len() as u8)?;
+            buf.copy_from_slice(&value.to_le_bytes()[..buf.len()]);
+            return Ok(());
+        }
+        self.check_access(addr, buf.len(), access)?;
+        for (number, offset, start, len) in Self::chunks(addr, buf.len()) {
+            let dest = &mut buf[start..start + len];
+            match self.pages.get(&number) {
+                Some(page) => dest.copy_from_slice(&page[offset..offset + len]),
+                None => dest.fill(0),
+            }
+        }
+        Ok(())
+    }
+
+    /// Split an access into naturally aligned `(address, offset, size)` pieces
+    fn aligned_pieces(addr: u32, len: usize) -> impl Iterator<Item = (u32, usize, usize)> {
+        let mut done = 0;
+        std::iter::from_fn(move || {
+            if done == len {
+                return None;
+            }
+            let current = addr.wrapping_add(done as u32);
+            let alignment = 1usize << current.trailing_zeros().min(3);
+            let remaining = 1usize << (len - done).ilog2();
+            let size = alignment.min(remaining);
+            let item = (current, done, size);
+            done += size;
+            Some(item)
+        })
+    }
+
+    /// Record a successful access if recording is enabled
+    fn record(&self, address: u32, data: &[u8], is_write: bool) {
+        if let Some(accesses) = self.accesses.borrow_mut().as_mut() {
+            let mut bytes = [0; 8];
+            bytes[..data.len()].copy_from_slice(data);
+            accesses.push(MemoryAccess {
+                address,
+                value: u64::from_le_bytes(bytes),
+                width: data.len() as u8,
+                is_write,
+                cycle: 0,
+            });
+        }
+    }
+
+    /// Typed load, applying the misaligned-access policy
+    fn load(&self, addr: u32, buf: &mut [u8]) -> Result<(), VmError> {
+        let split = !(addr as usize).is_multiple_of(buf.len()) && self.misaligned != MisalignedPolicy::Allow;
+        if !split {
+            self.read(addr, buf, AccessKind::Read)?;
+            self.record(addr, buf, false);
+            return Ok(());
+        }
+        if self.misaligned == MisalignedPolicy::Trap {
+            return Err(VmError::MisalignedAccess { address: addr, access: AccessKind::Read });
+        }
+
+        Self::check_range(addr, buf.len())?;
+        for (piece, offset, size) in Self::aligned_pieces(addr, buf.len()) {
+            let part = &mut buf[offset..offset + size];
+            self.read(piece, part, AccessKind::Read)?;
+            self.record(piece, part, false);
+        }
+        Ok(())
+    }
+
+    /// Typed store, applying the misaligned-access policy
+    fn store(&mut self, addr: u32, data: &[u8]) -> Result<(), VmError> {
+        let split = !(addr as usize).is_multiple_of(data.len()) && self.misaligned != MisalignedPolicy::Allow;
+        if !split {
+            self.store_unaligned(addr, data)?;
+            self.record(addr, data, true);
+            return Ok(());
+        }
+        if self.misaligned == MisalignedPolicy::Trap {
+            return Err(VmError::MisalignedAccess { address: addr, access: AccessKind::Write });
+        }
+
+        // Check every piece before writing any, so a fault in a later piece
+        // leaves the earlier ones unmodified
+        Self::check_range(addr, data.len())?;
+        let mut missing = BTreeSet::new();
+        for (piece, _, size) in Self::aligned_pieces(addr, data.len()) {
+            if self.device_for(piece, size, AccessKind::Write)?.is_none() {
+                self.check_access(piece, size, AccessKind::Write)?;
+                missing.extend(Self::chunks(piece, size).map(|(number, ..)| number));
+            }
+        }
+        missing.retain(|number| !self.pages.contains_key(number));
+        if self.pages.len() + missing.len() > self.max_pages {
+            return Err(VmError::OutOfMemory { address: addr, limit: self.max_pages });
+        }
+
+        for (piece, offset, size) in Self::aligned_pieces(addr, data.len()) {
+            let part = &data[offset..offset + size];
+            self.store_unaligned(piece, part)?;
+            self.record(piece, part, true);
+        }
+        Ok(())
+    }
+
+    /// Guest store: checks write permission before writing
+    fn store_unaligned(&mut self, addr: u32, data: &[u8]) -> Result<(), VmError> {
+        Self::check_range(addr, data.len())?;
+        if let Some(mapped) = self.device_for(addr, data.len(), AccessKind::Write)? {
+            let mut bytes = [0; 8];
+            bytes[..data.len()].copy_from_slice(data);
+            let mut device = mapped.device.lock().unwrap_or_else(|e| e.into_inner());
+            return device.write(addr - mapped.base, data.len() as u8, u64::from_le_bytes(bytes));
+        }
+        self.check_access(addr, data.len(), AccessKind::Write)?;
+        self.write(addr, data)
+    }
+
+    fn write(&mut self, addr: u32, data: &[u8]) -> Result<(), VmError> {
+        Self::check_range(addr, data.len())?;
+
+        // Refuse the whole store up front rather than leaving it half written
+        let missing = Self::chunks(addr, data.len())
+            .filter(|(number, ..)| !self.pages.contains_key(number))
+            .count();
+        if self.pages.len() + missing > self.max_pages {
+            return Err(VmError::OutOfMemory { address: addr, limit: self.max_pages });
+        }
+
+        for (number, offset, start, len) in Self::chunks(addr, data.len()) {
+            let page = self.pages.entry(number).or_insert_with(|| Arc::new([0; PAGE_SIZE]));
+            Arc::make_mut(page)[offset..offset + len].copy_from_slice(&data[start..start + len]);
+            self.dirty.insert(number);
+        }
+        Ok(())
     }
 }
\ No newline at end of file
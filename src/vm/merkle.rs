@@ -0,0 +1,182 @@
+//! SHA-256 Merkle commitment to the memory image
+//!
+//! The tree has one leaf per 32-bit word of the address space (2^30 leaves).
+//! Levels 0-10 form a subtree per 4 KiB page; levels 10-30 form a sparse tree
+//! over the page roots. Only page roots and the nodes above them are stored,
+//! with untouched subtrees taking precomputed all-zero hashes, so the root
+//! depends only on memory contents. Leaves are `H(0x00 || word)` and inner
+//! nodes `H(0x01 || left || right)`.
+
+use crate::vm::memory::PAGE_SIZE;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// A SHA-256 digest
+pub type Hash = [u8; 32];
+
+/// Number of levels within a page (1024 words)
+pub const PAGE_DEPTH: usize = 10;
+
+/// Number of levels from a word leaf to the memory root
+pub const TREE_DEPTH: usize = 30;
+
+/// Hash a word leaf
+pub fn hash_leaf(word: u32) -> Hash {
+    Sha256::new().chain_update([0]).chain_update(word.to_le_bytes()).finalize().into()
+}
+
+/// Hash two child nodes
+pub fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    Sha256::new().chain_update([1]).chain_update(left).chain_update(right).finalize().into()
+}
+
+/// Root of an all-zero subtree at each level, from a single leaf up to the full memory
+fn zero_hashes() -> &'static [Hash; TREE_DEPTH + 1] {
+    static ZERO: OnceLock<[Hash; TREE_DEPTH + 1]> = OnceLock::new();
+    ZERO.get_or_init(|| {
+        let mut hashes = [hash_leaf(0); TREE_DEPTH + 1];
+        for level in 1..=TREE_DEPTH {
+            hashes[level] = hash_node(&hashes[level - 1], &hashes[level - 1]);
+        }
+        hashes
+    })
+}
+
+/// Root of an all-zero page
+pub fn zero_page_root() -> Hash {
+    zero_hashes()[PAGE_DEPTH]
+}
+
+/// Every level of a page subtree, from the 1024 leaves up to the page root
+fn page_levels(page: &[u8; PAGE_SIZE]) -> Vec<Vec<Hash>> {
+    let leaves = page
+        .chunks_exact(4)
+        .map(|word| hash_leaf(u32::from_le_bytes(word.try_into().unwrap())))
+        .collect();
+
+    let mut levels: Vec<Vec<Hash>> = vec![leaves];
+    while levels.last().unwrap().len() > 1 {
+        let next = levels.last().unwrap().chunks_exact(2).map(|pair| hash_node(&pair[0], &pair[1])).collect();
+        levels.push(next);
+    }
+    levels
+}
+
+/// Root of a page subtree
+pub fn page_root(page: &[u8; PAGE_SIZE]) -> Hash {
+    page_levels(page)[PAGE_DEPTH][0]
+}
+
+/// Sparse tree over page roots
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    /// Non-default nodes at levels `PAGE_DEPTH..=TREE_DEPTH`, indexed by level - `PAGE_DEPTH`
+    nodes: Vec<HashMap<u32, Hash>>,
+}
+
+impl MerkleTree {
+    pub fn new() -> Self {
+        Self { nodes: vec![HashMap::new(); TREE_DEPTH - PAGE_DEPTH + 1] }
+    }
+
+    /// Node at `level` (counted from the word leaves) and `index`
+    fn node(&self, level: usize, index: u32) -> Hash {
+        self.nodes
+            .get(level - PAGE_DEPTH)
+            .and_then(|nodes| nodes.get(&index))
+            .copied()
+            .unwrap_or(zero_hashes()[level])
+    }
+
+    /// Memory root
+    pub fn root(&self) -> Hash {
+        self.node(TREE_DEPTH, 0)
+    }
+
+    /// Set a page root and recompute the path above it
+    pub fn set_page_root(&mut self, page: u32, root: Hash) {
+        let mut index = page;
+        self.nodes[0].insert(index, root);
+        for level in PAGE_DEPTH + 1..=TREE_DEPTH {
+            index >>= 1;
+            let left = self.node(level - 1, index * 2);
+            let right = self.node(level - 1, index * 2 + 1);
+            self.nodes[level - PAGE_DEPTH].insert(index, hash_node(&left, &right));
+        }
+    }
+
+    /// Build an inclusion proof for the word at `address` (word aligned),
+    /// given the contents of its page (`None` if untouched)
+    pub fn prove_word(&self, address: u32, page: Option<&[u8; PAGE_SIZE]>) -> WordProof {
+        let word_index = (address as usize % PAGE_SIZE) / 4;
+        let mut siblings = Vec::with_capacity(TREE_DEPTH);
+
+        let value = match page {
+            Some(page) => {
+                let levels = page_levels(page);
+                let mut index = word_index;
+                for level in &levels[..PAGE_DEPTH] {
+                    siblings.push(level[index ^ 1]);
+                    index >>= 1;
+                }
+                u32::from_le_bytes(page[word_index * 4..word_index * 4 + 4].try_into().unwrap())
+            },
+            None => {
+                siblings.extend_from_slice(&zero_hashes()[..PAGE_DEPTH]);
+                0
+            },
+        };
+
+        let mut index = address >> (PAGE_DEPTH + 2);
+        for level in PAGE_DEPTH..TREE_DEPTH {
+            siblings.push(self.node(level, index ^ 1));
+            index >>= 1;
+        }
+
+        WordProof { address, value, siblings }
+    }
+}
+
+impl Default for MerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Inclusion proof for one word of memory.
+///
+/// The same siblings also prove an update: writing `new_value` to the word
+/// changes the root to `root_with(new_value)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordProof {
+    /// Word-aligned address
+    pub address: u32,
+    /// Value of the word
+    pub value: u32,
+    /// Sibling hashes from the leaf up to just below the root
+    pub siblings: Vec<Hash>,
+}
+
+impl WordProof {
+    /// Root implied by the proof if the word held `value`
+    pub fn root_with(&self, value: u32) -> Hash {
+        let mut hash = hash_leaf(value);
+        let mut index = self.address >> 2;
+        for sibling in &self.siblings {
+            hash = if index & 1 == 0 { hash_node(&hash, sibling) } else { hash_node(sibling, &hash) };
+            index >>= 1;
+        }
+        hash
+    }
+
+    /// Check that the word is included in memory with the given root
+    pub fn verify(&self, root: &Hash) -> bool {
+        self.siblings.len() == TREE_DEPTH && self.root_with(self.value) == *root
+    }
+
+    /// Check that writing `new_value` to the word takes memory from `old_root` to `new_root`
+    pub fn verify_update(&self, old_root: &Hash, new_value: u32, new_root: &Hash) -> bool {
+        self.verify(old_root) && self.root_with(new_value) == *new_root
+    }
+}
\ No newline at end of file
@@ -1,19 +1,31 @@
 //! Virtual Machine implementation
 
 use crate::utils::{VmError, config::VmConfig};
-use crate::zk::Proof;
-use crate::risc_v::RegisterIndex;
+use crate::zk::{ExecutionTrace, MemoryAccess, Proof, ProofSystem, Prover};
+use crate::risc_v::{Assembler, Decoder, RegisterIndex};
+use crate::loader::ElfLoader;
+use crate::loader::elf::ElfFile;
+use std::fmt;
 use std::path::Path;
 
 pub mod cpu;
+pub mod csr;
 pub mod memory;
+pub mod device;
+pub mod merkle;
 pub mod executor;
 pub mod syscalls;
+pub mod trap;
+pub mod softfloat;
+pub(crate) mod crypto;
 
 pub use cpu::CpuState;
+pub use csr::CsrFile;
 pub use memory::Memory;
+pub use device::{Device, SharedDevice};
 pub use executor::Executor;
 pub use syscalls::SyscallHandler;
+pub use trap::{Exception, Trap};
 
 /// Main Virtual Machine
 #[derive(Debug)]
@@ -32,17 +44,51 @@ impl VirtualMachine {
 
     /// Create a new virtual machine with the specified configuration
     pub fn with_config(config: VmConfig) -> Self {
+        let mut memory = Memory::new(config.memory_size);
+        memory.set_misaligned_policy(config.misaligned);
         Self {
-            cpu: CpuState::new(),
-            memory: Memory::new(config.memory_size),
+            cpu: CpuState::with_xlen(config.isa.xlen()),
+            memory,
             config,
         }
     }
 
     /// Load an ELF file into memory
-    pub fn load_elf<P: AsRef<Path>>(&mut self, _path: P) -> Result<(), VmError> {
-        // For now, just return an error - ELF loading will be implemented later
-        Err(VmError::ElfError("ELF loading not yet implemented".to_string()))
+    pub fn load_elf<P: AsRef<Path>>(&mut self, path: P) -> Result<(), VmError> {
+        let elf = ElfLoader::new().load(path)?;
+        self.load_elf_file(&elf)
+    }
+
+    /// Load a parsed ELF file into memory; its class must match the configured ISA width
+    pub fn load_elf_file(&mut self, elf: &ElfFile) -> Result<(), VmError> {
+        if elf.xlen != self.config.isa.xlen() {
+            return Err(VmError::ElfError(format!(
+                "{}-bit ELF file cannot run on {}",
+                elf.xlen.bits(),
+                self.config.isa
+            )));
+        }
+
+        // Segments become the only accessible regions, with their program-header permissions
+        for segment in &elf.segments {
+            self.memory.load_segment(
+                segment.virtual_addr,
+                &segment.data,
+                segment.mem_size,
+                segment.permissions(),
+            )?;
+        }
+
+        self.cpu.pc = elf.entry_point;
+        Ok(())
+    }
+
+    /// Assemble RISC-V source text at `base` and set the PC to its entry point
+    pub fn load_asm(&mut self, base: u32, source: &str) -> Result<(), VmError> {
+        let program = Assembler::new(base).assemble(source)?;
+        self.memory.store_bytes(program.base, &program.data)?;
+        self.cpu.pc = program.entry;
+        Ok(())
     }
 
     /// Load raw binary data into memory at the specified address
@@ -63,34 +109,135 @@ impl VirtualMachine {
 
     /// Execute the program without proof generation
     pub fn execute(&mut self) -> Result<(), VmError> {
+        match self.run() {
+            ExitReason::Halted { .. } | ExitReason::Paused => Ok(()),
+            reason => Err(Self::exit_error(reason)),
+        }
+    }
+
+    /// Error reported by `execute` for a run that stopped without halting
+    fn exit_error(reason: ExitReason) -> VmError {
+        match reason {
+            ExitReason::CycleLimit => VmError::ExecutionError("Maximum cycles exceeded".to_string()),
+            ExitReason::Breakpoint { pc } => VmError::Breakpoint { pc },
+            ExitReason::Fault { error, .. } => error,
+            reason => VmError::ExecutionError(format!("Execution stopped: {}", reason)),
+        }
+    }
+
+    /// Execute a single instruction. Returns `Paused` if execution can continue.
+    pub fn step(&mut self) -> ExitReason {
+        if self.cpu.get_cycles() >= self.config.max_cycles {
+            return ExitReason::CycleLimit;
+        }
+
+        let pc = self.cpu.get_pc();
+        match Executor::execute_cycle(&mut self.cpu, &mut self.memory, &self.config) {
+            Ok(true) => ExitReason::Halted { code: self.cpu.exit_code.unwrap_or(0) },
+            Ok(false) => ExitReason::Paused,
+            Err(VmError::Breakpoint { pc }) => ExitReason::Breakpoint { pc },
+            Err(error) => ExitReason::Fault { pc, error },
+        }
+    }
+
+    /// Run until the program stops or `VmConfig::max_cycles` is reached
+    pub fn run(&mut self) -> ExitReason {
         loop {
-            // Check cycle limit
-            if self.cpu.get_cycles() >= self.config.max_cycles {
-                return Err(VmError::ExecutionError("Maximum cycles exceeded".to_string()));
+            match self.step() {
+                ExitReason::Paused => {},
+                reason => return reason,
             }
+        }
+    }
 
-            // Execute one cycle
-            let should_stop = Executor::execute_cycle(&mut self.cpu, &mut self.memory)?;
-            
-            if should_stop {
-                break;
+    /// Execute at most `n_cycles` instructions, returning `Paused` if the program is still running
+    pub fn run_for(&mut self, n_cycles: u64) -> ExitReason {
+        for _ in 0..n_cycles {
+            match self.step() {
+                ExitReason::Paused => {},
+                reason => return reason,
             }
         }
+        ExitReason::Paused
+    }
 
-        Ok(())
+    /// Run until the PC reaches `pc`, returning `Breakpoint { pc }` before the
+    /// instruction there executes. At least one instruction is executed.
+    pub fn run_until(&mut self, pc: u32) -> ExitReason {
+        loop {
+            match self.step() {
+                ExitReason::Paused if self.cpu.get_pc() == pc => return ExitReason::Breakpoint { pc },
+                ExitReason::Paused => {},
+                reason => return reason,
+            }
+        }
+    }
+
+    /// Code passed to the exit syscall, once the guest has exited
+    pub fn exit_code(&self) -> Option<u32> {
+        self.cpu.exit_code
     }
 
-    /// Execute the program with proof generation
+    /// Execute the program with proof generation. The program must halt
+    /// through the exit syscall; any other stop is returned as an error.
     pub fn execute_with_proof(&mut self) -> Result<Proof, VmError> {
         if !self.config.enable_proofs {
             return Err(VmError::ProofError("Proof generation not enabled".to_string()));
         }
 
-        // For now, execute without proof and return a dummy proof
-        self.execute()?;
-        
-        // TODO: Implement actual proof generation
-        Ok(Proof::dummy())
+        let (reason, trace) = self.run_traced();
+        match reason {
+            ExitReason::Halted { .. } => Prover::new().generate_proof(&trace),
+            reason => Err(Self::exit_error(reason)),
+        }
+    }
+
+    /// Like `run`, but also record every step and memory access. The trace is
+    /// returned however execution stopped, so a faulting run can be inspected;
+    /// a step that faulted is included, with the registers left unchanged.
+    pub fn run_traced(&mut self) -> (ExitReason, ExecutionTrace) {
+        self.memory.record_accesses(true);
+        let mut trace = ExecutionTrace::default();
+        let reason = loop {
+            let cycle = self.cpu.get_cycles();
+            let instruction = self.instruction_bits();
+            let pc = self.cpu.get_pc();
+            let registers = self.cpu.xregs();
+
+            let reason = self.step();
+            if matches!(reason, ExitReason::CycleLimit) {
+                break reason;
+            }
+            trace.instructions.push(instruction);
+            trace.pcs.push(pc);
+            trace.cycles.push(cycle);
+            trace.register_states.push(registers);
+            trace.memory_accesses.extend(
+                self.memory.take_accesses().into_iter().map(|access| MemoryAccess { cycle, ..access }),
+            );
+
+            if !matches!(reason, ExitReason::Paused) {
+                break reason;
+            }
+        };
+        self.memory.record_accesses(false);
+
+        trace.register_states.push(self.cpu.xregs());
+        trace.cycle_count = self.cpu.get_cycles();
+        (reason, trace)
+    }
+
+    /// Raw encoding of the instruction at the PC, or 0 if it cannot be fetched
+    fn instruction_bits(&self) -> u32 {
+        let pc = self.cpu.get_pc();
+        let Ok(parcel) = self.memory.fetch_halfword(pc) else {
+            return 0;
+        };
+        if Decoder::is_compressed(parcel) {
+            return parcel as u32;
+        }
+        let high = self.memory.fetch_halfword(pc.wrapping_add(2)).unwrap_or(0);
+        parcel as u32 | (high as u32) << 16
     }
 
     /// Get the current CPU state
@@ -113,6 +260,23 @@ impl VirtualMachine {
         &mut self.memory
     }
 
+    /// Capture the CPU and memory state. Memory pages are shared with the
+    /// running VM until either side writes them, so snapshots are cheap.
+    /// Attached devices are shared, not copied.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            cpu: self.cpu.clone(),
+            memory: self.memory.clone(),
+        }
+    }
+
+    /// Restore the CPU and memory state captured by `snapshot`; the snapshot
+    /// can be restored any number of times
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.cpu = snapshot.cpu.clone();
+        self.memory = snapshot.memory.clone();
+    }
+
     /// Reset the virtual machine
     pub fn reset(&mut self) {
         self.cpu.reset();
@@ -123,6 +287,7 @@ impl VirtualMachine {
     pub fn get_stats(&self) -> VmStats {
         VmStats {
             cycles: self.cpu.get_cycles(),
+            instret: self.cpu.get_instret(),
             pc: self.cpu.get_pc(),
             memory_size: self.memory.size(),
         }
@@ -135,10 +300,57 @@ impl Default for VirtualMachine {
     }
 }
 
+/// Why execution stopped
+#[derive(Debug)]
+pub enum ExitReason {
+    /// The guest called the exit syscall
+    Halted { code: u32 },
+    /// `VmConfig::max_cycles` was reached
+    CycleLimit,
+    /// An EBREAK was executed, or `run_until` reached its target
+    Breakpoint { pc: u32 },
+    /// Execution failed at `pc` and the fault was not handled by the guest
+    Fault { pc: u32, error: VmError },
+    /// The requested number of instructions was executed and the program can continue
+    Paused,
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Halted { code } => write!(f, "exited with code {}", code),
+            Self::CycleLimit => write!(f, "cycle limit reached"),
+            Self::Breakpoint { pc } => write!(f, "breakpoint at {:08x}", pc),
+            Self::Fault { pc, error } => write!(f, "fault at {:08x}: {}", pc, error),
+            Self::Paused => write!(f, "paused"),
+        }
+    }
+}
+
+/// Saved VM state, created by `VirtualMachine::snapshot`
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    cpu: CpuState,
+    memory: Memory,
+}
+
+impl Snapshot {
+    /// CPU state at the time of the snapshot
+    pub fn cpu_state(&self) -> &CpuState {
+        &self.cpu
+    }
+
+    /// Memory at the time of the snapshot
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+}
+
 /// Virtual machine execution statistics
 #[derive(Debug, Clone)]
 pub struct VmStats {
     pub cycles: u64,
+    pub instret: u64,
     pub pc: u32,
     pub memory_size: usize,
 }
\ No newline at end of file
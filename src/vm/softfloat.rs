@@ -0,0 +1,684 @@
+//! Software IEEE 754 binary32/binary64 arithmetic for the F and D extensions
+//!
+//! Operations work on raw bit patterns using integer arithmetic only, so
+//! results and exception flags are identical on every host.
+
+use crate::risc_v::instructions::{FloatFormat, RoundingMode};
+
+/// Inexact exception flag
+pub const FLAG_NX: u32 = 0x01;
+/// Underflow exception flag
+pub const FLAG_UF: u32 = 0x02;
+/// Overflow exception flag
+pub const FLAG_OF: u32 = 0x04;
+/// Divide-by-zero exception flag
+pub const FLAG_DZ: u32 = 0x08;
+/// Invalid-operation exception flag
+pub const FLAG_NV: u32 = 0x10;
+
+/// IEEE 754 binary interchange format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    exp_bits: u32,
+    frac_bits: u32,
+}
+
+/// binary32
+pub const SINGLE: Format = Format { exp_bits: 8, frac_bits: 23 };
+/// binary64
+pub const DOUBLE: Format = Format { exp_bits: 11, frac_bits: 52 };
+
+impl From<FloatFormat> for Format {
+    fn from(fmt: FloatFormat) -> Self {
+        match fmt {
+            FloatFormat::Single => SINGLE,
+            FloatFormat::Double => DOUBLE,
+        }
+    }
+}
+
+impl Format {
+    /// Sign bit of an encoded value
+    pub fn sign_mask(self) -> u64 {
+        1 << (self.exp_bits + self.frac_bits)
+    }
+
+    /// Canonical quiet NaN
+    pub fn nan(self) -> u64 {
+        self.max_exp() << self.frac_bits | 1 << (self.frac_bits - 1)
+    }
+
+    /// Box a value for storage in a 64-bit FP register; single-precision
+    /// values are NaN-boxed with all upper bits set
+    pub fn nan_box(self, bits: u64) -> u64 {
+        if self == SINGLE {
+            bits | 0xffff_ffff_0000_0000
+        } else {
+            bits
+        }
+    }
+
+    /// Unbox a value read from a 64-bit FP register; improperly NaN-boxed
+    /// single-precision values read as the canonical NaN
+    pub fn unbox(self, reg: u64) -> u64 {
+        if self != SINGLE {
+            reg
+        } else if reg >> 32 == 0xffff_ffff {
+            reg & 0xffff_ffff
+        } else {
+            self.nan()
+        }
+    }
+
+    fn bias(self) -> i32 {
+        (1 << (self.exp_bits - 1)) - 1
+    }
+
+    /// Exponent field of infinities and NaNs
+    fn max_exp(self) -> u64 {
+        (1 << self.exp_bits) - 1
+    }
+
+    fn frac_mask(self) -> u64 {
+        (1 << self.frac_bits) - 1
+    }
+
+    /// Unbiased exponent of the smallest normal number
+    fn emin(self) -> i32 {
+        1 - self.bias()
+    }
+
+    fn sign(self, negative: bool) -> u64 {
+        if negative {
+            self.sign_mask()
+        } else {
+            0
+        }
+    }
+
+    fn zero(self, negative: bool) -> u64 {
+        self.sign(negative)
+    }
+
+    fn inf(self, negative: bool) -> u64 {
+        self.sign(negative) | self.max_exp() << self.frac_bits
+    }
+
+    fn max_finite(self, negative: bool) -> u64 {
+        self.sign(negative) | (self.max_exp() - 1) << self.frac_bits | self.frac_mask()
+    }
+
+    fn is_nan(self, bits: u64) -> bool {
+        matches!(self.unpack(bits), Value::Nan { .. })
+    }
+
+    fn is_signaling(self, bits: u64) -> bool {
+        matches!(self.unpack(bits), Value::Nan { signaling: true })
+    }
+
+    fn unpack(self, bits: u64) -> Value {
+        let negative = bits & self.sign_mask() != 0;
+        let exp = (bits >> self.frac_bits) & self.max_exp();
+        let frac = bits & self.frac_mask();
+
+        if exp == self.max_exp() {
+            if frac == 0 {
+                Value::Inf(negative)
+            } else {
+                Value::Nan { signaling: frac >> (self.frac_bits - 1) == 0 }
+            }
+        } else if exp == 0 {
+            if frac == 0 {
+                Value::Zero(negative)
+            } else {
+                let exp = self.emin() - self.frac_bits as i32;
+                Value::Finite(Finite { negative, exp, sig: frac as u128 })
+            }
+        } else {
+            let exp = exp as i32 - self.bias() - self.frac_bits as i32;
+            Value::Finite(Finite { negative, exp, sig: (frac | 1 << self.frac_bits) as u128 })
+        }
+    }
+
+    /// Total-order key for non-NaN values, with both zeros equal
+    fn key(self, bits: u64) -> i128 {
+        let magnitude = (bits & !self.sign_mask()) as i128;
+        if bits & self.sign_mask() != 0 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+/// A decoded floating-point value
+#[derive(Debug, Clone, Copy)]
+enum Value {
+    Nan { signaling: bool },
+    Inf(bool),
+    Zero(bool),
+    Finite(Finite),
+}
+
+/// A nonzero finite value `(-1)^negative * sig * 2^exp`
+#[derive(Debug, Clone, Copy)]
+struct Finite {
+    negative: bool,
+    exp: i32,
+    sig: u128,
+}
+
+impl Finite {
+    /// Shift the significand so its most significant bit is at `bit`
+    fn normalized(self, bit: u32) -> Self {
+        let shift = bit as i32 - (127 - self.sig.leading_zeros() as i32);
+        Self { exp: self.exp - shift, sig: self.sig << shift, ..self }
+    }
+}
+
+/// Shift right, ORing any bits shifted out into the least significant bit
+fn shift_right_jam(sig: u128, shift: u32) -> u128 {
+    if shift >= 128 {
+        (sig != 0) as u128
+    } else {
+        sig >> shift | (sig & ((1 << shift) - 1) != 0) as u128
+    }
+}
+
+/// Integer square root (floor)
+fn isqrt(n: u128) -> u128 {
+    let mut rem = n;
+    let mut root = 0u128;
+    let mut bit = 1u128 << 126;
+    while bit > n {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rem >= root + bit {
+            rem -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
+
+/// Floating-point unit state for a single operation: the format, a resolved
+/// rounding mode and the exception flags raised so far
+#[derive(Debug, Clone)]
+pub struct SoftFloat {
+    fmt: Format,
+    rm: RoundingMode,
+    /// Accrued exception flags (`FLAG_*`)
+    pub flags: u32,
+}
+
+impl SoftFloat {
+    /// Create a context for `fmt`; `rm` must not be `RoundingMode::Dyn`
+    pub fn new(fmt: Format, rm: RoundingMode) -> Self {
+        Self { fmt, rm, flags: 0 }
+    }
+
+    /// `a + b`
+    pub fn add(&mut self, a: u64, b: u64) -> u64 {
+        self.add_signed(a, b, false)
+    }
+
+    /// `a - b`
+    pub fn sub(&mut self, a: u64, b: u64) -> u64 {
+        self.add_signed(a, b, true)
+    }
+
+    /// `a * b`
+    pub fn mul(&mut self, a: u64, b: u64) -> u64 {
+        let fmt = self.fmt;
+        let (va, vb) = (fmt.unpack(a), fmt.unpack(b));
+        if let Some(nan) = self.propagate_nan(&[va, vb]) {
+            return nan;
+        }
+
+        match (va, vb) {
+            (Value::Inf(_), Value::Zero(_)) | (Value::Zero(_), Value::Inf(_)) => self.invalid(),
+            (Value::Inf(sa), v) | (v, Value::Inf(sa)) => fmt.inf(sa ^ negative(v)),
+            (Value::Zero(sa), v) | (v, Value::Zero(sa)) => fmt.zero(sa ^ negative(v)),
+            (Value::Finite(x), Value::Finite(y)) => {
+                self.round_pack(x.negative ^ y.negative, x.exp + y.exp, x.sig * y.sig, false)
+            },
+            _ => unreachable!("NaN operands are handled above"),
+        }
+    }
+
+    /// `a / b`
+    pub fn div(&mut self, a: u64, b: u64) -> u64 {
+        let fmt = self.fmt;
+        let (va, vb) = (fmt.unpack(a), fmt.unpack(b));
+        if let Some(nan) = self.propagate_nan(&[va, vb]) {
+            return nan;
+        }
+        let sign = negative(va) ^ negative(vb);
+
+        match (va, vb) {
+            (Value::Inf(_), Value::Inf(_)) | (Value::Zero(_), Value::Zero(_)) => self.invalid(),
+            (Value::Inf(_), _) => fmt.inf(sign),
+            (_, Value::Inf(_)) | (Value::Zero(_), _) => fmt.zero(sign),
+            (_, Value::Zero(_)) => {
+                self.flags |= FLAG_DZ;
+                fmt.inf(sign)
+            },
+            (Value::Finite(x), Value::Finite(y)) => {
+                let (x, y) = (x.normalized(63), y.normalized(63));
+                let dividend = x.sig << 64;
+                let quotient = dividend / y.sig;
+                let remainder = dividend % y.sig;
+                self.round_pack(sign, x.exp - y.exp - 64, quotient, remainder != 0)
+            },
+            _ => unreachable!("NaN operands are handled above"),
+        }
+    }
+
+    /// Square root of `a`
+    pub fn sqrt(&mut self, a: u64) -> u64 {
+        let fmt = self.fmt;
+        let va = fmt.unpack(a);
+        if let Some(nan) = self.propagate_nan(&[va]) {
+            return nan;
+        }
+
+        match va {
+            // sqrt(-0) = -0
+            Value::Zero(sign) => fmt.zero(sign),
+            Value::Inf(true) | Value::Finite(Finite { negative: true, .. }) => self.invalid(),
+            Value::Inf(false) => fmt.inf(false),
+            Value::Finite(x) => {
+                // Make the exponent even so it can be halved exactly
+                let mut x = x.normalized(63);
+                if x.exp & 1 != 0 {
+                    x.sig <<= 1;
+                    x.exp -= 1;
+                }
+                let radicand = x.sig << 62;
+                let root = isqrt(radicand);
+                self.round_pack(false, (x.exp - 62) / 2, root, root * root != radicand)
+            },
+            _ => unreachable!("NaN operands are handled above"),
+        }
+    }
+
+    /// Fused `(a * b) + c`, negating the product and/or addend as requested,
+    /// with a single rounding
+    pub fn fma(&mut self, a: u64, b: u64, c: u64, negate_product: bool, negate_addend: bool) -> u64 {
+        let fmt = self.fmt;
+        let (va, vb, vc) = (fmt.unpack(a), fmt.unpack(b), fmt.unpack(c));
+
+        // inf * 0 is invalid even when the addend is a quiet NaN
+        let inf_times_zero = matches!(
+            (va, vb),
+            (Value::Inf(_), Value::Zero(_)) | (Value::Zero(_), Value::Inf(_))
+        );
+        if inf_times_zero {
+            self.flags |= FLAG_NV;
+        }
+        if let Some(nan) = self.propagate_nan(&[va, vb, vc]) {
+            return nan;
+        }
+        if inf_times_zero {
+            return fmt.nan();
+        }
+
+        let product_sign = negative(va) ^ negative(vb) ^ negate_product;
+        let addend_sign = negative(vc) ^ negate_addend;
+
+        match (va, vb, vc) {
+            (Value::Inf(_), _, _) | (_, Value::Inf(_), _) => match vc {
+                // inf - inf
+                Value::Inf(_) if addend_sign != product_sign => self.invalid(),
+                _ => fmt.inf(product_sign),
+            },
+            (_, _, Value::Inf(_)) => fmt.inf(addend_sign),
+            (Value::Zero(_), _, Value::Zero(_)) | (_, Value::Zero(_), Value::Zero(_)) => {
+                fmt.zero(self.zero_sum_sign(product_sign, addend_sign))
+            },
+            (Value::Zero(_), _, _) | (_, Value::Zero(_), _) => c ^ fmt.sign(negate_addend),
+            (Value::Finite(x), Value::Finite(y), Value::Zero(_)) => {
+                self.round_pack(product_sign, x.exp + y.exp, x.sig * y.sig, false)
+            },
+            (Value::Finite(x), Value::Finite(y), Value::Finite(z)) => {
+                let product = Finite { negative: product_sign, exp: x.exp + y.exp, sig: x.sig * y.sig };
+                self.add_finite(product, Finite { negative: addend_sign, ..z })
+            },
+            _ => unreachable!("NaN operands are handled above"),
+        }
+    }
+
+    /// Quiet equality comparison; only signaling NaNs raise invalid
+    pub fn eq(&mut self, a: u64, b: u64) -> bool {
+        let fmt = self.fmt;
+        if fmt.is_signaling(a) || fmt.is_signaling(b) {
+            self.flags |= FLAG_NV;
+        }
+        !fmt.is_nan(a) && !fmt.is_nan(b) && fmt.key(a) == fmt.key(b)
+    }
+
+    /// Signaling less-than comparison; any NaN raises invalid
+    pub fn lt(&mut self, a: u64, b: u64) -> bool {
+        self.ordered(a, b) && self.fmt.key(a) < self.fmt.key(b)
+    }
+
+    /// Signaling less-than-or-equal comparison; any NaN raises invalid
+    pub fn le(&mut self, a: u64, b: u64) -> bool {
+        self.ordered(a, b) && self.fmt.key(a) <= self.fmt.key(b)
+    }
+
+    /// IEEE 754-2019 minimumNumber: a NaN operand yields the other operand, and -0 < +0
+    pub fn min(&mut self, a: u64, b: u64) -> u64 {
+        self.min_max(a, b, true)
+    }
+
+    /// IEEE 754-2019 maximumNumber: a NaN operand yields the other operand, and -0 < +0
+    pub fn max(&mut self, a: u64, b: u64) -> u64 {
+        self.min_max(a, b, false)
+    }
+
+    /// The `fclass` bit mask for a value
+    pub fn classify(&self, a: u64) -> u32 {
+        let fmt = self.fmt;
+        let subnormal = (a >> fmt.frac_bits) & fmt.max_exp() == 0;
+        match fmt.unpack(a) {
+            Value::Inf(true) => 1 << 0,
+            Value::Finite(x) if x.negative && !subnormal => 1 << 1,
+            Value::Finite(x) if x.negative => 1 << 2,
+            Value::Zero(true) => 1 << 3,
+            Value::Zero(false) => 1 << 4,
+            Value::Finite(_) if subnormal => 1 << 5,
+            Value::Finite(_) => 1 << 6,
+            Value::Inf(false) => 1 << 7,
+            Value::Nan { signaling: true } => 1 << 8,
+            Value::Nan { signaling: false } => 1 << 9,
+        }
+    }
+
+    /// Convert to a 32-bit integer, rounding with the current mode. Out of
+    /// range values and NaNs saturate and raise invalid.
+    pub fn to_int(&mut self, a: u64, signed: bool) -> u32 {
+        if signed {
+            self.round_to_int(a, i32::MIN as i128, i32::MAX as i128) as u32
+        } else {
+            self.round_to_int(a, 0, u32::MAX as i128) as u32
+        }
+    }
+
+    /// Convert to a 64-bit integer, rounding with the current mode. Out of
+    /// range values and NaNs saturate and raise invalid.
+    pub fn to_long(&mut self, a: u64, signed: bool) -> u64 {
+        if signed {
+            self.round_to_int(a, i64::MIN as i128, i64::MAX as i128) as u64
+        } else {
+            self.round_to_int(a, 0, u64::MAX as i128) as u64
+        }
+    }
+
+    /// Convert to an integer in `min..=max`
+    fn round_to_int(&mut self, a: u64, min: i128, max: i128) -> i128 {
+        let value = match self.fmt.unpack(a) {
+            Value::Nan { .. } => Err(max),
+            Value::Inf(sign) => Err(if sign { min } else { max }),
+            Value::Zero(_) => Ok(0),
+            // Anything of magnitude 2^72 or more is out of range
+            Value::Finite(x) if x.exp > 72 => Err(if x.negative { min } else { max }),
+            Value::Finite(x) => {
+                let (magnitude, inexact) = self.round_at(x.negative, x.sig, false, -x.exp);
+                let value = if x.negative { -(magnitude as i128) } else { magnitude as i128 };
+                if value < min || value > max {
+                    Err(if x.negative { min } else { max })
+                } else {
+                    if inexact {
+                        self.flags |= FLAG_NX;
+                    }
+                    Ok(value)
+                }
+            },
+        };
+
+        value.unwrap_or_else(|saturated| {
+            self.flags |= FLAG_NV;
+            saturated
+        })
+    }
+
+    /// Convert a 32-bit integer, rounding with the current mode
+    pub fn from_int(&mut self, value: u32, signed: bool) -> u64 {
+        if signed {
+            self.from_long(value as i32 as u64, true)
+        } else {
+            self.from_long(value as u64, false)
+        }
+    }
+
+    /// Convert a 64-bit integer, rounding with the current mode
+    pub fn from_long(&mut self, value: u64, signed: bool) -> u64 {
+        let (negative, magnitude) = if signed && (value as i64) < 0 {
+            (true, (value as i64).unsigned_abs())
+        } else {
+            (false, value)
+        };
+
+        if magnitude == 0 {
+            return self.fmt.zero(false);
+        }
+        self.round_pack(negative, 0, magnitude as u128, false)
+    }
+
+    /// Convert a value encoded in format `from` to the current format
+    pub fn convert(&mut self, a: u64, from: Format) -> u64 {
+        let fmt = self.fmt;
+        let va = from.unpack(a);
+        if let Some(nan) = self.propagate_nan(&[va]) {
+            return nan;
+        }
+
+        match va {
+            Value::Inf(sign) => fmt.inf(sign),
+            Value::Zero(sign) => fmt.zero(sign),
+            Value::Finite(x) => self.round_pack(x.negative, x.exp, x.sig, false),
+            Value::Nan { .. } => unreachable!("NaN operands are handled above"),
+        }
+    }
+
+    fn add_signed(&mut self, a: u64, b: u64, subtract: bool) -> u64 {
+        let fmt = self.fmt;
+        let b = b ^ fmt.sign(subtract);
+        let (va, vb) = (fmt.unpack(a), fmt.unpack(b));
+        if let Some(nan) = self.propagate_nan(&[va, vb]) {
+            return nan;
+        }
+
+        match (va, vb) {
+            (Value::Inf(sa), Value::Inf(sb)) if sa != sb => self.invalid(),
+            (Value::Inf(sign), _) | (_, Value::Inf(sign)) => fmt.inf(sign),
+            (Value::Zero(sa), Value::Zero(sb)) => fmt.zero(self.zero_sum_sign(sa, sb)),
+            (Value::Zero(_), _) => b,
+            (_, Value::Zero(_)) => a,
+            (Value::Finite(x), Value::Finite(y)) => self.add_finite(x, y),
+            _ => unreachable!("NaN operands are handled above"),
+        }
+    }
+
+    /// Add two nonzero finite values with a single rounding
+    fn add_finite(&mut self, x: Finite, y: Finite) -> u64 {
+        // Align both significands at bit 119, leaving headroom for the sum
+        let (mut x, mut y) = (x.normalized(119), y.normalized(119));
+        if x.exp < y.exp {
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        // Small exponent differences shift the larger operand left exactly;
+        // larger ones leave the smaller operand below the rounding position
+        let diff = (x.exp - y.exp) as u32;
+        let (xs, ys, exp) = if diff <= 7 {
+            (x.sig << diff, y.sig, y.exp)
+        } else {
+            (x.sig, shift_right_jam(y.sig, diff), x.exp)
+        };
+
+        let (negative, sig) = if x.negative == y.negative {
+            (x.negative, xs + ys)
+        } else if xs >= ys {
+            (x.negative, xs - ys)
+        } else {
+            (y.negative, ys - xs)
+        };
+
+        if sig == 0 {
+            // Exact cancellation gives +0, or -0 when rounding down
+            return self.fmt.zero(self.rm == RoundingMode::Rdn);
+        }
+        self.round_pack(negative, exp, sig, false)
+    }
+
+    /// Round `(-1)^negative * (sig + sticky) * 2^exp` to the current format,
+    /// where a set `sticky` stands for a nonzero fraction below `sig`
+    fn round_pack(&mut self, negative: bool, exp: i32, sig: u128, sticky: bool) -> u64 {
+        let fmt = self.fmt;
+        let p = fmt.frac_bits as i32;
+
+        // The value lies in [2^e, 2^(e+1))
+        let e = exp + (127 - sig.leading_zeros() as i32);
+
+        // Tininess is detected after rounding, as if the exponent range were unbounded
+        let tiny = e < fmt.emin() && {
+            let (unbounded, _) = self.round_at(negative, sig, sticky, e - p - exp);
+            !(e + 1 == fmt.emin() && unbounded >> (p + 1) != 0)
+        };
+
+        // Subnormal results keep the least significant bit at 2^(emin - p)
+        let mut lsb = e.max(fmt.emin()) - p;
+        let (mut m, inexact) = self.round_at(negative, sig, sticky, lsb - exp);
+        if m >> (p + 1) != 0 {
+            m >>= 1;
+            lsb += 1;
+        }
+
+        if inexact {
+            self.flags |= FLAG_NX;
+            if tiny {
+                self.flags |= FLAG_UF;
+            }
+        }
+        if m == 0 {
+            return fmt.zero(negative);
+        }
+
+        let biased = if m >> p != 0 { lsb + p + fmt.bias() } else { 0 };
+        if biased >= fmt.max_exp() as i32 {
+            self.flags |= FLAG_OF | FLAG_NX;
+            let to_inf = match self.rm {
+                RoundingMode::Rtz => false,
+                RoundingMode::Rdn => negative,
+                RoundingMode::Rup => !negative,
+                _ => true,
+            };
+            return if to_inf { fmt.inf(negative) } else { fmt.max_finite(negative) };
+        }
+
+        fmt.sign(negative) | (biased as u64) << p | (m as u64 & fmt.frac_mask())
+    }
+
+    /// Shift `sig` (plus `sticky`) right by `shift` bits, rounding to an
+    /// integer with the current mode. Returns the rounded value and whether
+    /// it is inexact.
+    fn round_at(&self, negative: bool, sig: u128, sticky: bool, shift: i32) -> (u128, bool) {
+        let (kept, half, rest) = if shift <= 0 {
+            (sig << -shift, false, sticky)
+        } else {
+            let shift = shift as u32;
+            let kept = sig.checked_shr(shift).unwrap_or(0);
+            let half = sig.checked_shr(shift - 1).unwrap_or(0) & 1 != 0;
+            let below = 1u128.checked_shl(shift - 1).map_or(u128::MAX, |bit| bit - 1);
+            (kept, half, sig & below != 0 || sticky)
+        };
+
+        let inexact = half || rest;
+        let round_up = match self.rm {
+            RoundingMode::Rtz => false,
+            RoundingMode::Rdn => negative && inexact,
+            RoundingMode::Rup => !negative && inexact,
+            RoundingMode::Rmm => half,
+            // `Dyn` is resolved by the caller; treat it like the default mode
+            RoundingMode::Rne | RoundingMode::Dyn => half && (rest || kept & 1 != 0),
+        };
+        (kept + round_up as u128, inexact)
+    }
+
+    /// Sign of an exact zero sum of two zeros or of a cancellation
+    fn zero_sum_sign(&self, sa: bool, sb: bool) -> bool {
+        if sa == sb {
+            sa
+        } else {
+            self.rm == RoundingMode::Rdn
+        }
+    }
+
+    /// If any operand is a NaN, return the canonical NaN, raising invalid for
+    /// signaling NaNs
+    fn propagate_nan(&mut self, values: &[Value]) -> Option<u64> {
+        let mut nan = false;
+        for value in values {
+            if let Value::Nan { signaling } = value {
+                nan = true;
+                if *signaling {
+                    self.flags |= FLAG_NV;
+                }
+            }
+        }
+        nan.then(|| self.fmt.nan())
+    }
+
+    fn invalid(&mut self) -> u64 {
+        self.flags |= FLAG_NV;
+        self.fmt.nan()
+    }
+
+    /// Whether neither operand is a NaN, raising invalid otherwise
+    fn ordered(&mut self, a: u64, b: u64) -> bool {
+        if self.fmt.is_nan(a) || self.fmt.is_nan(b) {
+            self.flags |= FLAG_NV;
+            return false;
+        }
+        true
+    }
+
+    fn min_max(&mut self, a: u64, b: u64, min: bool) -> u64 {
+        let fmt = self.fmt;
+        if fmt.is_signaling(a) || fmt.is_signaling(b) {
+            self.flags |= FLAG_NV;
+        }
+
+        match (fmt.is_nan(a), fmt.is_nan(b)) {
+            (true, true) => fmt.nan(),
+            (true, false) => b,
+            (false, true) => a,
+            _ => {
+                let (ka, kb) = (fmt.key(a), fmt.key(b));
+                if ka == kb {
+                    // Distinguish the zeros: min prefers -0, max prefers +0
+                    let a_negative = a & fmt.sign_mask() != 0;
+                    if a_negative == min { a } else { b }
+                } else if (ka < kb) == min {
+                    a
+                } else {
+                    b
+                }
+            },
+        }
+    }
+}
+
+fn negative(value: Value) -> bool {
+    match value {
+        Value::Inf(sign) | Value::Zero(sign) => sign,
+        Value::Finite(x) => x.negative,
+        Value::Nan { .. } => false,
+    }
+}
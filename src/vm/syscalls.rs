@@ -49,6 +49,7 @@ impl SyscallHandler {
             Syscall::Exit => {
                 // Exit with code in a0
                 tracing::info!("Program exited with code: {}", arg0);
+                cpu.exit_code = Some(arg0);
                 return Ok(true); // Signal VM to stop
             },
 
@@ -59,17 +60,23 @@ impl SyscallHandler {
                 let count = arg2;
 
                 if fd == 1 || fd == 2 {
-                    // stdout or stderr
-                    let data = memory.load_bytes(buf_addr, count as usize)
+                    // stdout or stderr; the buffer is read with byte loads so
+                    // that the reads are recorded in execution traces
+                    let data = (0..count)
+                        .map(|i| match buf_addr.checked_add(i) {
+                            Some(addr) => memory.load_byte(addr),
+                            None => Err(VmError::MemoryViolation { address: buf_addr }),
+                        })
+                        .collect::<Result<Vec<u8>, VmError>>()
                         .map_err(|_| VmError::SyscallError("Invalid buffer address".to_string()))?;
                     
                     if fd == 1 {
-                        io::stdout().write_all(data)
+                        io::stdout().write_all(&data)
                             .map_err(|e| VmError::SyscallError(format!("Write failed: {}", e)))?;
                         io::stdout().flush()
                             .map_err(|e| VmError::SyscallError(format!("Flush failed: {}", e)))?;
                     } else {
-                        io::stderr().write_all(data)
+                        io::stderr().write_all(&data)
                             .map_err(|e| VmError::SyscallError(format!("Write failed: {}", e)))?;
                         io::stderr().flush()
                             .map_err(|e| VmError::SyscallError(format!("Flush failed: {}", e)))?;
@@ -0,0 +1,79 @@
+//! Machine-mode trap handling
+
+use crate::risc_v::Instruction;
+use crate::utils::VmError;
+use crate::vm::memory::AccessKind;
+
+/// Synchronous exception causes (the `mcause` exception codes)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exception {
+    InstructionAddressMisaligned = 0,
+    InstructionAccessFault = 1,
+    IllegalInstruction = 2,
+    Breakpoint = 3,
+    LoadAddressMisaligned = 4,
+    LoadAccessFault = 5,
+    StoreAddressMisaligned = 6,
+    StoreAccessFault = 7,
+    EcallFromMMode = 11,
+}
+
+impl Exception {
+    /// Get the exception code written to `mcause`
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+}
+
+/// A pending trap: the exception cause and the value written to `mtval`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trap {
+    pub cause: Exception,
+    pub tval: u32,
+}
+
+impl Trap {
+    pub fn new(cause: Exception, tval: u32) -> Self {
+        Self { cause, tval }
+    }
+
+    /// Classify a fault as an architectural exception.
+    ///
+    /// `instruction` is the instruction being executed, or `None` if the fault
+    /// happened while fetching or decoding it. Host-level failures (syscall and
+    /// I/O errors, exceeded limits) return `None` and always abort execution.
+    pub fn from_error(err: &VmError, instruction: Option<&Instruction>) -> Option<Self> {
+        match err {
+            VmError::InvalidInstruction(word) => {
+                Some(Self::new(Exception::IllegalInstruction, *word))
+            },
+            VmError::InvalidCsr(_) => Some(Self::new(Exception::IllegalInstruction, 0)),
+            VmError::MemoryViolation { address } => {
+                let cause = match instruction {
+                    None => Exception::InstructionAccessFault,
+                    Some(i) if i.is_store() => Exception::StoreAccessFault,
+                    Some(_) => Exception::LoadAccessFault,
+                };
+                Some(Self::new(cause, *address))
+            },
+            VmError::AccessFault { address, access } => {
+                let cause = match access {
+                    AccessKind::Read => Exception::LoadAccessFault,
+                    AccessKind::Write => Exception::StoreAccessFault,
+                    AccessKind::Execute => Exception::InstructionAccessFault,
+                };
+                Some(Self::new(cause, *address))
+            },
+            VmError::MisalignedAccess { address, access } => {
+                let cause = match access {
+                    AccessKind::Read => Exception::LoadAddressMisaligned,
+                    AccessKind::Write => Exception::StoreAddressMisaligned,
+                    AccessKind::Execute => Exception::InstructionAddressMisaligned,
+                };
+                Some(Self::new(cause, *address))
+            },
+            VmError::Breakpoint { pc } => Some(Self::new(Exception::Breakpoint, *pc)),
+            _ => None,
+        }
+    }
+}
@@ -16,4 +16,10 @@ impl CircuitBuilder {
         // TODO: Implement instruction circuit generation
         Ok(())
     }
+}
+
+impl Default for CircuitBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file
@@ -16,4 +16,10 @@ impl ConstraintSystem {
         // TODO: Implement constraint addition
         Ok(())
     }
+}
+
+impl Default for ConstraintSystem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file
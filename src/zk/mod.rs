@@ -35,20 +35,36 @@ pub trait ProofSystem {
     fn verify_proof(&self, proof: &Proof, public_inputs: &[u8]) -> Result<bool, crate::utils::VmError>;
 }
 
-/// Execution trace for proof generation
-#[derive(Debug, Clone)]
+/// Execution trace for proof generation.
+///
+/// `instructions`, `pcs` and `cycles` have one entry per executed step.
+/// `register_states` has one more: the state before each step, then the
+/// final state, so step `i` goes from `register_states[i]` to
+/// `register_states[i + 1]`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ExecutionTrace {
+    /// Raw encoding of each fetched instruction (16-bit parcels for RVC)
     pub instructions: Vec<u32>,
-    pub register_states: Vec<[u32; 32]>,
+    /// Address of each instruction
+    pub pcs: Vec<u32>,
+    /// Cycle count at the start of each step
+    pub cycles: Vec<u64>,
+    /// Integer registers, zero-extended from XLEN
+    pub register_states: Vec<[u64; 32]>,
+    /// Loads and stores in execution order
     pub memory_accesses: Vec<MemoryAccess>,
     pub cycle_count: u64,
 }
 
 /// Memory access record
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MemoryAccess {
     pub address: u32,
-    pub value: u32,
+    /// Value loaded or stored, zero-extended
+    pub value: u64,
+    /// Access width in bytes
+    pub width: u8,
     pub is_write: bool,
+    /// Cycle count at the start of the instruction making the access
     pub cycle: u64,
 }
\ No newline at end of file
@@ -14,6 +14,12 @@ impl Prover {
     }
 }
 
+impl Default for Prover {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ProofSystem for Prover {
     fn generate_proof(&self, _trace: &ExecutionTrace) -> Result<Proof, VmError> {
         // TODO: Implement actual proof generation
@@ -18,4 +18,10 @@ impl Verifier {
         let _ = (proof, public_inputs);
         Ok(true)
     }
+}
+
+impl Default for Verifier {
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file
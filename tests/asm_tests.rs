@@ -0,0 +1,146 @@
+//! Tests for the assembler
+
+use zk_risc_v_vm::risc_v::{Assembler, Decoder, Instruction, RegisterIndex};
+use zk_risc_v_vm::{VirtualMachine, VmError};
+
+fn words(source: &str) -> Result<Vec<u32>, VmError> {
+    let program = Assembler::new(0x1000).assemble(source)?;
+    Ok(program
+        .data
+        .chunks(4)
+        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
+
+#[test]
+fn test_assemble_matches_known_encodings() -> Result<(), VmError> {
+    let source = "
+        addi x1, x0, 42
+        add gp, ra, sp      # ABI names
+        lw a0, 8(sp)
+        sw a1, 12(sp)
+        amoadd.w gp, sp, (ra)
+        csrr a0, cycle
+        fence
+        fence rw, w
+        fence.tso
+        fence.i
+        ecall
+    ";
+    assert_eq!(
+        words(source)?,
+        [
+            0x02a00093, 0x002081b3, 0x00812503, 0x00b12623, 0x0020a1af, 0xc0002573,
+            0x0ff0000f, 0x0310000f, 0x8330000f, 0x0000100f, 0x00000073,
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_labels_and_pseudo_instructions() -> Result<(), VmError> {
+    use RegisterIndex::*;
+
+    let source = "
+        _start:
+            li t0, 0x12345678
+            la a0, msg
+            call func
+        loop: j loop
+        func:
+            ret
+        msg: .asciz \"hi; #\"
+    ";
+    let program = Assembler::new(0x1000).assemble(source)?;
+    assert_eq!(program.entry, 0x1000);
+    assert_eq!(program.symbols["loop"], 0x1018);
+    assert_eq!(program.symbols["func"], 0x101c);
+    assert_eq!(program.symbols["msg"], 0x1020);
+    assert_eq!(&program.data[0x20..], b"hi; #\0");
+
+    let decoded: Vec<Instruction> = program.data[..0x20]
+        .chunks(4)
+        .map(|chunk| Decoder::decode(u32::from_le_bytes(chunk.try_into().unwrap())))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(
+        decoded,
+        [
+            Instruction::Lui { rd: X5, imm: 0x12345000 },
+            Instruction::Addi { rd: X5, rs1: X5, imm: 0x678 },
+            Instruction::Auipc { rd: X10, imm: 0 },
+            Instruction::Addi { rd: X10, rs1: X10, imm: 0x18 },
+            Instruction::Auipc { rd: X1, imm: 0 },
+            Instruction::Jalr { rd: X1, rs1: X1, imm: 0xc },
+            Instruction::Jal { rd: X0, imm: 0 },
+            Instruction::Jalr { rd: X0, rs1: X1, imm: 0 },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_data_directives() -> Result<(), VmError> {
+    let program = Assembler::new(0x2000).assemble(
+        "
+        .byte 1, 0xff
+        .align 2
+        table: .word table, -1
+        .half 0x1234
+        .ascii \"ok\"
+        .byte '#', ',' # comment
+        ",
+    )?;
+    assert_eq!(
+        program.data,
+        [1, 0xff, 0, 0, 0x04, 0x20, 0, 0, 0xff, 0xff, 0xff, 0xff, 0x34, 0x12, b'o', b'k', b'#', b',']
+    );
+    Ok(())
+}
+
+#[test]
+fn test_assembly_errors_report_line() {
+    let cases = [
+        ("nop\nfoo x1, x2", 2),
+        ("addi x1, x0, 4096", 1),
+        ("\n\nbeq x1, x2, missing", 3),
+        ("a:\na:", 2),
+        ("add x1, x2", 1),
+        // Values that would wrap into range when truncated to 32 bits
+        ("addi a0, a0, 4294967295", 1),
+        ("nop\nlw a0, 4294967296(sp)", 2),
+        ("jalr ra, a0, 4294967295", 1),
+        ("csrrwi a0, mscratch, 4294967297", 1),
+        ("li a0, -0x8000000000000000", 1),
+        (".space -1", 1),
+    ];
+    for (source, expected) in cases {
+        match Assembler::new(0).assemble(source) {
+            Err(VmError::AssemblyError { line, .. }) => assert_eq!(line, expected, "{:?}", source),
+            other => panic!("{:?} should fail to assemble, got {:?}", source, other),
+        }
+    }
+}
+
+#[test]
+fn test_load_asm_and_execute() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(
+        0x1000,
+        "
+        data: .word 5
+        _start:
+            la t0, data
+            lw t1, 0(t0)
+            li a0, 0
+        loop:
+            add a0, a0, t1
+            addi t1, t1, -1
+            bnez t1, loop
+            li a7, 93
+            ecall
+        ",
+    )?;
+    vm.execute()?;
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X10), 15);
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Tests for the Zba/Zbb/Zbs bit-manipulation extensions
+
+mod common;
+
+use common::run;
+use zk_risc_v_vm::utils::config::VmConfig;
+use zk_risc_v_vm::{VirtualMachine, VmError};
+
+#[test]
+fn test_zba_shift_add() -> Result<(), VmError> {
+    assert_eq!(run("sh1add a0, a0, a1", 3, 100)?, 106);
+    assert_eq!(run("sh2add a0, a0, a1", 3, 100)?, 112);
+    assert_eq!(run("sh3add a0, a0, a1", 0x2000_0001, 1)?, 9);
+    Ok(())
+}
+
+#[test]
+fn test_zbb_logic_and_counts() -> Result<(), VmError> {
+    assert_eq!(run("andn a0, a0, a1", 0xff, 0x0f)?, 0xf0);
+    assert_eq!(run("orn a0, a0, a1", 0, 0xffff_fff0)?, 0xf);
+    assert_eq!(run("xnor a0, a0, a1", 0xf0f0_f0f0, 0xff00_ff00)?, 0xf00f_f00f);
+    assert_eq!(run("clz a0, a0", 0x0001_0000, 0)?, 15);
+    assert_eq!(run("clz a0, a0", 0, 0)?, 32);
+    assert_eq!(run("ctz a0, a0", 0x0001_0000, 0)?, 16);
+    assert_eq!(run("cpop a0, a0", 0xf0f0_0001, 0)?, 9);
+    Ok(())
+}
+
+#[test]
+fn test_zbb_min_max_and_extension() -> Result<(), VmError> {
+    assert_eq!(run("min a0, a0, a1", -5i32 as u32, 3)?, -5i32 as u32);
+    assert_eq!(run("minu a0, a0, a1", -5i32 as u32, 3)?, 3);
+    assert_eq!(run("max a0, a0, a1", -5i32 as u32, 3)?, 3);
+    assert_eq!(run("maxu a0, a0, a1", -5i32 as u32, 3)?, -5i32 as u32);
+    assert_eq!(run("sext.b a0, a0", 0x1280, 0)?, 0xffff_ff80);
+    assert_eq!(run("sext.h a0, a0", 0x1_8000, 0)?, 0xffff_8000);
+    assert_eq!(run("zext.h a0, a0", 0xdead_beef, 0)?, 0xbeef);
+    Ok(())
+}
+
+#[test]
+fn test_zbb_rotates_and_bytes() -> Result<(), VmError> {
+    assert_eq!(run("rol a0, a0, a1", 0x8000_0001, 33)?, 0x0000_0003);
+    assert_eq!(run("ror a0, a0, a1", 0x8000_0001, 1)?, 0xc000_0000);
+    assert_eq!(run("rori a0, a0, 8", 0x1234_5678, 0)?, 0x7812_3456);
+    assert_eq!(run("orc.b a0, a0", 0x0100_2000, 0)?, 0xff00_ff00);
+    assert_eq!(run("rev8 a0, a0", 0x1234_5678, 0)?, 0x7856_3412);
+    Ok(())
+}
+
+#[test]
+fn test_zbs_single_bit() -> Result<(), VmError> {
+    assert_eq!(run("bset a0, a0, a1", 0, 35)?, 0x8);
+    assert_eq!(run("bclr a0, a0, a1", 0xff, 0)?, 0xfe);
+    assert_eq!(run("binv a0, a0, a1", 0x10, 4)?, 0);
+    assert_eq!(run("bext a0, a0, a1", 0x10, 4)?, 1);
+    assert_eq!(run("bseti a0, a0, 31", 0, 0)?, 0x8000_0000);
+    assert_eq!(run("bclri a0, a0, 31", u32::MAX, 0)?, 0x7fff_ffff);
+    assert_eq!(run("binvi a0, a0, 1", 0, 0)?, 2);
+    assert_eq!(run("bexti a0, a0, 1", 1, 0)?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_bitmanip_is_illegal_when_disabled() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::with_config(VmConfig { isa: "rv32im".parse()?, ..VmConfig::default() });
+    vm.load_asm(0x1000, "cpop a0, a0")?;
+    assert!(matches!(vm.execute(), Err(VmError::InvalidInstruction(0x60251513))));
+    Ok(())
+}
@@ -0,0 +1,137 @@
+//! Tests for the zkvm command-line interface
+
+mod common;
+
+use common::{program_elf, write_temp};
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn zkvm(args: &[&str], path: &PathBuf) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_zkvm"))
+        .arg("execute")
+        .arg("--file")
+        .arg(path)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_exit_code_is_propagated() {
+    let path = write_temp("exit.elf", &program_elf(&[
+        0x00300513, // addi a0, x0, 3
+        0x05d00893, // addi a7, x0, 93
+        0x00000073, // ecall
+    ]));
+
+    let output = zkvm(&[], &path);
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), "exited with code 3 (cycles: 2, instret: 3)");
+
+    let output = zkvm(&["--json"], &path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["status"], "halted");
+    assert_eq!(summary["exit_code"], 3);
+    assert_eq!(summary["instret"], 3);
+}
+
+#[test]
+fn test_exit_code_with_zero_low_byte_fails() {
+    let path = write_temp("exit256.elf", &program_elf(&[
+        0x10000513, // addi a0, x0, 256
+        0x05d00893, // addi a7, x0, 93
+        0x00000073, // ecall
+    ]));
+    let output = zkvm(&["--json"], &path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["exit_code"], 256);
+}
+
+#[test]
+fn test_fault_fails_the_process() {
+    let path = write_temp("fault.elf", &program_elf(&[0xffffffff]));
+    let output = zkvm(&["--json"], &path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["status"], "fault");
+    assert_eq!(summary["pc"], 0x1000);
+}
+
+#[test]
+fn test_prove_reports_the_run_outcome() {
+    let path = write_temp("prove.elf", &program_elf(&[
+        0x00300513, // addi a0, x0, 3
+        0x05d00893, // addi a7, x0, 93
+        0x00000073, // ecall
+    ]));
+
+    // The proof goes to stderr so stdout holds only the summary
+    let output = zkvm(&["--prove", "--json"], &path);
+    assert_eq!(output.status.code(), Some(3));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["status"], "halted");
+    let proof: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert!(proof["data"].is_array());
+
+    // A run that does not halt is summarized without a proof
+    let output = zkvm(&["--prove", "--json", "--max-cycles", "1"], &path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["status"], "cycle_limit");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_max_cycles_flag() {
+    let path = write_temp("loop.elf", &program_elf(&[0x0000006f])); // jal x0, 0
+    let output = zkvm(&["--json", "--max-cycles", "10"], &path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["status"], "cycle_limit");
+    assert_eq!(summary["instret"], 10);
+}
+
+#[test]
+fn test_config_file_and_flag_overrides() {
+    let elf = write_temp("config.elf", &program_elf(&[0x0000006f]));
+    let config = write_temp("config.toml", b"max_cycles = 5\nmemory_size = 65536\n");
+
+    let output = zkvm(&["--json", "--config", config.to_str().unwrap()], &elf);
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["status"], "cycle_limit");
+    assert_eq!(summary["instret"], 5);
+
+    let output = zkvm(&["--json", "--config", config.to_str().unwrap(), "--max-cycles", "7"], &elf);
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["instret"], 7);
+
+    let output = zkvm(&["--config", config.to_str().unwrap(), "--memory-size", "64X"], &elf);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid size `64X`"));
+
+    std::fs::remove_file(&elf).unwrap();
+    std::fs::remove_file(&config).unwrap();
+}
+
+#[test]
+fn test_config_file_rejects_unknown_settings() {
+    let elf = write_temp("unknown.elf", &program_elf(&[0x0000006f]));
+    let config = write_temp("unknown.json", br#"{"max_cycle": 5}"#);
+    let output = zkvm(&["--config", config.to_str().unwrap()], &elf);
+    std::fs::remove_file(&elf).unwrap();
+    std::fs::remove_file(&config).unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown setting `max_cycle`"));
+}
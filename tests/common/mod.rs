@@ -0,0 +1,97 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use zk_risc_v_vm::risc_v::RegisterIndex;
+use zk_risc_v_vm::{VirtualMachine, VmError};
+
+/// Run `op` with `a0 = a` and `a1 = b` and return `a0`
+pub fn run(op: &str, a: u32, b: u32) -> Result<u32, VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.set_register(RegisterIndex::X10, a);
+    vm.set_register(RegisterIndex::X11, b);
+    vm.load_asm(0x1000, &format!("{}\nli a7, 93\necall", op))?;
+    vm.execute()?;
+    Ok(vm.cpu_state().read_register(RegisterIndex::X10))
+}
+
+/// Build a minimal ELF32 executable with a single PT_LOAD segment
+pub fn build_elf(class: u8, machine: u16, entry: u32, vaddr: u32, data: &[u8], mem_size: u32) -> Vec<u8> {
+    let mut elf = Vec::new();
+
+    // ELF header
+    elf.extend_from_slice(&[0x7f, b'E', b'L', b'F', class, 1, 1, 0]);
+    elf.extend_from_slice(&[0; 8]);
+    elf.extend_from_slice(&2u16.to_le_bytes()); // e_type = ET_EXEC
+    elf.extend_from_slice(&machine.to_le_bytes());
+    elf.extend_from_slice(&1u32.to_le_bytes()); // e_version
+    elf.extend_from_slice(&entry.to_le_bytes());
+    elf.extend_from_slice(&52u32.to_le_bytes()); // e_phoff
+    elf.extend_from_slice(&0u32.to_le_bytes()); // e_shoff
+    elf.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+    elf.extend_from_slice(&52u16.to_le_bytes()); // e_ehsize
+    elf.extend_from_slice(&32u16.to_le_bytes()); // e_phentsize
+    elf.extend_from_slice(&1u16.to_le_bytes()); // e_phnum
+    elf.extend_from_slice(&40u16.to_le_bytes()); // e_shentsize
+    elf.extend_from_slice(&0u16.to_le_bytes()); // e_shnum
+    elf.extend_from_slice(&0u16.to_le_bytes()); // e_shstrndx
+
+    // Program header
+    let offset = 52 + 32;
+    for field in [1, offset, vaddr, vaddr, data.len() as u32, mem_size, 0x7, 0x1000] {
+        elf.extend_from_slice(&field.to_le_bytes());
+    }
+
+    elf.extend_from_slice(data);
+    elf
+}
+
+/// Build a minimal ELF64 executable with a single PT_LOAD segment
+pub fn build_elf64(entry: u64, vaddr: u64, data: &[u8]) -> Vec<u8> {
+    let mut elf = Vec::new();
+
+    // ELF header
+    elf.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    elf.extend_from_slice(&[0; 8]);
+    elf.extend_from_slice(&2u16.to_le_bytes()); // e_type = ET_EXEC
+    elf.extend_from_slice(&243u16.to_le_bytes()); // e_machine = EM_RISCV
+    elf.extend_from_slice(&1u32.to_le_bytes()); // e_version
+    elf.extend_from_slice(&entry.to_le_bytes());
+    elf.extend_from_slice(&64u64.to_le_bytes()); // e_phoff
+    elf.extend_from_slice(&0u64.to_le_bytes()); // e_shoff
+    elf.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+    elf.extend_from_slice(&64u16.to_le_bytes()); // e_ehsize
+    elf.extend_from_slice(&56u16.to_le_bytes()); // e_phentsize
+    elf.extend_from_slice(&1u16.to_le_bytes()); // e_phnum
+    elf.extend_from_slice(&64u16.to_le_bytes()); // e_shentsize
+    elf.extend_from_slice(&0u16.to_le_bytes()); // e_shnum
+    elf.extend_from_slice(&0u16.to_le_bytes()); // e_shstrndx
+
+    // Program header
+    elf.extend_from_slice(&1u32.to_le_bytes()); // p_type = PT_LOAD
+    elf.extend_from_slice(&7u32.to_le_bytes()); // p_flags
+    for field in [64 + 56, vaddr, vaddr, data.len() as u64, data.len() as u64, 0x1000] {
+        elf.extend_from_slice(&field.to_le_bytes());
+    }
+
+    elf.extend_from_slice(data);
+    elf
+}
+
+/// Build an ELF32 executable that runs `code` from 0x1000
+pub fn program_elf(code: &[u32]) -> Vec<u8> {
+    let data = words(code);
+    build_elf(1, 243, 0x1000, 0x1000, &data, data.len() as u32)
+}
+
+/// Write `bytes` to a file in the temporary directory, unique to this process
+pub fn write_temp(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("zkvm-{}-{}", std::process::id(), name));
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+/// Little-endian bytes of a sequence of instruction words
+pub fn words(program: &[u32]) -> Vec<u8> {
+    program.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
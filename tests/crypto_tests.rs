@@ -0,0 +1,72 @@
+//! Tests for the Zknh/Zkne/Zknd scalar cryptography extensions
+
+mod common;
+
+use common::run;
+use zk_risc_v_vm::VmError;
+
+/// Run an RV32 SHA-512 instruction pair producing the (high, low) halves of `x`
+fn run_sha512(high_op: &str, low_op: &str, x: u64) -> Result<u64, VmError> {
+    let (hi, lo) = ((x >> 32) as u32, x as u32);
+    let high = run(&format!("{} a0, a0, a1", high_op), hi, lo)?;
+    let low = run(&format!("{} a0, a0, a1", low_op), lo, hi)?;
+    Ok((high as u64) << 32 | low as u64)
+}
+
+#[test]
+fn test_sha256_functions() -> Result<(), VmError> {
+    for x in [0x6a09e667u32, 0xdeadbeef, 0x00000001, 0x80000000] {
+        assert_eq!(run("sha256sig0 a0, a0", x, 0)?, x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3));
+        assert_eq!(run("sha256sig1 a0, a0", x, 0)?, x.rotate_right(17) ^ x.rotate_right(19) ^ (x >> 10));
+        assert_eq!(run("sha256sum0 a0, a0", x, 0)?, x.rotate_right(2) ^ x.rotate_right(13) ^ x.rotate_right(22));
+        assert_eq!(run("sha256sum1 a0, a0", x, 0)?, x.rotate_right(6) ^ x.rotate_right(11) ^ x.rotate_right(25));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_sha512_functions_from_halves() -> Result<(), VmError> {
+    for x in [0x6a09e667f3bcc908u64, 0x0123456789abcdef, 1, 1 << 63] {
+        assert_eq!(run_sha512("sha512sig0h", "sha512sig0l", x)?, x.rotate_right(1) ^ x.rotate_right(8) ^ (x >> 7));
+        assert_eq!(run_sha512("sha512sig1h", "sha512sig1l", x)?, x.rotate_right(19) ^ x.rotate_right(61) ^ (x >> 6));
+        assert_eq!(
+            run_sha512("sha512sum0r", "sha512sum0r", x)?,
+            x.rotate_right(28) ^ x.rotate_right(34) ^ x.rotate_right(39)
+        );
+        assert_eq!(
+            run_sha512("sha512sum1r", "sha512sum1r", x)?,
+            x.rotate_right(14) ^ x.rotate_right(18) ^ x.rotate_right(41)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_aes_encrypt_round() -> Result<(), VmError> {
+    // FIPS-197 Appendix B, round 1: SubBytes, ShiftRows and MixColumns
+    let state = [0xbee33d19u32, 0x2be2f4a0, 0x2a8dc69a, 0x0848f8e9];
+    let expected = [0xe5816604u32, 0x9a19cbe0, 0x7ad3f848, 0x4c260628];
+
+    for (column, &want) in expected.iter().enumerate() {
+        let mut acc = 0;
+        for bs in 0..4 {
+            let op = format!("aes32esmi a0, a0, a1, {}", bs);
+            acc = run(&op, acc, state[(column + bs) % 4])?;
+        }
+        assert_eq!(acc, want, "column {}", column);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_aes_sbox_round_trip() -> Result<(), VmError> {
+    assert_eq!(run("aes32esi a0, a0, a1, 0", 0, 0x00)?, 0x63);
+    assert_eq!(run("aes32esi a0, a0, a1, 2", 0xff, 0x0053_0000)?, 0x00ed_00ff);
+    assert_eq!(run("aes32dsi a0, a0, a1, 0", 0, 0x63)?, 0x00);
+    assert_eq!(run("aes32dsi a0, a0, a1, 3", 0, 0xed00_0000)?, 0x5300_0000);
+
+    // InvSubBytes(0x7c) = 0x01, whose InvMixColumns column is (0e, 09, 0d, 0b)
+    assert_eq!(run("aes32dsmi a0, a0, a1, 0", 0, 0x7c)?, 0x0b0d090e);
+    assert_eq!(run("aes32dsmi a0, a0, a1, 1", 0, 0x7c00)?, 0x0d090e0b);
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Tests for memory-mapped I/O devices
+
+use std::sync::{Arc, Mutex};
+use zk_risc_v_vm::risc_v::RegisterIndex;
+use zk_risc_v_vm::vm::memory::AccessKind;
+use zk_risc_v_vm::vm::Device;
+use zk_risc_v_vm::{VirtualMachine, VmError};
+
+const UART_BASE: u32 = 0x1000_0000;
+const TIMER_BASE: u32 = 0x1000_1000;
+
+/// Console that collects bytes written to its data register
+#[derive(Default)]
+struct Uart {
+    output: Vec<u8>,
+}
+
+impl Device for Uart {
+    fn read(&mut self, offset: u32, _size: u8) -> Result<u64, VmError> {
+        // Line status: transmitter always ready
+        Ok(if offset == 5 { 0x20 } else { 0 })
+    }
+
+    fn write(&mut self, offset: u32, _size: u8, value: u64) -> Result<(), VmError> {
+        if offset == 0 {
+            self.output.push(value as u8);
+        }
+        Ok(())
+    }
+}
+
+/// Timer that advances by one tick per read, with a writable compare register
+#[derive(Default)]
+struct Timer {
+    ticks: u64,
+    compare: u64,
+}
+
+impl Device for Timer {
+    fn read(&mut self, offset: u32, size: u8) -> Result<u64, VmError> {
+        match (offset, size) {
+            (0, 8) => {
+                self.ticks += 1;
+                Ok(self.ticks)
+            },
+            (8, 4) => Ok(self.compare),
+            _ => Err(VmError::DeviceError(format!("bad timer read at {:#x}", offset))),
+        }
+    }
+
+    fn write(&mut self, offset: u32, _size: u8, value: u64) -> Result<(), VmError> {
+        match offset {
+            8 => self.compare = value,
+            _ => return Err(VmError::DeviceError(format!("bad timer write at {:#x}", offset))),
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_uart_and_timer() -> Result<(), VmError> {
+    let uart = Arc::new(Mutex::new(Uart::default()));
+    let timer = Arc::new(Mutex::new(Timer::default()));
+
+    let mut vm = VirtualMachine::new();
+    vm.memory_mut().attach_device(UART_BASE, 0x100, uart.clone())?;
+    vm.memory_mut().attach_device(TIMER_BASE, 0x10, timer.clone())?;
+    vm.load_asm(
+        0x1000,
+        "
+        _start:
+            li t0, 0x10000000
+            lbu t1, 5(t0)
+            li t2, 'h'
+            sb t2, 0(t0)
+            li t2, 'i'
+            sb t2, 0(t0)
+
+            li t0, 0x10001000
+            li t3, 100
+            sw t3, 8(t0)
+            lw a1, 8(t0)
+            fld ft0, 0(t0)
+            fld ft0, 0(t0)
+            fmv.x.w a0, ft0
+            li a7, 93
+            ecall
+        ",
+    )?;
+    vm.execute()?;
+
+    assert_eq!(uart.lock().unwrap().output, b"hi");
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X6), 0x20);
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X10), 2);
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X11), 100);
+    assert_eq!(timer.lock().unwrap().compare, 100);
+    Ok(())
+}
+
+#[test]
+fn test_device_access_rules() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.memory_mut().attach_device(TIMER_BASE, 0x10, Arc::new(Mutex::new(Timer::default())))?;
+
+    // Overlapping ranges are rejected
+    let result = vm.memory_mut().attach_device(TIMER_BASE + 8, 0x10, Arc::new(Mutex::new(Uart::default())));
+    assert!(matches!(result, Err(VmError::DeviceError(_))));
+
+    // Accesses straddling the device boundary fault; device errors propagate
+    assert!(matches!(
+        vm.memory().load_word(TIMER_BASE - 2),
+        Err(VmError::AccessFault { address, access: AccessKind::Read }) if address == TIMER_BASE - 2
+    ));
+    assert!(matches!(vm.memory().load_byte(TIMER_BASE + 12), Err(VmError::DeviceError(_))));
+
+    // Devices cannot be executed
+    vm.set_pc(TIMER_BASE);
+    assert!(matches!(
+        vm.execute(),
+        Err(VmError::AccessFault { access: AccessKind::Execute, .. })
+    ));
+    Ok(())
+}
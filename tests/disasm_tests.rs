@@ -0,0 +1,87 @@
+//! Tests for the disassembler
+
+use std::collections::BTreeMap;
+use zk_risc_v_vm::risc_v::{Decoder, Disassembler, Instruction, RegisterIndex};
+use zk_risc_v_vm::VmError;
+
+fn disasm(word: u32) -> Result<String, VmError> {
+    Ok(Decoder::decode(word)?.to_string())
+}
+
+#[test]
+fn test_display_uses_abi_names() -> Result<(), VmError> {
+    assert_eq!(disasm(0x002081b3)?, "add gp, ra, sp");
+    assert_eq!(disasm(0x0220c1b3)?, "div gp, ra, sp");
+    assert_eq!(disasm(0x00812503)?, "lw a0, 8(sp)");
+    assert_eq!(disasm(0x00b12623)?, "sw a1, 12(sp)");
+    assert_eq!(disasm(0x123452b7)?, "lui t0, 0x12345");
+    assert_eq!(disasm(0x0020a1af)?, "amoadd.w gp, sp, (ra)");
+    assert_eq!(disasm(0x00000073)?, "ecall");
+    assert_eq!(disasm(0x0ff0000f)?, "fence");
+    assert_eq!(disasm(0x0310000f)?, "fence rw, w");
+    assert_eq!(disasm(0x8330000f)?, "fence.tso");
+    assert_eq!(disasm(0x0000100f)?, "fence.i");
+    Ok(())
+}
+
+#[test]
+fn test_pseudo_instructions() -> Result<(), VmError> {
+    assert_eq!(disasm(0x00000013)?, "nop");
+    assert_eq!(disasm(0x02a00093)?, "li ra, 42");
+    assert_eq!(disasm(0x00050593)?, "mv a1, a0");
+    assert_eq!(disasm(0x00008067)?, "ret");
+    assert_eq!(disasm(0xc0002573)?, "csrr a0, cycle");
+    assert_eq!(disasm(0x30529073)?, "csrw mtvec, t0");
+    assert_eq!(disasm(0x0100006f)?, "j . + 16");
+    Ok(())
+}
+
+#[test]
+fn test_branch_targets_with_symbols() {
+    use RegisterIndex::*;
+
+    let mut symbols = BTreeMap::new();
+    symbols.insert(0x1000, "loop".to_string());
+    let disassembler = Disassembler::with_symbols(symbols);
+
+    let beq = Instruction::Beq { rs1: X10, rs2: X11, imm: 0x10 };
+    assert_eq!(disassembler.format(&beq, 0x1000), "beq a0, a1, 0x1010 <loop+0x10>");
+
+    let bne = Instruction::Bne { rs1: X10, rs2: X11, imm: -8 };
+    assert_eq!(disassembler.format(&bne, 0x1008), "bne a0, a1, 0x1000 <loop>");
+
+    assert_eq!(Disassembler::new().format(&beq, 0x1000), "beq a0, a1, 0x1010");
+}
+
+#[test]
+fn test_disassemble_listing() {
+    let mut code = Vec::new();
+    code.extend_from_slice(&0x4515u16.to_le_bytes()); // c.li a0, 5
+    code.extend_from_slice(&0x05d00893u32.to_le_bytes()); // li a7, 93
+    code.extend_from_slice(&0xffffffffu32.to_le_bytes()); // invalid
+
+    let mut symbols = BTreeMap::new();
+    symbols.insert(0x1000, "_start".to_string());
+    let listing = Disassembler::with_symbols(symbols).disassemble(&code, 0x1000);
+    let lines: Vec<&str> = listing.lines().filter(|l| !l.is_empty()).collect();
+
+    assert_eq!(lines[0], "00001000 <_start>:");
+    assert!(lines[1].ends_with("4515      li a0, 5"));
+    assert!(lines[2].starts_with("    1002:"));
+    assert!(lines[2].ends_with("05d00893  li a7, 93"));
+    assert!(lines[3].ends_with(".word 0xffffffff"));
+}
+
+#[test]
+fn test_float_instructions() -> Result<(), VmError> {
+    assert_eq!(disasm(0x00c5f553)?, "fadd.s fa0, fa1, fa2");
+    assert_eq!(disasm(0x6ac59543)?, "fmadd.d fa0, fa1, fa2, fa3, rtz");
+    assert_eq!(disasm(0x00813507)?, "fld fa0, 8(sp)");
+    assert_eq!(disasm(0xe0058553)?, "fmv.x.w a0, fa1");
+    assert_eq!(disasm(0x20b59553)?, "fneg.s fa0, fa1");
+    assert_eq!(disasm(0xc0051553)?, "fcvt.w.s a0, fa0, rtz");
+
+    // c.fldsp fa0, 8(sp)
+    assert_eq!(Decoder::decode_compressed(0x2522)?.to_string(), "fld fa0, 8(sp)");
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Tests for ELF loading
+
+mod common;
+
+use common::{build_elf, build_elf64, words, write_temp};
+use zk_risc_v_vm::loader::ElfLoader;
+use zk_risc_v_vm::risc_v::isa::{IsaConfig, Xlen};
+use zk_risc_v_vm::risc_v::RegisterIndex;
+use zk_risc_v_vm::utils::config::VmConfig;
+use zk_risc_v_vm::{VirtualMachine, VmError};
+
+#[test]
+fn test_load_and_execute_elf() -> Result<(), VmError> {
+    let code = words(&[
+        0x02a00513, // addi x10, x0, 42
+        0x05d00893, // addi x17, x0, 93
+        0x00000073, // ecall
+    ]);
+    let path = write_temp("exec.elf", &build_elf(1, 243, 0x1000, 0x1000, &code, code.len() as u32));
+
+    let mut vm = VirtualMachine::new();
+    vm.load_elf(&path)?;
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(vm.cpu_state().get_pc(), 0x1000);
+    vm.execute()?;
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X10), 42);
+
+    Ok(())
+}
+
+#[test]
+fn test_bss_is_zero_filled() -> Result<(), VmError> {
+    let code = words(&[0x00000073]);
+    let path = write_temp("bss.elf", &build_elf(1, 243, 0x2000, 0x2000, &code, 0x100));
+
+    let mut vm = VirtualMachine::new();
+    vm.memory_mut().store_word(0x2080, 0xdeadbeef)?;
+    vm.load_elf(&path)?;
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(vm.memory().load_word(0x2000)?, 0x00000073);
+    assert_eq!(vm.memory().load_word(0x2080)?, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_rejects_non_riscv_elf() {
+    let loader = ElfLoader::new();
+
+    // EM_X86_64
+    let result = loader.parse(&build_elf(1, 62, 0x1000, 0x1000, &[0; 4], 4));
+    assert!(matches!(result, Err(VmError::ElfError(_))));
+
+    // Garbage input
+    let result = loader.parse(b"not an elf file");
+    assert!(matches!(result, Err(VmError::ElfError(_))));
+}
+
+#[test]
+fn test_load_elf64_requires_rv64_isa() -> Result<(), VmError> {
+    let code = words(&[
+        0xfff0051b, // addiw x10, x0, -1
+        0x02055513, // srli x10, x10, 32
+        0x05d00893, // addi x17, x0, 93
+        0x00000073, // ecall
+    ]);
+    let elf = ElfLoader::new().parse(&build_elf64(0x1000, 0x1000, &code))?;
+    assert_eq!(elf.xlen, Xlen::Rv64);
+
+    let mut vm = VirtualMachine::new();
+    assert!(matches!(vm.load_elf_file(&elf), Err(VmError::ElfError(_))));
+
+    let mut vm = VirtualMachine::with_config(VmConfig { isa: IsaConfig::full(Xlen::Rv64), ..VmConfig::default() });
+    vm.load_elf_file(&elf)?;
+    vm.execute()?;
+    assert_eq!(vm.cpu_state().read_xreg(RegisterIndex::X10), 0xffff_ffff);
+
+    // Entry points above 4 GiB cannot be addressed
+    let result = ElfLoader::new().parse(&build_elf64(1 << 32, 0x1000, &code));
+    assert!(matches!(result, Err(VmError::ElfError(_))));
+
+    Ok(())
+}
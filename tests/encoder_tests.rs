@@ -0,0 +1,278 @@
+//! Tests for the instruction encoder
+
+use proptest::prelude::*;
+use zk_risc_v_vm::risc_v::{Decoder, FloatFormat, Instruction, RegisterIndex, RoundingMode};
+use zk_risc_v_vm::VmError;
+
+type Reg = RegisterIndex;
+type AesOp = fn(Reg, Reg, Reg, u32) -> Instruction;
+type FloatOp = fn(FloatFormat, Reg, Reg, Reg, RoundingMode) -> Instruction;
+type FusedOp = fn(FloatFormat, Reg, Reg, Reg, Reg, RoundingMode) -> Instruction;
+
+fn reg() -> impl Strategy<Value = Reg> {
+    (0u32..32).prop_map(|r| RegisterIndex::from_u32(r).unwrap())
+}
+
+/// Signed immediate of `bits` width that is a multiple of `align`
+fn imm(bits: u32, align: i32) -> impl Strategy<Value = i32> {
+    let half = 1i32 << (bits - 1);
+    (-half..half).prop_map(move |imm| imm - imm.rem_euclid(align))
+}
+
+fn instruction() -> impl Strategy<Value = Instruction> {
+    let r_ops: [fn(Reg, Reg, Reg) -> Instruction; 51] = [
+        |rd, rs1, rs2| Instruction::Add { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sub { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Slt { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sltu { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::And { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Or { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Xor { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sll { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Srl { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sra { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Mul { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Mulh { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Mulhsu { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Mulhu { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Div { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Divu { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Rem { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Remu { rd, rs1, rs2 },
+        |rd, rs1, _| Instruction::LrW { rd, rs1 },
+        |rd, rs1, rs2| Instruction::ScW { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::AmoswapW { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::AmoaddW { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::AmoandW { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::AmoorW { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::AmoxorW { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::AmominW { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::AmomaxW { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::AmominuW { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::AmomaxuW { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sh1add { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sh2add { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sh3add { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Andn { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Orn { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Xnor { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Max { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Maxu { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Min { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Minu { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Rol { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Ror { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Bclr { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Bext { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Binv { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Bset { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sha512Sig0h { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sha512Sig0l { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sha512Sig1h { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sha512Sig1l { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sha512Sum0r { rd, rs1, rs2 },
+        |rd, rs1, rs2| Instruction::Sha512Sum1r { rd, rs1, rs2 },
+    ];
+    let unary_ops: [fn(Reg, Reg) -> Instruction; 12] = [
+        |rd, rs1| Instruction::Clz { rd, rs1 },
+        |rd, rs1| Instruction::Ctz { rd, rs1 },
+        |rd, rs1| Instruction::Cpop { rd, rs1 },
+        |rd, rs1| Instruction::SextB { rd, rs1 },
+        |rd, rs1| Instruction::SextH { rd, rs1 },
+        |rd, rs1| Instruction::ZextH { rd, rs1 },
+        |rd, rs1| Instruction::OrcB { rd, rs1 },
+        |rd, rs1| Instruction::Rev8 { rd, rs1 },
+        |rd, rs1| Instruction::Sha256Sig0 { rd, rs1 },
+        |rd, rs1| Instruction::Sha256Sig1 { rd, rs1 },
+        |rd, rs1| Instruction::Sha256Sum0 { rd, rs1 },
+        |rd, rs1| Instruction::Sha256Sum1 { rd, rs1 },
+    ];
+    let aes_ops: [AesOp; 4] = [
+        |rd, rs1, rs2, bs| Instruction::Aes32esi { rd, rs1, rs2, bs },
+        |rd, rs1, rs2, bs| Instruction::Aes32esmi { rd, rs1, rs2, bs },
+        |rd, rs1, rs2, bs| Instruction::Aes32dsi { rd, rs1, rs2, bs },
+        |rd, rs1, rs2, bs| Instruction::Aes32dsmi { rd, rs1, rs2, bs },
+    ];
+    let i_ops: [fn(Reg, Reg, i32) -> Instruction; 12] = [
+        |rd, rs1, imm| Instruction::Addi { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Slti { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Sltiu { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Andi { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Ori { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Xori { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Lb { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Lh { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Lw { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Lbu { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Lhu { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Jalr { rd, rs1, imm },
+    ];
+    let shift_ops: [fn(Reg, Reg, u32) -> Instruction; 8] = [
+        |rd, rs1, shamt| Instruction::Slli { rd, rs1, shamt },
+        |rd, rs1, shamt| Instruction::Srli { rd, rs1, shamt },
+        |rd, rs1, shamt| Instruction::Srai { rd, rs1, shamt },
+        |rd, rs1, shamt| Instruction::Rori { rd, rs1, shamt },
+        |rd, rs1, shamt| Instruction::Bclri { rd, rs1, shamt },
+        |rd, rs1, shamt| Instruction::Bexti { rd, rs1, shamt },
+        |rd, rs1, shamt| Instruction::Binvi { rd, rs1, shamt },
+        |rd, rs1, shamt| Instruction::Bseti { rd, rs1, shamt },
+    ];
+    let s_ops: [fn(Reg, Reg, i32) -> Instruction; 3] = [
+        |rs1, rs2, imm| Instruction::Sb { rs1, rs2, imm },
+        |rs1, rs2, imm| Instruction::Sh { rs1, rs2, imm },
+        |rs1, rs2, imm| Instruction::Sw { rs1, rs2, imm },
+    ];
+    let b_ops: [fn(Reg, Reg, i32) -> Instruction; 6] = [
+        |rs1, rs2, imm| Instruction::Beq { rs1, rs2, imm },
+        |rs1, rs2, imm| Instruction::Bne { rs1, rs2, imm },
+        |rs1, rs2, imm| Instruction::Blt { rs1, rs2, imm },
+        |rs1, rs2, imm| Instruction::Bge { rs1, rs2, imm },
+        |rs1, rs2, imm| Instruction::Bltu { rs1, rs2, imm },
+        |rs1, rs2, imm| Instruction::Bgeu { rs1, rs2, imm },
+    ];
+    let csr_ops: [fn(Reg, Reg, u16) -> Instruction; 6] = [
+        |rd, rs1, csr| Instruction::Csrrw { rd, rs1, csr },
+        |rd, rs1, csr| Instruction::Csrrs { rd, rs1, csr },
+        |rd, rs1, csr| Instruction::Csrrc { rd, rs1, csr },
+        |rd, rs1, csr| Instruction::Csrrwi { rd, uimm: rs1 as u32, csr },
+        |rd, rs1, csr| Instruction::Csrrsi { rd, uimm: rs1 as u32, csr },
+        |rd, rs1, csr| Instruction::Csrrci { rd, uimm: rs1 as u32, csr },
+    ];
+
+    prop_oneof![
+        (0..r_ops.len(), reg(), reg(), reg()).prop_map(move |(op, rd, rs1, rs2)| r_ops[op](rd, rs1, rs2)),
+        (0..unary_ops.len(), reg(), reg()).prop_map(move |(op, rd, rs1)| unary_ops[op](rd, rs1)),
+        (0..aes_ops.len(), reg(), reg(), reg(), 0u32..4)
+            .prop_map(move |(op, rd, rs1, rs2, bs)| aes_ops[op](rd, rs1, rs2, bs)),
+        (0..i_ops.len(), reg(), reg(), imm(12, 1)).prop_map(move |(op, rd, rs1, imm)| i_ops[op](rd, rs1, imm)),
+        (0..shift_ops.len(), reg(), reg(), 0u32..32).prop_map(move |(op, rd, rs1, shamt)| shift_ops[op](rd, rs1, shamt)),
+        (0..s_ops.len(), reg(), reg(), imm(12, 1)).prop_map(move |(op, rs1, rs2, imm)| s_ops[op](rs1, rs2, imm)),
+        (0..b_ops.len(), reg(), reg(), imm(13, 2)).prop_map(move |(op, rs1, rs2, imm)| b_ops[op](rs1, rs2, imm)),
+        (0..csr_ops.len(), reg(), reg(), 0u16..4096).prop_map(move |(op, rd, rs1, csr)| csr_ops[op](rd, rs1, csr)),
+        (reg(), imm(21, 2)).prop_map(|(rd, imm)| Instruction::Jal { rd, imm }),
+        (reg(), any::<i32>()).prop_map(|(rd, imm)| Instruction::Lui { rd, imm: imm & !0xfff }),
+        (reg(), any::<i32>()).prop_map(|(rd, imm)| Instruction::Auipc { rd, imm: imm & !0xfff }),
+        (0u32..16, 0u32..16, 0u32..16).prop_map(|(fm, pred, succ)| Instruction::Fence { fm, pred, succ }),
+        Just(Instruction::FenceI),
+        Just(Instruction::Ecall),
+        Just(Instruction::Ebreak),
+        Just(Instruction::Mret),
+        float_instruction(),
+    ]
+}
+
+fn float_instruction() -> impl Strategy<Value = Instruction> {
+    let fmt = || prop_oneof![Just(FloatFormat::Single), Just(FloatFormat::Double)];
+    let rm = || proptest::sample::select(RoundingMode::ALL.to_vec());
+    let ops: [FloatOp; 28] = [
+        |fmt, rd, rs1, rs2, rm| Instruction::Fadd { fmt, rd, rs1, rs2, rm },
+        |fmt, rd, rs1, rs2, rm| Instruction::Fsub { fmt, rd, rs1, rs2, rm },
+        |fmt, rd, rs1, rs2, rm| Instruction::Fmul { fmt, rd, rs1, rs2, rm },
+        |fmt, rd, rs1, rs2, rm| Instruction::Fdiv { fmt, rd, rs1, rs2, rm },
+        |fmt, rd, rs1, _, rm| Instruction::Fsqrt { fmt, rd, rs1, rm },
+        |fmt, rd, rs1, rs2, _| Instruction::Fsgnj { fmt, rd, rs1, rs2 },
+        |fmt, rd, rs1, rs2, _| Instruction::Fsgnjn { fmt, rd, rs1, rs2 },
+        |fmt, rd, rs1, rs2, _| Instruction::Fsgnjx { fmt, rd, rs1, rs2 },
+        |fmt, rd, rs1, rs2, _| Instruction::Fmin { fmt, rd, rs1, rs2 },
+        |fmt, rd, rs1, rs2, _| Instruction::Fmax { fmt, rd, rs1, rs2 },
+        |fmt, rd, rs1, rs2, _| Instruction::Feq { fmt, rd, rs1, rs2 },
+        |fmt, rd, rs1, rs2, _| Instruction::Flt { fmt, rd, rs1, rs2 },
+        |fmt, rd, rs1, rs2, _| Instruction::Fle { fmt, rd, rs1, rs2 },
+        |fmt, rd, rs1, _, _| Instruction::Fclass { fmt, rd, rs1 },
+        |fmt, rd, rs1, _, rm| Instruction::FcvtW { fmt, rd, rs1, rm },
+        |fmt, rd, rs1, _, rm| Instruction::FcvtWu { fmt, rd, rs1, rm },
+        |fmt, rd, rs1, _, rm| Instruction::FcvtFW { fmt, rd, rs1, rm },
+        |fmt, rd, rs1, _, rm| Instruction::FcvtFWu { fmt, rd, rs1, rm },
+        |_, rd, rs1, _, rm| Instruction::FcvtSD { rd, rs1, rm },
+        |_, rd, rs1, _, rm| Instruction::FcvtDS { rd, rs1, rm },
+        |_, rd, rs1, _, _| Instruction::FmvXW { rd, rs1 },
+        |_, rd, rs1, _, _| Instruction::FmvWX { rd, rs1 },
+        |fmt, rd, rs1, _, rm| Instruction::FcvtL { fmt, rd, rs1, rm },
+        |fmt, rd, rs1, _, rm| Instruction::FcvtLu { fmt, rd, rs1, rm },
+        |fmt, rd, rs1, _, rm| Instruction::FcvtFL { fmt, rd, rs1, rm },
+        |fmt, rd, rs1, _, rm| Instruction::FcvtFLu { fmt, rd, rs1, rm },
+        |_, rd, rs1, _, _| Instruction::FmvXD { rd, rs1 },
+        |_, rd, rs1, _, _| Instruction::FmvDX { rd, rs1 },
+    ];
+    let fused_ops: [FusedOp; 4] = [
+        |fmt, rd, rs1, rs2, rs3, rm| Instruction::Fmadd { fmt, rd, rs1, rs2, rs3, rm },
+        |fmt, rd, rs1, rs2, rs3, rm| Instruction::Fmsub { fmt, rd, rs1, rs2, rs3, rm },
+        |fmt, rd, rs1, rs2, rs3, rm| Instruction::Fnmsub { fmt, rd, rs1, rs2, rs3, rm },
+        |fmt, rd, rs1, rs2, rs3, rm| Instruction::Fnmadd { fmt, rd, rs1, rs2, rs3, rm },
+    ];
+    let mem_ops: [fn(Reg, Reg, i32) -> Instruction; 4] = [
+        |rd, rs1, imm| Instruction::Flw { rd, rs1, imm },
+        |rd, rs1, imm| Instruction::Fld { rd, rs1, imm },
+        |rs1, rs2, imm| Instruction::Fsw { rs1, rs2, imm },
+        |rs1, rs2, imm| Instruction::Fsd { rs1, rs2, imm },
+    ];
+
+    prop_oneof![
+        (0..ops.len(), fmt(), reg(), reg(), reg(), rm())
+            .prop_map(move |(op, fmt, rd, rs1, rs2, rm)| ops[op](fmt, rd, rs1, rs2, rm)),
+        (0..fused_ops.len(), fmt(), (reg(), reg(), reg(), reg()), rm())
+            .prop_map(move |(op, fmt, (rd, rs1, rs2, rs3), rm)| fused_ops[op](fmt, rd, rs1, rs2, rs3, rm)),
+        (0..mem_ops.len(), reg(), reg(), imm(12, 1)).prop_map(move |(op, a, b, imm)| mem_ops[op](a, b, imm)),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn test_encode_decode_round_trip(instruction in instruction()) {
+        let word = instruction.encode().unwrap();
+        prop_assert_eq!(Decoder::decode(word).unwrap(), instruction);
+    }
+}
+
+#[test]
+fn test_encode_known_words() -> Result<(), VmError> {
+    use RegisterIndex::*;
+
+    assert_eq!(Instruction::Addi { rd: X1, rs1: X0, imm: 42 }.encode()?, 0x02a00093);
+    assert_eq!(Instruction::Addi { rd: X17, rs1: X0, imm: 93 }.encode()?, 0x05d00893);
+    assert_eq!(Instruction::Add { rd: X3, rs1: X1, rs2: X2 }.encode()?, 0x002081b3);
+    assert_eq!(Instruction::Ecall.encode()?, 0x00000073);
+    assert_eq!(Instruction::Fence { fm: 0, pred: 0xf, succ: 0xf }.encode()?, 0x0ff0000f);
+    assert_eq!(Instruction::FenceI.encode()?, 0x0000100f);
+    assert_eq!(Instruction::Rev8 { rd: X10, rs1: X10 }.encode()?, 0x69855513);
+    assert_eq!(Instruction::OrcB { rd: X10, rs1: X10 }.encode()?, 0x28755513);
+    assert_eq!(Instruction::ZextH { rd: X10, rs1: X10 }.encode()?, 0x08054533);
+    assert_eq!(Instruction::Sha256Sig0 { rd: X10, rs1: X10 }.encode()?, 0x10251513);
+    assert_eq!(Instruction::Aes32esmi { rd: X10, rs1: X10, rs2: X11, bs: 3 }.encode()?, 0xe6b50533);
+
+    // fadd.s fa0, fa1, fa2 and fmadd.d fa0, fa1, fa2, fa3, rtz
+    let (single, double) = (FloatFormat::Single, FloatFormat::Double);
+    let dynamic = RoundingMode::Dyn;
+    assert_eq!(Instruction::Fadd { fmt: single, rd: X10, rs1: X11, rs2: X12, rm: dynamic }.encode()?, 0x00c5f553);
+    let fmadd = Instruction::Fmadd { fmt: double, rd: X10, rs1: X11, rs2: X12, rs3: X13, rm: RoundingMode::Rtz };
+    assert_eq!(fmadd.encode()?, 0x6ac59543);
+    assert_eq!(Instruction::Fld { rd: X10, rs1: X2, imm: 8 }.encode()?, 0x00813507);
+    assert_eq!(Instruction::FmvXW { rd: X10, rs1: X11 }.encode()?, 0xe0058553);
+
+    Ok(())
+}
+
+#[test]
+fn test_encode_rejects_out_of_range_fields() {
+    use RegisterIndex::*;
+
+    let invalid = [
+        Instruction::Addi { rd: X1, rs1: X0, imm: 2048 },
+        Instruction::Sw { rs1: X2, rs2: X1, imm: -2049 },
+        Instruction::Slli { rd: X1, rs1: X1, shamt: 64 },
+        Instruction::Beq { rs1: X1, rs2: X2, imm: 3 },
+        Instruction::Beq { rs1: X1, rs2: X2, imm: 4096 },
+        Instruction::Jal { rd: X1, imm: 1 << 20 },
+        Instruction::Lui { rd: X1, imm: 0x123 },
+        Instruction::Csrrw { rd: X1, rs1: X2, csr: 0x1000 },
+        Instruction::Csrrwi { rd: X1, uimm: 32, csr: 0x340 },
+    ];
+
+    for instruction in invalid {
+        assert!(
+            matches!(instruction.encode(), Err(VmError::EncodingError(_))),
+            "{:?} should not encode",
+            instruction
+        );
+    }
+}
@@ -2,7 +2,7 @@
 
 use zk_risc_v_vm::{VirtualMachine, VmError};
 use zk_risc_v_vm::risc_v::{RegisterIndex, Decoder, Instruction};
-use zk_risc_v_vm::vm::Executor;
+use zk_risc_v_vm::utils::config::{TrapMode, VmConfig};
 
 #[test]
 fn test_instruction_decoding() -> Result<(), VmError> {
@@ -57,7 +57,7 @@ fn test_program_with_exit() -> Result<(), VmError> {
     // addi x10, x0, 0     # Load exit code 0 into x10 (a0)
     // ecall               # System call
     
-    let program = vec![
+    let program = [
         0x05d00893u32, // addi x17, x0, 93
         0x00000513u32, // addi x10, x0, 0
         0x00000073u32, // ecall
@@ -77,4 +77,304 @@ fn test_program_with_exit() -> Result<(), VmError> {
     assert!(result.is_ok());
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Run `instruction` with x1/x2 preloaded and return the value written to x3
+fn run_rtype(instruction: u32, x1: u32, x2: u32) -> Result<u32, VmError> {
+    let mut vm = VirtualMachine::new();
+
+    let program = [
+        instruction,
+        0x05d00893u32, // addi x17, x0, 93
+        0x00000073u32, // ecall
+    ];
+    for (i, &word) in program.iter().enumerate() {
+        vm.memory_mut().store_word(0x1000 + (i as u32) * 4, word)?;
+    }
+
+    vm.set_register(RegisterIndex::X1, x1);
+    vm.set_register(RegisterIndex::X2, x2);
+    vm.set_pc(0x1000);
+    vm.execute()?;
+
+    Ok(vm.cpu_state().read_register(RegisterIndex::X3))
+}
+
+#[test]
+fn test_multiply_instructions() -> Result<(), VmError> {
+    assert_eq!(run_rtype(0x022081b3, 7, (-6i32) as u32)?, (-42i32) as u32); // mul x3, x1, x2
+    assert_eq!(run_rtype(0x022091b3, (-2i32) as u32, 0x8000_0000)?, 1); // mulh x3, x1, x2
+    assert_eq!(run_rtype(0x0220a1b3, (-1i32) as u32, 0xffff_ffff)?, 0xffff_ffff); // mulhsu x3, x1, x2
+    assert_eq!(run_rtype(0x0220b1b3, 0xffff_ffff, 0xffff_ffff)?, 0xffff_fffe); // mulhu x3, x1, x2
+    Ok(())
+}
+
+#[test]
+fn test_divide_edge_cases() -> Result<(), VmError> {
+    // Division by zero
+    assert_eq!(run_rtype(0x0220c1b3, 17, 0)?, u32::MAX); // div x3, x1, x2
+    assert_eq!(run_rtype(0x0220d1b3, 17, 0)?, u32::MAX); // divu x3, x1, x2
+    assert_eq!(run_rtype(0x0220e1b3, 17, 0)?, 17); // rem x3, x1, x2
+    assert_eq!(run_rtype(0x0220f1b3, 17, 0)?, 17); // remu x3, x1, x2
+
+    // Signed overflow
+    assert_eq!(run_rtype(0x0220c1b3, 0x8000_0000, (-1i32) as u32)?, 0x8000_0000);
+    assert_eq!(run_rtype(0x0220e1b3, 0x8000_0000, (-1i32) as u32)?, 0);
+
+    // Rounding towards zero
+    assert_eq!(run_rtype(0x0220c1b3, (-7i32) as u32, 2)?, (-3i32) as u32);
+    assert_eq!(run_rtype(0x0220e1b3, (-7i32) as u32, 2)?, (-1i32) as u32);
+    Ok(())
+}
+
+#[test]
+fn test_compressed_decoding() -> Result<(), VmError> {
+    use RegisterIndex::*;
+
+    let cases = [
+        (0x4515u16, Instruction::Addi { rd: X10, rs1: X0, imm: 5 }), // c.li a0, 5
+        (0x85aa, Instruction::Add { rd: X11, rs1: X0, rs2: X10 }), // c.mv a1, a0
+        (0x8082, Instruction::Jalr { rd: X0, rs1: X1, imm: 0 }), // c.jr ra
+        (0x7139, Instruction::Addi { rd: X2, rs1: X2, imm: -64 }), // c.addi16sp sp, -64
+        (0x0800, Instruction::Addi { rd: X8, rs1: X2, imm: 16 }), // c.addi4spn s0, sp, 16
+        (0x4522, Instruction::Lw { rd: X10, rs1: X2, imm: 8 }), // c.lwsp a0, 8(sp)
+        (0xc62e, Instruction::Sw { rs1: X2, rs2: X11, imm: 12 }), // c.swsp a1, 12(sp)
+        (0xdc75, Instruction::Beq { rs1: X8, rs2: X0, imm: -4 }), // c.beqz s0, -4
+        (0x9002, Instruction::Ebreak), // c.ebreak
+    ];
+
+    for (parcel, expected) in cases {
+        assert!(Decoder::is_compressed(parcel));
+        assert_eq!(Decoder::decode_compressed(parcel)?, expected, "parcel {:04x}", parcel);
+    }
+
+    // The all-zero parcel is defined to be illegal
+    assert!(Decoder::decode_compressed(0x0000).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_mixed_length_execution() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+
+    let program: [&[u8]; 6] = [
+        &0x4515u16.to_le_bytes(),     // 0x1000: c.li a0, 5
+        &0x050du16.to_le_bytes(),     // 0x1002: c.addi a0, 3
+        &0x05d00893u32.to_le_bytes(), // 0x1004: addi x17, x0, 93
+        &0x2019u16.to_le_bytes(),     // 0x1008: c.jal +6
+        &0x4505u16.to_le_bytes(),     // 0x100a: c.li a0, 1 (skipped)
+        &[0x00, 0x00],                // 0x100c: padding (skipped)
+    ];
+    vm.load_binary(0x1000, &program.concat())?;
+    vm.memory_mut().store_word(0x100e, 0x00000073)?; // 0x100e: ecall
+
+    vm.set_pc(0x1000);
+    vm.execute()?;
+
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X10), 8);
+    // c.jal links to the next 2-byte instruction
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X1), 0x100a);
+
+    Ok(())
+}
+
+#[test]
+fn test_fences_execute_as_no_ops() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+
+    let program = [
+        0x02a00513u32, // addi a0, x0, 42
+        0x0ff0000fu32, // fence
+        0x8330000fu32, // fence.tso
+        0x0000100fu32, // fence.i
+        0x05d00893u32, // addi x17, x0, 93
+        0x00000073u32, // ecall
+    ];
+    for (i, &word) in program.iter().enumerate() {
+        vm.memory_mut().store_word(0x1000 + (i as u32) * 4, word)?;
+    }
+
+    vm.set_pc(0x1000);
+    vm.execute()?;
+
+    assert_eq!(vm.exit_code(), Some(42));
+    assert_eq!(vm.cpu_state().get_instret(), 6);
+    Ok(())
+}
+
+#[test]
+fn test_csr_counters_and_scratch() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+
+    let program = [
+        0x00000013u32, // nop
+        0xc0002573u32, // rdcycle a0
+        0xc02025f3u32, // rdinstret a1
+        0xc80026f3u32, // rdcycleh a3
+        0x34051073u32, // csrw mscratch, a0
+        0x34002673u32, // csrr a2, mscratch
+        0x05d00893u32, // addi x17, x0, 93
+        0x00000073u32, // ecall
+    ];
+    for (i, &word) in program.iter().enumerate() {
+        vm.memory_mut().store_word(0x1000 + (i as u32) * 4, word)?;
+    }
+
+    vm.set_pc(0x1000);
+    let start = vm.cpu_state().get_cycles() as u32;
+    vm.execute()?;
+
+    let cpu = vm.cpu_state();
+    assert_eq!(cpu.read_register(RegisterIndex::X10), start + 1);
+    assert_eq!(cpu.read_register(RegisterIndex::X11), 2);
+    assert_eq!(cpu.read_register(RegisterIndex::X13), 0);
+    assert_eq!(cpu.read_register(RegisterIndex::X12), start + 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_instret_excludes_trapped_instructions() -> Result<(), VmError> {
+    let config = VmConfig { trap_mode: TrapMode::Guest, ..VmConfig::default() };
+    let mut vm = VirtualMachine::with_config(config);
+    load_trap_program(&mut vm)?;
+    let handler = [
+        0xc02025f3u32, // rdinstret a1
+        0xc0002573u32, // rdcycle a0
+        0x05d00893u32, // addi x17, x0, 93
+        0x00000073u32, // ecall
+    ];
+    for (i, &word) in handler.iter().enumerate() {
+        vm.memory_mut().store_word(0x1100 + (i as u32) * 4, word)?;
+    }
+
+    vm.execute()?;
+
+    // The illegal instruction takes a cycle to trap but does not retire
+    let cpu = vm.cpu_state();
+    assert_eq!(cpu.read_register(RegisterIndex::X11), 3);
+    assert_eq!(cpu.read_register(RegisterIndex::X10), 6); // includes the cycle from set_pc
+    assert_eq!(cpu.get_instret(), 7);
+
+    Ok(())
+}
+
+#[test]
+fn test_csr_write_to_read_only_counter_fails() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.memory_mut().store_word(0x1000, 0xc0051073)?; // csrw cycle, a0
+    vm.set_pc(0x1000);
+
+    assert!(matches!(vm.execute(), Err(VmError::InvalidCsr(0xc00))));
+
+    Ok(())
+}
+
+/// Program that installs a handler at 0x1100, executes an illegal instruction,
+/// and exits with the `mcause` value observed by the handler
+fn load_trap_program(vm: &mut VirtualMachine) -> Result<(), VmError> {
+    let program = [
+        0x000012b7u32, // lui t0, 1
+        0x10028293u32, // addi t0, t0, 0x100
+        0x30529073u32, // csrw mtvec, t0
+        0xffffffffu32, // illegal instruction
+        0x05d00893u32, // addi x17, x0, 93
+        0x00000073u32, // ecall
+    ];
+    let handler = [
+        0x34202573u32, // csrr a0, mcause
+        0x341025f3u32, // csrr a1, mepc
+        0x34302673u32, // csrr a2, mtval
+        0x00458593u32, // addi a1, a1, 4
+        0x34159073u32, // csrw mepc, a1
+        0x30200073u32, // mret
+    ];
+    for (i, &word) in program.iter().enumerate() {
+        vm.memory_mut().store_word(0x1000 + (i as u32) * 4, word)?;
+    }
+    for (i, &word) in handler.iter().enumerate() {
+        vm.memory_mut().store_word(0x1100 + (i as u32) * 4, word)?;
+    }
+    vm.set_pc(0x1000);
+    Ok(())
+}
+
+#[test]
+fn test_illegal_instruction_traps_to_guest_handler() -> Result<(), VmError> {
+    let config = VmConfig { trap_mode: TrapMode::Guest, ..VmConfig::default() };
+    let mut vm = VirtualMachine::with_config(config);
+    load_trap_program(&mut vm)?;
+
+    vm.execute()?;
+
+    let cpu = vm.cpu_state();
+    assert_eq!(cpu.read_register(RegisterIndex::X10), 2); // illegal instruction
+    assert_eq!(cpu.read_register(RegisterIndex::X11), 0x1010); // mepc + 4
+    assert_eq!(cpu.read_register(RegisterIndex::X12), 0xffffffff); // mtval
+
+    Ok(())
+}
+
+#[test]
+fn test_illegal_instruction_aborts_by_default() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    load_trap_program(&mut vm)?;
+
+    assert!(matches!(vm.execute(), Err(VmError::InvalidInstruction(0xffffffff))));
+    assert_eq!(vm.cpu_state().get_pc(), 0x100c);
+
+    Ok(())
+}
+
+#[test]
+fn test_ebreak_traps_with_breakpoint_cause() -> Result<(), VmError> {
+    let config = VmConfig { trap_mode: TrapMode::Guest, ..VmConfig::default() };
+    let mut vm = VirtualMachine::with_config(config);
+    load_trap_program(&mut vm)?;
+    vm.memory_mut().store_word(0x100c, 0x00100073)?; // ebreak
+
+    vm.execute()?;
+
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X10), 3); // breakpoint
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X12), 0x100c); // faulting pc
+
+    Ok(())
+}
+
+#[test]
+fn test_atomic_memory_operations() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+
+    let program = [
+        0x0020a1afu32, // amoadd.w x3, x2, (x1)
+        0x8050a22fu32, // amomin.w x4, x5, (x1)
+        0xe050a4afu32, // amomaxu.w x9, x5, (x1)
+        0x1000a32fu32, // lr.w x6, (x1)
+        0x1820a3afu32, // sc.w x7, x2, (x1)
+        0x1820a42fu32, // sc.w x8, x2, (x1)
+        0x05d00893u32, // addi x17, x0, 93
+        0x00000073u32, // ecall
+    ];
+    for (i, &word) in program.iter().enumerate() {
+        vm.memory_mut().store_word(0x1000 + (i as u32) * 4, word)?;
+    }
+    vm.memory_mut().store_word(0x2000, 10)?;
+
+    vm.set_register(RegisterIndex::X1, 0x2000);
+    vm.set_register(RegisterIndex::X2, 5);
+    vm.set_register(RegisterIndex::X5, (-1i32) as u32);
+    vm.set_pc(0x1000);
+    vm.execute()?;
+
+    let cpu = vm.cpu_state();
+    assert_eq!(cpu.read_register(RegisterIndex::X3), 10);
+    assert_eq!(cpu.read_register(RegisterIndex::X4), 15);
+    assert_eq!(cpu.read_register(RegisterIndex::X9), 0xffffffff);
+    assert_eq!(cpu.read_register(RegisterIndex::X6), 0xffffffff);
+    assert_eq!(cpu.read_register(RegisterIndex::X7), 0); // sc.w succeeded
+    assert_eq!(cpu.read_register(RegisterIndex::X8), 1); // reservation already consumed
+    assert_eq!(vm.memory().load_word(0x2000)?, 5);
+
+    Ok(())
+}
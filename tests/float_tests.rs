@@ -0,0 +1,285 @@
+//! Tests for the soft-float F and D extensions
+
+use proptest::prelude::*;
+use zk_risc_v_vm::risc_v::{FloatFormat, RegisterIndex, RoundingMode};
+use zk_risc_v_vm::utils::config::{TrapMode, VmConfig};
+use zk_risc_v_vm::vm::csr::FFLAGS;
+use zk_risc_v_vm::vm::softfloat::{self, SoftFloat, FLAG_DZ, FLAG_NV, FLAG_NX, FLAG_OF, FLAG_UF};
+use zk_risc_v_vm::{VirtualMachine, VmError};
+
+fn single(rm: RoundingMode) -> SoftFloat {
+    SoftFloat::new(softfloat::SINGLE, rm)
+}
+
+fn double(rm: RoundingMode) -> SoftFloat {
+    SoftFloat::new(softfloat::DOUBLE, rm)
+}
+
+fn bits(x: f32) -> u64 {
+    x.to_bits() as u64
+}
+
+/// Arbitrary bit patterns, biased towards operands with nearby exponents so
+/// that cancellation and carries are exercised
+fn f32_bits() -> impl Strategy<Value = u32> {
+    prop_oneof![
+        any::<u32>(),
+        (any::<bool>(), 120u32..136, 0u32..1 << 23)
+            .prop_map(|(sign, exp, frac)| (sign as u32) << 31 | exp << 23 | frac),
+    ]
+}
+
+fn f64_bits() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        any::<u64>(),
+        (any::<bool>(), 1015u64..1031, 0u64..1 << 52)
+            .prop_map(|(sign, exp, frac)| (sign as u64) << 63 | exp << 52 | frac),
+    ]
+}
+
+/// Compare against the host result, treating all NaNs as equal since RISC-V
+/// always produces the canonical NaN
+fn same_f32(soft: u64, host: f32) -> bool {
+    let soft = f32::from_bits(soft as u32);
+    (soft.is_nan() && host.is_nan()) || soft.to_bits() == host.to_bits()
+}
+
+fn same_f64(soft: u64, host: f64) -> bool {
+    let soft = f64::from_bits(soft);
+    (soft.is_nan() && host.is_nan()) || soft.to_bits() == host.to_bits()
+}
+
+proptest! {
+    #[test]
+    fn single_precision_matches_host(a in f32_bits(), b in f32_bits(), c in f32_bits()) {
+        let (x, y, z) = (f32::from_bits(a), f32::from_bits(b), f32::from_bits(c));
+        let (a, b, c) = (a as u64, b as u64, c as u64);
+        let mut fpu = single(RoundingMode::Rne);
+
+        prop_assert!(same_f32(fpu.add(a, b), x + y));
+        prop_assert!(same_f32(fpu.sub(a, b), x - y));
+        prop_assert!(same_f32(fpu.mul(a, b), x * y));
+        prop_assert!(same_f32(fpu.div(a, b), x / y));
+        prop_assert!(same_f32(fpu.sqrt(a), x.sqrt()));
+        prop_assert!(same_f32(fpu.fma(a, b, c, false, false), x.mul_add(y, z)));
+        prop_assert!(same_f32(fpu.fma(a, b, c, true, true), -x.mul_add(y, z)));
+        prop_assert!(same_f64(double(RoundingMode::Rne).convert(a, softfloat::SINGLE), x as f64));
+    }
+
+    #[test]
+    fn double_precision_matches_host(a in f64_bits(), b in f64_bits(), c in f64_bits()) {
+        let (x, y, z) = (f64::from_bits(a), f64::from_bits(b), f64::from_bits(c));
+        let mut fpu = double(RoundingMode::Rne);
+
+        prop_assert!(same_f64(fpu.add(a, b), x + y));
+        prop_assert!(same_f64(fpu.sub(a, b), x - y));
+        prop_assert!(same_f64(fpu.mul(a, b), x * y));
+        prop_assert!(same_f64(fpu.div(a, b), x / y));
+        prop_assert!(same_f64(fpu.sqrt(a), x.sqrt()));
+        prop_assert!(same_f64(fpu.fma(a, b, c, false, false), x.mul_add(y, z)));
+
+        let mut fpu = single(RoundingMode::Rne);
+        prop_assert!(same_f32(fpu.convert(a, softfloat::DOUBLE), x as f32));
+    }
+
+    #[test]
+    fn integer_conversions_match_host(value in any::<u32>(), bits in f64_bits()) {
+        let mut fpu = double(RoundingMode::Rne);
+        prop_assert_eq!(fpu.from_int(value, true), (value as i32 as f64).to_bits());
+        prop_assert_eq!(fpu.from_int(value, false), (value as f64).to_bits());
+
+        let mut fpu = single(RoundingMode::Rne);
+        prop_assert_eq!(fpu.from_int(value, true), (value as i32 as f32).to_bits() as u64);
+
+        // Rust's saturating casts truncate, matching RTZ except for NaN
+        let x = f64::from_bits(bits);
+        let mut fpu = double(RoundingMode::Rtz);
+        if !x.is_nan() {
+            prop_assert_eq!(fpu.to_int(bits, true), x as i32 as u32);
+            prop_assert_eq!(fpu.to_int(bits, false), x as u32);
+        }
+    }
+}
+
+#[test]
+fn test_directed_rounding() {
+    let (one, three) = (1.0f32.to_bits() as u64, 3.0f32.to_bits() as u64);
+    let third = |rm| single(rm).div(one, three);
+
+    // 1/3 = 0x3eaaaaaa.aaa..., so only the upward modes round up
+    assert_eq!(third(RoundingMode::Rne), 0x3eaaaaab);
+    assert_eq!(third(RoundingMode::Rtz), 0x3eaaaaaa);
+    assert_eq!(third(RoundingMode::Rdn), 0x3eaaaaaa);
+    assert_eq!(third(RoundingMode::Rup), 0x3eaaaaab);
+    assert_eq!(third(RoundingMode::Rmm), 0x3eaaaaab);
+    assert_eq!(single(RoundingMode::Rdn).div(one ^ 0x8000_0000, three), 0xbeaaaaab);
+
+    // Ties: 2^24 + 1 is halfway between two representable singles
+    let tie = (1 << 24) + 1;
+    assert_eq!(single(RoundingMode::Rne).from_int(tie, false), 0x4b800000);
+    assert_eq!(single(RoundingMode::Rmm).from_int(tie, false), 0x4b800001);
+
+    // x - x is -0 only when rounding down
+    assert_eq!(single(RoundingMode::Rne).sub(one, one), 0);
+    assert_eq!(single(RoundingMode::Rdn).sub(one, one), 0x8000_0000);
+
+    // Overflow rounds to infinity or the largest finite value
+    let max = f32::MAX.to_bits() as u64;
+    assert_eq!(single(RoundingMode::Rne).add(max, max), f32::INFINITY.to_bits() as u64);
+    assert_eq!(single(RoundingMode::Rtz).add(max, max), max);
+
+    // Float-to-integer conversions use the rounding mode
+    let two_and_half = 2.5f64.to_bits();
+    assert_eq!(double(RoundingMode::Rne).to_int(two_and_half, true), 2);
+    assert_eq!(double(RoundingMode::Rmm).to_int(two_and_half, true), 3);
+    assert_eq!(double(RoundingMode::Rdn).to_int((-2.5f64).to_bits(), true), -3i32 as u32);
+}
+
+#[test]
+fn test_exception_flags() {
+    let flags = |op: fn(&mut SoftFloat) -> u64| {
+        let mut fpu = single(RoundingMode::Rne);
+        op(&mut fpu);
+        fpu.flags
+    };
+
+    assert_eq!(flags(|fpu| fpu.add(bits(1.0), bits(2.0))), 0);
+    assert_eq!(flags(|fpu| fpu.div(bits(1.0), bits(3.0))), FLAG_NX);
+    assert_eq!(flags(|fpu| fpu.div(bits(1.0), bits(0.0))), FLAG_DZ);
+    assert_eq!(flags(|fpu| fpu.mul(bits(f32::MAX), bits(2.0))), FLAG_OF | FLAG_NX);
+    assert_eq!(flags(|fpu| fpu.sqrt(bits(-1.0))), FLAG_NV);
+    assert_eq!(flags(|fpu| fpu.sub(bits(f32::INFINITY), bits(f32::INFINITY))), FLAG_NV);
+    assert_eq!(flags(|fpu| fpu.fma(bits(f32::INFINITY), 0, bits(f32::NAN), false, false)), FLAG_NV);
+
+    // Tiny and inexact results underflow; exact subnormal results do not
+    assert_eq!(flags(|fpu| fpu.mul(1, bits(0.5))), FLAG_UF | FLAG_NX);
+    assert_eq!(flags(|fpu| fpu.mul(bits(f32::MIN_POSITIVE), bits(0.5))), 0);
+    // Tininess is detected after rounding, so results that round up to the
+    // smallest normal number do not underflow
+    // (1 + 2^-23) * (2^-126 - 2^-149) = 2^-126 - 2^-172
+    assert_eq!(flags(|fpu| fpu.mul(0x3f800001, 0x007fffff)), FLAG_NX);
+
+    // Quiet comparisons only signal on signaling NaNs
+    let mut fpu = single(RoundingMode::Rne);
+    assert!(!fpu.eq(bits(f32::NAN), bits(1.0)));
+    assert_eq!(fpu.flags, 0);
+    assert!(!fpu.lt(bits(f32::NAN), bits(1.0)));
+    assert_eq!(fpu.flags, FLAG_NV);
+    let mut fpu = single(RoundingMode::Rne);
+    assert!(!fpu.eq(0x7f800001, bits(1.0)));
+    assert_eq!(fpu.flags, FLAG_NV);
+
+    // Out-of-range conversions saturate and raise invalid
+    let mut fpu = single(RoundingMode::Rne);
+    assert_eq!(fpu.to_int(bits(-1.0), false), 0);
+    assert_eq!(fpu.to_int(bits(f32::NAN), true), i32::MAX as u32);
+    assert_eq!(fpu.flags, FLAG_NV);
+}
+
+#[test]
+fn test_min_max_and_classify() {
+    let mut fpu = single(RoundingMode::Rne);
+
+    assert_eq!(fpu.min(bits(-0.0), bits(0.0)), bits(-0.0));
+    assert_eq!(fpu.max(bits(-0.0), bits(0.0)), bits(0.0));
+    assert_eq!(fpu.min(bits(f32::NAN), bits(2.0)), bits(2.0));
+    assert_eq!(fpu.max(bits(f32::NAN), bits(f32::NAN)), 0x7fc00000);
+    assert_eq!(fpu.flags, 0);
+
+    let classes = [
+        f32::NEG_INFINITY, -1.0, -1e-40, -0.0, 0.0, 1e-40, 1.0, f32::INFINITY,
+    ];
+    for (i, x) in classes.into_iter().enumerate() {
+        assert_eq!(fpu.classify(bits(x)), 1 << i, "{}", x);
+    }
+    assert_eq!(fpu.classify(0x7f800001), 1 << 8);
+    assert_eq!(fpu.classify(0x7fc00000), 1 << 9);
+}
+
+#[test]
+fn test_float_program() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(
+        0x1000,
+        "
+        _start:
+            la t0, data
+            flw fa0, 0(t0)
+            flw fa1, 4(t0)
+            fld fa2, 8(t0)
+            fmul.s fa3, fa0, fa1        # 1.5 * 2.5 = 3.75
+            fcvt.d.s fa4, fa3
+            fadd.d fa4, fa4, fa2        # 3.75 + 0.25 = 4.0
+            fcvt.w.d a0, fa4
+            fsd fa4, 16(t0)
+            fdiv.s fa5, fa0, fa1, rtz   # inexact
+            frflags a1
+            fsqrt.d fa6, fa2            # 0.5
+            fcvt.w.d a2, fa6            # rounds to 0 with frm = rne
+            flt.s a3, fa0, fa1
+            fclass.d a4, fa2
+            fneg.s fa7, fa0
+            fmv.x.w a5, fa7
+            li a7, 93
+            ecall
+        .align 3
+        data:
+            .word 0x3fc00000, 0x40200000
+            .word 0x00000000, 0x3fd00000
+            .word 0, 0
+        ",
+    )?;
+    vm.execute()?;
+
+    let cpu = vm.cpu_state();
+    let reg = |r| cpu.read_register(r);
+    assert_eq!(reg(RegisterIndex::X10), 4);
+    assert_eq!(reg(RegisterIndex::X11), FLAG_NX);
+    assert_eq!(reg(RegisterIndex::X12), 0);
+    assert_eq!(reg(RegisterIndex::X13), 1);
+    assert_eq!(reg(RegisterIndex::X14), 1 << 6);
+    assert_eq!(reg(RegisterIndex::X15), (-1.5f32).to_bits());
+
+    // Singles are NaN-boxed in the 64-bit registers
+    assert_eq!(cpu.read_fp_register(RegisterIndex::X13), 0xffff_ffff_0000_0000 | 3.75f32.to_bits() as u64);
+    assert_eq!(cpu.read_float(FloatFormat::Double, RegisterIndex::X14), 4.0f64.to_bits());
+    assert_eq!(cpu.read_csr(FFLAGS)?, FLAG_NX as u64);
+
+    let stored = vm.memory().load_bytes(reg(RegisterIndex::X5) + 16, 8)?;
+    assert_eq!(f64::from_le_bytes(stored.try_into().unwrap()), 4.0);
+    Ok(())
+}
+
+#[test]
+fn test_dynamic_rounding_mode() -> Result<(), VmError> {
+    let program = |frm: u32| {
+        format!(
+            "
+            li t0, {}
+            fsrm t0
+            li t1, 7
+            fcvt.s.w fa0, t1
+            li t1, 2
+            fcvt.s.w fa1, t1
+            fdiv.s fa2, fa0, fa1    # 3.5
+            fcvt.w.s a0, fa2
+            li a7, 93
+            ecall
+            ",
+            frm
+        )
+    };
+
+    for (frm, expected) in [(0, 4), (1, 3), (2, 3), (3, 4), (4, 4)] {
+        let mut vm = VirtualMachine::new();
+        vm.load_asm(0x1000, &program(frm))?;
+        vm.execute()?;
+        assert_eq!(vm.cpu_state().read_register(RegisterIndex::X10), expected, "frm = {}", frm);
+    }
+
+    // A reserved value in frm makes dynamic-rounding instructions illegal
+    let mut vm = VirtualMachine::with_config(VmConfig { trap_mode: TrapMode::Abort, ..VmConfig::default() });
+    vm.load_asm(0x1000, &program(5))?;
+    assert!(matches!(vm.execute(), Err(VmError::InvalidInstruction(_))));
+    Ok(())
+}
@@ -42,6 +42,49 @@ fn test_register_operations() {
     assert_eq!(vm.cpu_state().read_register(RegisterIndex::X0), 0);
 }
 
+#[test]
+fn test_register_names() -> Result<(), VmError> {
+    assert_eq!("x0".parse::<RegisterIndex>()?, RegisterIndex::X0);
+    assert_eq!("x31".parse::<RegisterIndex>()?, RegisterIndex::X31);
+    assert_eq!("sp".parse::<RegisterIndex>()?, RegisterIndex::X2);
+    assert_eq!("fp".parse::<RegisterIndex>()?, RegisterIndex::X8);
+    assert_eq!("s11".parse::<RegisterIndex>()?, RegisterIndex::X27);
+    assert_eq!("t6".parse::<RegisterIndex>()?, RegisterIndex::X31);
+    for invalid in ["x32", "x01", "a8", "SP", ""] {
+        assert!(matches!(invalid.parse::<RegisterIndex>(), Err(VmError::InvalidRegisterName(_))));
+    }
+
+    for i in 0..32 {
+        let reg = RegisterIndex::from_u32(i).unwrap();
+        assert_eq!(reg.abi_name().parse::<RegisterIndex>()?, reg);
+        assert_eq!(format!("x{}", i).parse::<RegisterIndex>()?, reg);
+    }
+    assert_eq!(RegisterIndex::X8.abi_name(), "s0");
+    Ok(())
+}
+
+#[test]
+fn test_register_classes() {
+    let names = |pred: fn(RegisterIndex) -> bool| {
+        (0..32)
+            .map(|i| RegisterIndex::from_u32(i).unwrap())
+            .filter(|&r| pred(r))
+            .map(RegisterIndex::abi_name)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    assert_eq!(names(RegisterIndex::is_argument), "a0 a1 a2 a3 a4 a5 a6 a7");
+    assert_eq!(
+        names(RegisterIndex::is_caller_saved),
+        "ra t0 t1 t2 a0 a1 a2 a3 a4 a5 a6 a7 t3 t4 t5 t6"
+    );
+    assert_eq!(
+        names(RegisterIndex::is_callee_saved),
+        "sp s0 s1 s2 s3 s4 s5 s6 s7 s8 s9 s10 s11"
+    );
+}
+
 #[test] 
 fn test_memory_operations() -> Result<(), VmError> {
     let mut vm = VirtualMachine::new();
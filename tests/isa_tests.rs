@@ -0,0 +1,113 @@
+//! Tests for ISA string configuration
+
+use zk_risc_v_vm::risc_v::isa::{Extension, IsaConfig, Xlen};
+use zk_risc_v_vm::risc_v::RegisterIndex;
+use zk_risc_v_vm::utils::config::{TrapMode, VmConfig};
+use zk_risc_v_vm::{VirtualMachine, VmError};
+
+#[test]
+fn test_parse_isa_strings() -> Result<(), VmError> {
+    let isa: IsaConfig = "rv32im".parse()?;
+    assert!(isa.has(Extension::I) && isa.has(Extension::M));
+    assert!(!isa.has(Extension::A) && !isa.has(Extension::C) && !isa.has(Extension::Zicsr));
+
+    let isa: IsaConfig = "RV32IMAC_Zicsr_Zbb".parse()?;
+    assert_eq!(isa.to_string(), "rv32imac_zicsr_zbb");
+
+    // `g` expands to imafd_zicsr; multi-letter extensions may follow without `_`
+    assert_eq!("rv32gc".parse::<IsaConfig>()?.to_string(), "rv32imafdc_zicsr");
+    assert_eq!("rv32izicsr".parse::<IsaConfig>()?.to_string(), "rv32i_zicsr");
+
+    assert_eq!(IsaConfig::default().to_string(), "rv32imafdc_zicsr_zba_zbb_zbs_zknd_zkne_zknh");
+
+    let isa: IsaConfig = "rv64imac_zicsr".parse()?;
+    assert_eq!(isa.xlen(), Xlen::Rv64);
+    assert_eq!(isa.to_string(), "rv64imac_zicsr");
+    assert_eq!(IsaConfig::full(Xlen::Rv64).to_string(), "rv64imafdc_zicsr");
+    assert_eq!("rv64gc".parse::<IsaConfig>()?.to_string(), "rv64imafdc_zicsr");
+
+    for invalid in ["rv128i", "rv64i_zbb", "rv32", "rv32e", "rv32imx", "rv32i_zfoo", "rv32id"] {
+        assert!(
+            matches!(invalid.parse::<IsaConfig>(), Err(VmError::InvalidIsa(_))),
+            "{} should be rejected",
+            invalid
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_rv32_only_extensions_refused_on_rv64() -> Result<(), VmError> {
+    let mut isa = IsaConfig::full(Xlen::Rv64);
+    for ext in [Extension::Zba, Extension::Zbb, Extension::Zbs, Extension::Zknd, Extension::Zkne, Extension::Zknh] {
+        assert!(matches!(isa.enable(ext), Err(VmError::InvalidIsa(_))), "{} should be refused", ext.name());
+        assert!(!isa.has(ext));
+    }
+    isa.disable(Extension::M);
+    isa.enable(Extension::M)?;
+    assert!(isa.has(Extension::M));
+    Ok(())
+}
+
+#[test]
+fn test_isa_config_serializes_as_string() -> Result<(), VmError> {
+    let config = VmConfig { isa: "rv32im".parse()?, ..VmConfig::default() };
+    let json = serde_json::to_string(&config)?;
+    assert!(json.contains("\"isa\":\"rv32im\""));
+
+    let parsed: VmConfig = serde_json::from_str(&json)?;
+    assert_eq!(parsed.isa, config.isa);
+    Ok(())
+}
+
+#[test]
+fn test_disabled_extension_is_illegal() -> Result<(), VmError> {
+    let program = "
+        li a0, 6
+        li a1, 7
+        mul a0, a0, a1
+        li a7, 93
+        ecall
+    ";
+
+    let mut vm = VirtualMachine::with_config(VmConfig { isa: "rv32im".parse()?, ..VmConfig::default() });
+    vm.load_asm(0x1000, program)?;
+    vm.execute()?;
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X10), 42);
+
+    let mut vm = VirtualMachine::with_config(VmConfig { isa: "rv32i".parse()?, ..VmConfig::default() });
+    vm.load_asm(0x1000, program)?;
+    assert!(matches!(vm.execute(), Err(VmError::InvalidInstruction(0x02b50533))));
+    Ok(())
+}
+
+#[test]
+fn test_disabled_compressed_traps_as_illegal() -> Result<(), VmError> {
+    let config = VmConfig {
+        isa: "rv32i_zicsr".parse()?,
+        trap_mode: TrapMode::Guest,
+        ..VmConfig::default()
+    };
+    let mut vm = VirtualMachine::with_config(config);
+    vm.load_asm(
+        0x1000,
+        "
+        _start:
+            la t0, handler
+            csrw mtvec, t0
+            .half 0x4515        # c.li a0, 5
+            .align 2
+        handler:
+            csrr a0, mcause
+            csrr a1, mtval
+            li a7, 93
+            ecall
+        ",
+    )?;
+    vm.execute()?;
+
+    let cpu = vm.cpu_state();
+    assert_eq!(cpu.read_register(RegisterIndex::X10), 2);
+    assert_eq!(cpu.read_register(RegisterIndex::X11), 0x4515);
+    Ok(())
+}
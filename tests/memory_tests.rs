@@ -0,0 +1,297 @@
+//! Tests for sparse paged memory
+
+use std::sync::{Arc, Mutex};
+use zk_risc_v_vm::risc_v::RegisterIndex;
+use zk_risc_v_vm::utils::config::{MisalignedPolicy, TrapMode, VmConfig};
+use zk_risc_v_vm::vm::memory::{AccessKind, Memory, Permissions, Region, PAGE_SIZE};
+use zk_risc_v_vm::vm::Device;
+use zk_risc_v_vm::{VirtualMachine, VmError};
+
+#[test]
+fn test_pages_allocated_on_first_store() -> Result<(), VmError> {
+    let mut memory = Memory::new(16 * PAGE_SIZE);
+
+    // Untouched memory reads as zero without allocating
+    assert_eq!(memory.load_word(0x8000_0000)?, 0);
+    assert_eq!(memory.page_count(), 0);
+
+    memory.store_word(0xffff_fffc, 0xdeadbeef)?;
+    memory.store_byte(0x8000_0010, 0x42)?;
+    assert_eq!(memory.load_word(0xffff_fffc)?, 0xdeadbeef);
+    assert_eq!(memory.load_byte(0x8000_0010)?, 0x42);
+
+    let bases: Vec<u32> = memory.pages().map(|(base, _)| base).collect();
+    assert_eq!(bases, [0x8000_0000, 0xffff_f000]);
+
+    // Accesses past the top of the address space do not wrap around
+    assert!(matches!(memory.load_word(0xffff_fffe), Err(VmError::MemoryViolation { address: 0xffff_fffe })));
+    assert!(matches!(memory.store_halfword(0xffff_ffff, 1), Err(VmError::MemoryViolation { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_access_spanning_pages() -> Result<(), VmError> {
+    let mut memory = Memory::new(16 * PAGE_SIZE);
+    memory.store_doubleword(0x1ffc, 0x0123_4567_89ab_cdef)?;
+
+    assert_eq!(memory.page_count(), 2);
+    assert_eq!(memory.load_word(0x1ffc)?, 0x89ab_cdef);
+    assert_eq!(memory.load_word(0x2000)?, 0x0123_4567);
+    assert_eq!(memory.load_bytes(0x1ffe, 4)?, [0xab, 0x89, 0x67, 0x45]);
+    Ok(())
+}
+
+#[test]
+fn test_page_limit() -> Result<(), VmError> {
+    let mut memory = Memory::new(2 * PAGE_SIZE);
+    memory.store_word(0x0000, 1)?;
+    memory.store_word(0x5000, 2)?;
+
+    // A third page is refused, and so is a store that would only partly fit
+    assert!(matches!(memory.store_byte(0x9000, 3), Err(VmError::OutOfMemory { address: 0x9000, limit: 2 })));
+    assert!(matches!(memory.store_word(0x5ffe, 4), Err(VmError::OutOfMemory { .. })));
+    assert_eq!(memory.load_halfword(0x5ffe)?, 0);
+
+    // Existing pages can still be written
+    memory.store_word(0x0ffc, 5)?;
+    assert_eq!(memory.page_count(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_bss_larger_than_memory_limit() -> Result<(), VmError> {
+    let mut memory = Memory::new(4 * PAGE_SIZE);
+    memory.store_word(0x2ffc, 0xffff_ffff)?;
+
+    // 16 MiB of .bss against a 16 KiB limit: only pages already resident are touched
+    memory.load_segment(0x1000, &[1, 2, 3, 4], 16 << 20, Permissions::READ_WRITE)?;
+    assert_eq!(memory.page_count(), 2);
+    assert_eq!(memory.load_word(0x1000)?, 0x0403_0201);
+
+    // Stale contents of resident pages inside .bss are cleared
+    assert_eq!(memory.load_word(0x2ffc)?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_program_in_high_memory() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::with_config(VmConfig { memory_size: 64 * 1024, ..VmConfig::default() });
+    vm.load_asm(
+        0x8000_0000,
+        "
+        _start:
+            li sp, 0xfffffff0
+            li t0, 42
+            sw t0, -4(sp)
+            lw a0, -4(sp)
+            li a7, 93
+            ecall
+        ",
+    )?;
+    vm.execute()?;
+
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X10), 42);
+    assert_eq!(vm.memory().page_count(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_region_permissions() -> Result<(), VmError> {
+    let mut memory = Memory::new(16 * PAGE_SIZE);
+    memory.load_segment(0x1000, &[0x13, 0, 0, 0], 4, Permissions::READ_EXECUTE)?;
+    memory.load_segment(0x2000, &[1, 2], 0x10, Permissions::READ_WRITE)?;
+
+    assert_eq!(memory.fetch_word(0x1000)?, 0x13);
+    assert_eq!(memory.load_word(0x2000)?, 0x0201);
+    memory.store_word(0x200c, 7)?;
+
+    assert!(matches!(
+        memory.store_byte(0x1000, 0),
+        Err(VmError::AccessFault { address: 0x1000, access: AccessKind::Write })
+    ));
+    assert!(matches!(
+        memory.fetch_halfword(0x2000),
+        Err(VmError::AccessFault { address: 0x2000, access: AccessKind::Execute })
+    ));
+    // Gaps between regions are unmapped, including the tail of a straddling access
+    assert!(matches!(
+        memory.load_word(0x200e),
+        Err(VmError::AccessFault { address: 0x2010, access: AccessKind::Read })
+    ));
+
+    // Untouched .bss pages are mapped but not resident, and read as zero
+    memory.load_segment(0x10_0000, &[], 8 * PAGE_SIZE as u32, Permissions::READ_WRITE)?;
+    assert_eq!(memory.load_word(0x10_4000)?, 0);
+    assert_eq!(memory.page_count(), 2);
+
+    // Remapping part of a region splits it
+    memory.map(0x2004, 4, Permissions::READ_ONLY);
+    assert_eq!(memory.regions().len(), 5);
+    assert_eq!(memory.regions()[2], Region { start: 0x2004, size: 4, permissions: Permissions::READ_ONLY });
+    assert!(matches!(memory.store_word(0x2004, 0), Err(VmError::AccessFault { .. })));
+    memory.store_word(0x2008, 0)?;
+    Ok(())
+}
+
+#[test]
+fn test_write_to_text_traps() -> Result<(), VmError> {
+    let source = "
+        _start:
+            la t0, handler
+            csrw mtvec, t0
+            la t0, _start
+            sw zero, 0(t0)
+        handler:
+            csrr a0, mcause
+            csrr a1, mtval
+            li a7, 93
+            ecall
+    ";
+
+    let config = VmConfig { trap_mode: TrapMode::Guest, ..VmConfig::default() };
+    let mut vm = VirtualMachine::with_config(config);
+    vm.load_asm(0x1000, source)?;
+    vm.memory_mut().map(0x1000, 0x100, Permissions::READ_EXECUTE);
+    vm.execute()?;
+
+    // Store access fault
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X10), 7);
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X11), 0x1000);
+    Ok(())
+}
+
+/// Device that records the `(offset, size)` of every store
+#[derive(Default)]
+struct Recorder {
+    stores: Vec<(u32, u8)>,
+}
+
+impl Device for Recorder {
+    fn read(&mut self, _offset: u32, _size: u8) -> Result<u64, VmError> {
+        Ok(0)
+    }
+
+    fn write(&mut self, offset: u32, size: u8, _value: u64) -> Result<(), VmError> {
+        self.stores.push((offset, size));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_misaligned_policies() -> Result<(), VmError> {
+    let mut memory = Memory::new(16 * PAGE_SIZE);
+    memory.set_misaligned_policy(MisalignedPolicy::Trap);
+    memory.store_word(0x1000, 0x1234_5678)?;
+    assert!(matches!(
+        memory.load_word(0x1002),
+        Err(VmError::MisalignedAccess { address: 0x1002, access: AccessKind::Read })
+    ));
+    assert!(matches!(
+        memory.store_halfword(0x1001, 0),
+        Err(VmError::MisalignedAccess { address: 0x1001, access: AccessKind::Write })
+    ));
+    // Raw byte accesses are not subject to the policy
+    assert_eq!(memory.load_bytes(0x1001, 2)?, [0x56, 0x34]);
+
+    memory.set_misaligned_policy(MisalignedPolicy::Split);
+    assert_eq!(memory.load_word(0x1002)?, 0x1234);
+
+    let recorder = Arc::new(Mutex::new(Recorder::default()));
+    memory.attach_device(0x2000, 0x10, recorder.clone())?;
+    memory.store_word(0x2001, 0)?;
+    memory.store_doubleword(0x2008, 0)?;
+    assert_eq!(recorder.lock().unwrap().stores, [(1, 1), (2, 2), (4, 1), (8, 8)]);
+    Ok(())
+}
+
+#[test]
+fn test_split_store_faulting_in_second_piece_writes_nothing() -> Result<(), VmError> {
+    let mut memory = Memory::new(16 * PAGE_SIZE);
+    memory.set_misaligned_policy(MisalignedPolicy::Split);
+    memory.load_segment(0x1000, &[0xaa; 4], 4, Permissions::READ_WRITE)?;
+    memory.load_segment(0x1004, &[0xbb; 4], 4, Permissions::READ_ONLY)?;
+
+    assert!(matches!(
+        memory.store_word(0x1002, 0),
+        Err(VmError::AccessFault { address: 0x1004, access: AccessKind::Write })
+    ));
+    assert_eq!(memory.load_bytes(0x1000, 8)?, [0xaa, 0xaa, 0xaa, 0xaa, 0xbb, 0xbb, 0xbb, 0xbb]);
+    Ok(())
+}
+
+#[test]
+fn test_misaligned_traps_in_guest() -> Result<(), VmError> {
+    let run = |isa: &str, body: &str| -> Result<(u32, u32), VmError> {
+        let config = VmConfig {
+            isa: isa.parse()?,
+            trap_mode: TrapMode::Guest,
+            misaligned: MisalignedPolicy::Trap,
+            ..VmConfig::default()
+        };
+        let mut vm = VirtualMachine::with_config(config);
+        vm.load_asm(
+            0x1000,
+            &format!(
+                "
+                _start:
+                    la t0, handler
+                    csrw mtvec, t0
+                    {}
+                handler:
+                    csrr a0, mcause
+                    csrr a1, mtval
+                    li a7, 93
+                    ecall
+                ",
+                body
+            ),
+        )?;
+        vm.execute()?;
+        let cpu = vm.cpu_state();
+        Ok((cpu.read_register(RegisterIndex::X10), cpu.read_register(RegisterIndex::X11)))
+    };
+
+    assert_eq!(run("rv32i_zicsr", "li t1, 0x3002\n lw t2, 0(t1)")?, (4, 0x3002));
+    assert_eq!(run("rv32i_zicsr", "li t1, 0x3001\n sh zero, 0(t1)")?, (6, 0x3001));
+    // Jump targets must be 4-byte aligned without the C extension
+    assert_eq!(run("rv32i_zicsr", "li t1, 0x3002\n jr t1")?, (0, 0x3002));
+    Ok(())
+}
+
+#[test]
+fn test_misaligned_jump_traps_at_the_jump() -> Result<(), VmError> {
+    let config = VmConfig {
+        isa: "rv32i_zicsr".parse()?,
+        trap_mode: TrapMode::Guest,
+        misaligned: MisalignedPolicy::Trap,
+        ..VmConfig::default()
+    };
+    let mut vm = VirtualMachine::with_config(config);
+    vm.load_asm(
+        0x1000,
+        "
+        _start:
+            la t0, handler
+            csrw mtvec, t0
+            li t1, 0x3002
+            li ra, 7
+        jump:
+            jalr ra, 0(t1)
+        handler:
+            csrr a0, mcause
+            csrr a1, mepc
+            csrr a2, mtval
+            la a3, jump
+            li a7, 93
+            ecall
+        ",
+    )?;
+    vm.execute()?;
+
+    let cpu = vm.cpu_state();
+    assert_eq!(cpu.read_register(RegisterIndex::X10), 0); // instruction address misaligned
+    assert_eq!(cpu.read_register(RegisterIndex::X11), cpu.read_register(RegisterIndex::X13));
+    assert_eq!(cpu.read_register(RegisterIndex::X12), 0x3002);
+    assert_eq!(cpu.read_register(RegisterIndex::X1), 7);
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! Tests for the Merkle memory commitment
+
+use zk_risc_v_vm::vm::memory::{Memory, PAGE_SIZE};
+use zk_risc_v_vm::vm::merkle::WordProof;
+use zk_risc_v_vm::VmError;
+
+#[test]
+fn test_root_depends_only_on_contents() -> Result<(), VmError> {
+    let mut a = Memory::new(16 * PAGE_SIZE);
+    let mut b = Memory::new(16 * PAGE_SIZE);
+    let empty = a.root();
+
+    // Allocating a page with zeros does not change the commitment
+    a.store_word(0x8000_0000, 0)?;
+    assert_eq!(a.root(), empty);
+
+    a.store_word(0x1000, 1)?;
+    a.store_word(0xffff_fffc, 2)?;
+    b.store_word(0xffff_fffc, 2)?;
+    b.store_word(0x1000, 7)?;
+    assert_ne!(a.root(), b.root());
+
+    b.store_word(0x1000, 1)?;
+    assert_eq!(a.root(), b.root());
+
+    a.clear();
+    assert_eq!(a.root(), empty);
+    Ok(())
+}
+
+#[test]
+fn test_word_inclusion_and_update_proofs() -> Result<(), VmError> {
+    let mut memory = Memory::new(16 * PAGE_SIZE);
+    memory.store_word(0x2004, 0xdeadbeef)?;
+    memory.store_word(0x9000, 5)?;
+
+    let root = memory.root();
+    let proof = memory.prove_word(0x2004)?;
+    assert_eq!(proof.value, 0xdeadbeef);
+    assert!(proof.verify(&root));
+
+    let forged = WordProof { value: 0, ..proof.clone() };
+    assert!(!forged.verify(&root));
+
+    // Untouched words are provably zero
+    let untouched = memory.prove_word(0x4000_0000)?;
+    assert_eq!(untouched.value, 0);
+    assert!(untouched.verify(&root));
+
+    // The inclusion proof also proves the root transition of a write
+    memory.store_word(0x2004, 42)?;
+    let new_root = memory.root();
+    assert!(proof.verify_update(&root, 42, &new_root));
+    assert!(!proof.verify_update(&root, 43, &new_root));
+
+    assert!(matches!(memory.prove_word(0x2002), Err(VmError::MisalignedAccess { .. })));
+    Ok(())
+}
@@ -0,0 +1,194 @@
+//! Tests for RV64 execution
+
+use zk_risc_v_vm::risc_v::isa::Xlen;
+use zk_risc_v_vm::risc_v::{Decoder, Disassembler, Instruction, RegisterIndex};
+use zk_risc_v_vm::utils::config::{TrapMode, VmConfig};
+use zk_risc_v_vm::{VirtualMachine, VmError};
+
+use RegisterIndex::*;
+
+fn run(isa: &str, program: &str) -> Result<VirtualMachine, VmError> {
+    let mut vm = VirtualMachine::with_config(VmConfig { isa: isa.parse()?, ..VmConfig::default() });
+    vm.load_asm(0x1000, program)?;
+    vm.execute()?;
+    Ok(vm)
+}
+
+#[test]
+fn test_word_ops_sign_extend() -> Result<(), VmError> {
+    let vm = run(
+        "rv64imac_zicsr",
+        "
+        li t0, 0x7fffffff
+        addi t1, t0, 1          # 0x80000000 in 64 bits
+        addiw t2, t0, 1         # wraps to -0x80000000
+        li t3, 1
+        sllw t4, t3, t0         # shift amount taken mod 32
+        slli t5, t3, 40
+        srliw t6, t2, 31
+        li a7, 93
+        ecall
+        ",
+    )?;
+
+    let cpu = vm.cpu_state();
+    assert_eq!(cpu.read_xreg(X6), 0x8000_0000);
+    assert_eq!(cpu.read_xreg(X7), 0xffff_ffff_8000_0000);
+    assert_eq!(cpu.read_xreg(X29), 0xffff_ffff_8000_0000);
+    assert_eq!(cpu.read_xreg(X30), 1 << 40);
+    assert_eq!(cpu.read_xreg(X31), 1);
+    Ok(())
+}
+
+#[test]
+fn test_doubleword_memory_and_multiply() -> Result<(), VmError> {
+    let vm = run(
+        "rv64imac_zicsr",
+        "
+        la t0, data
+        ld t1, 0(t0)
+        lw t2, 0(t0)
+        lwu t3, 0(t0)
+        mulhu t4, t1, t1
+        divw t5, t1, t1
+        sd t1, 8(t0)
+        ld t6, 8(t0)
+        li a7, 93
+        ecall
+        .align 3
+    data:
+        .dword 0xfedcba9887654321
+        .dword 0
+        ",
+    )?;
+
+    let cpu = vm.cpu_state();
+    let value = 0xfedc_ba98_8765_4321u64;
+    assert_eq!(cpu.read_xreg(X6), value);
+    assert_eq!(cpu.read_xreg(X7), 0xffff_ffff_8765_4321);
+    assert_eq!(cpu.read_xreg(X28), 0x8765_4321);
+    assert_eq!(cpu.read_xreg(X29), ((value as u128 * value as u128) >> 64) as u64);
+    assert_eq!(cpu.read_xreg(X30), 1);
+    assert_eq!(cpu.read_xreg(X31), value);
+    Ok(())
+}
+
+#[test]
+fn test_rv64_instructions_illegal_on_rv32() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(0x1000, "addw a0, a0, a1")?;
+    assert!(matches!(vm.execute(), Err(VmError::InvalidInstruction(_))));
+
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(0x1000, "fcvt.l.d a0, fa0")?;
+    assert!(matches!(vm.execute(), Err(VmError::InvalidInstruction(_))));
+
+    // slli with a shift amount of 32 or more only exists on RV64
+    let mut vm = VirtualMachine::new();
+    vm.load_binary(0x1000, &0x0200_9093u32.to_le_bytes())?;
+    vm.set_pc(0x1000);
+    assert!(matches!(vm.execute(), Err(VmError::InvalidInstruction(0x0200_9093))));
+    Ok(())
+}
+
+#[test]
+fn test_float_conversions_and_moves_are_xlen_wide() -> Result<(), VmError> {
+    let vm = run(
+        "rv64gc",
+        "
+        li t0, -5
+        fcvt.d.l ft0, t0
+        fcvt.l.d t1, ft0
+        fmv.x.d t2, ft0
+        fmv.d.x ft1, t2
+        feq.d t3, ft0, ft1
+        li t4, -1
+        fcvt.s.lu ft2, t4        # rounds up to 2^64
+        fcvt.lu.s t5, ft2        # out of range, saturates
+        fcvt.s.w ft3, t4
+        fmv.x.w t6, ft3          # sign-extended bits of -1.0f
+        li a7, 93
+        ecall
+        ",
+    )?;
+
+    let cpu = vm.cpu_state();
+    assert_eq!(cpu.read_xreg(X6), -5i64 as u64);
+    assert_eq!(cpu.read_xreg(X7), (-5.0f64).to_bits());
+    assert_eq!(cpu.read_xreg(X28), 1);
+    assert_eq!(cpu.read_xreg(X30), u64::MAX);
+    assert_eq!(cpu.read_xreg(X31), (-1.0f32).to_bits() as i32 as i64 as u64);
+    Ok(())
+}
+
+#[test]
+fn test_compressed_rv64_forms() -> Result<(), VmError> {
+    // c.ld a0, 8(a1)
+    assert_eq!(
+        Decoder::decode_compressed_xlen(0x6588, Xlen::Rv64)?,
+        Instruction::Ld { rd: X10, rs1: X11, imm: 8 }
+    );
+    // The same encoding is c.flw on RV32
+    assert!(!matches!(Decoder::decode_compressed(0x6588)?, Instruction::Ld { .. }));
+
+    let disasm = Disassembler::new().with_xlen(Xlen::Rv64);
+    assert!(disasm.disassemble(&0x2505u16.to_le_bytes(), 0x1000).contains("addiw a0, a0, 1"));
+    Ok(())
+}
+
+#[test]
+fn test_csrs_are_xlen_wide() -> Result<(), VmError> {
+    let vm = run(
+        "rv64imac_zicsr",
+        "
+        li t0, 1
+        slli t0, t0, 40
+        addi t0, t0, 4
+        csrw mscratch, t0
+        csrr t1, mscratch
+        csrw mepc, t0
+        csrr t2, mepc
+        li a7, 93
+        ecall
+        ",
+    )?;
+    assert_eq!(vm.cpu_state().read_xreg(X6), (1 << 40) + 4);
+    assert_eq!(vm.cpu_state().read_xreg(X7), (1 << 40) + 4);
+
+    // The upper-half counters only exist on RV32
+    let result = run("rv64imac_zicsr", "csrr a0, cycleh");
+    assert!(matches!(result, Err(VmError::InvalidCsr(0xc80))));
+    Ok(())
+}
+
+#[test]
+fn test_address_above_4gib_traps_to_guest() -> Result<(), VmError> {
+    let config = VmConfig { isa: "rv64imac_zicsr".parse()?, trap_mode: TrapMode::Guest, ..VmConfig::default() };
+    let mut vm = VirtualMachine::with_config(config);
+    vm.load_asm(
+        0x1000,
+        "
+        _start:
+            la t0, handler
+            csrw mtvec, t0
+            li t1, 1
+            slli t1, t1, 32
+        fault:
+            lw t2, 8(t1)
+        handler:
+            csrr a0, mcause
+            csrr a1, mepc
+            csrr a2, mtval
+            la a3, fault
+            li a7, 93
+            ecall
+        ",
+    )?;
+    vm.execute()?;
+
+    let cpu = vm.cpu_state();
+    assert_eq!(cpu.read_xreg(X10), 5); // load access fault
+    assert_eq!(cpu.read_xreg(X11), cpu.read_xreg(X13));
+    assert_eq!(cpu.read_xreg(X12), 8);
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! Tests for VM snapshots
+
+use zk_risc_v_vm::risc_v::RegisterIndex;
+use zk_risc_v_vm::{VirtualMachine, VmError};
+
+#[test]
+fn test_restore_runs_variants_from_snapshot() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(
+        0x1000,
+        "
+        _start:
+            la t0, counter
+            lw t1, 0(t0)
+            add t1, t1, a0
+            sw t1, 0(t0)
+            mv a0, t1
+            li a7, 93
+            ecall
+        counter:
+            .word 100
+        ",
+    )?;
+    let snapshot = vm.snapshot();
+
+    for input in [1, 2, 3] {
+        vm.restore(&snapshot);
+        vm.set_register(RegisterIndex::X10, input);
+        vm.execute()?;
+        assert_eq!(vm.cpu_state().read_register(RegisterIndex::X10), 100 + input);
+    }
+
+    // Writes after the snapshot do not leak into it
+    assert_eq!(vm.memory().load_word(0x1020)?, 103);
+    assert_eq!(snapshot.memory().load_word(0x1020)?, 100);
+    assert_eq!(snapshot.cpu_state().get_cycles(), 0);
+
+    vm.restore(&snapshot);
+    assert_eq!(vm.memory().load_word(0x1020)?, 100);
+    assert_eq!(vm.cpu_state().get_pc(), 0x1000);
+    assert_eq!(vm.cpu_state().get_cycles(), 0);
+    Ok(())
+}
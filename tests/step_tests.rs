@@ -0,0 +1,71 @@
+//! Tests for step-wise execution
+
+use zk_risc_v_vm::risc_v::RegisterIndex;
+use zk_risc_v_vm::utils::config::VmConfig;
+use zk_risc_v_vm::{ExitReason, VirtualMachine, VmError};
+
+const PROGRAM: &str = "
+    _start:
+        li t0, 0
+        li t1, 10
+    loop:
+        addi t0, t0, 1
+        bne t0, t1, loop
+    done:
+        li a0, 7
+        li a7, 93
+        ecall
+";
+
+#[test]
+fn test_step_and_run_for() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(0x1000, PROGRAM)?;
+
+    assert!(matches!(vm.step(), ExitReason::Paused));
+    assert_eq!(vm.cpu_state().get_pc(), 0x1004);
+
+    assert!(matches!(vm.run_for(3), ExitReason::Paused));
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X5), 1);
+    assert_eq!(vm.exit_code(), None);
+
+    assert!(matches!(vm.run_for(1000), ExitReason::Halted { code: 7 }));
+    assert_eq!(vm.exit_code(), Some(7));
+    Ok(())
+}
+
+#[test]
+fn test_run_until() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(0x1000, PROGRAM)?;
+
+    // `loop` is hit on every iteration, without executing the instruction there
+    assert!(matches!(vm.run_until(0x1008), ExitReason::Breakpoint { pc: 0x1008 }));
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X5), 0);
+    assert!(matches!(vm.run_until(0x1008), ExitReason::Breakpoint { pc: 0x1008 }));
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X5), 1);
+
+    assert!(matches!(vm.run_until(0x1010), ExitReason::Breakpoint { pc: 0x1010 }));
+    assert_eq!(vm.cpu_state().read_register(RegisterIndex::X5), 10);
+    assert!(matches!(vm.run(), ExitReason::Halted { code: 7 }));
+    Ok(())
+}
+
+#[test]
+fn test_exit_reasons() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::with_config(VmConfig { max_cycles: 5, ..VmConfig::default() });
+    vm.load_asm(0x1000, "spin: j spin")?;
+    assert!(matches!(vm.run(), ExitReason::CycleLimit));
+
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(0x1000, "nop\n ebreak")?;
+    assert!(matches!(vm.run(), ExitReason::Breakpoint { pc: 0x1004 }));
+
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(0x1000, "nop\n .word 0xffffffff")?;
+    assert!(matches!(
+        vm.run(),
+        ExitReason::Fault { pc: 0x1004, error: VmError::InvalidInstruction(0xffffffff) }
+    ));
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Tests for execution trace recording
+
+use zk_risc_v_vm::utils::config::VmConfig;
+use zk_risc_v_vm::zk::MemoryAccess;
+use zk_risc_v_vm::{ExitReason, VirtualMachine, VmError};
+
+#[test]
+fn test_trace_records_steps_and_memory() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(
+        0x1000,
+        "
+        _start:
+            li a0, 0x1234
+            la t0, data
+            sw a0, 0(t0)
+            lw a1, 0(t0)
+            lbu a2, 1(t0)
+            li a7, 93
+            ecall
+        data:
+            .word 0
+        ",
+    )?;
+    let (reason, trace) = vm.run_traced();
+    assert!(matches!(reason, ExitReason::Halted { code: 0x1234 }));
+
+    // li expands to lui + addi, la to auipc + addi
+    let steps = trace.instructions.len();
+    assert_eq!(steps, 9);
+    assert_eq!(trace.pcs.len(), steps);
+    assert_eq!(trace.cycles.len(), steps);
+    assert_eq!(trace.register_states.len(), steps + 1);
+    assert_eq!(trace.pcs[..3], [0x1000, 0x1004, 0x1008]);
+    assert_eq!(trace.instructions[8], 0x00000073);
+    assert_eq!(trace.cycle_count, vm.cpu_state().get_cycles());
+
+    // Each step goes from one register state to the next
+    assert_eq!(trace.register_states[0][10], 0);
+    assert_eq!(trace.register_states[1][10], 0x1000);
+    assert_eq!(trace.register_states[2][10], 0x1234);
+    assert_eq!(trace.register_states[steps][12], 0x12);
+
+    let data = 0x1024;
+    let access = |step: usize, address, value, width, is_write| MemoryAccess {
+        address,
+        value,
+        width,
+        is_write,
+        cycle: trace.cycles[step],
+    };
+    assert_eq!(
+        trace.memory_accesses,
+        [
+            access(4, data, 0x1234, 4, true),
+            access(5, data, 0x1234, 4, false),
+            access(6, data + 1, 0x12, 1, false),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_execute_with_proof_uses_trace() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::with_config(VmConfig { enable_proofs: true, ..VmConfig::default() });
+    vm.load_asm(0x1000, "li a7, 93\n ecall")?;
+    vm.execute_with_proof()?;
+
+    // Recording stops once execution finishes
+    vm.memory_mut().store_word(0x2000, 1)?;
+    assert!(vm.memory_mut().take_accesses().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_trace_records_write_syscall_buffer() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(
+        0x1000,
+        "
+        _start:
+            li a0, 1
+            la a1, message
+            li a2, 2
+            li a7, 64
+            ecall
+            li a7, 93
+            ecall
+        message:
+            .byte 0x6f, 0x6b
+        ",
+    )?;
+    let (_, trace) = vm.run_traced();
+
+    let ecall = trace.instructions.iter().position(|&bits| bits == 0x00000073).unwrap();
+    let reads: Vec<_> = trace.memory_accesses.iter().map(|a| (a.address, a.value, a.width, a.cycle)).collect();
+    let message = trace.register_states[ecall][11] as u32;
+    let cycle = trace.cycles[ecall];
+    assert_eq!(reads, [(message, 0x6f, 1, cycle), (message + 1, 0x6b, 1, cycle)]);
+    Ok(())
+}
+
+#[test]
+fn test_trace_kept_when_execution_stops_early() -> Result<(), VmError> {
+    let mut vm = VirtualMachine::new();
+    vm.load_asm(0x1000, "li t0, 5\n sw t0, 0(zero)\n .word 0xffffffff")?;
+    let (reason, trace) = vm.run_traced();
+
+    assert!(matches!(reason, ExitReason::Fault { pc: 0x1008, error: VmError::InvalidInstruction(0xffffffff) }));
+    assert_eq!(trace.pcs, [0x1000, 0x1004, 0x1008]);
+    assert_eq!(trace.register_states.len(), 4);
+    assert_eq!(trace.register_states[3], trace.register_states[2]);
+    assert_eq!(trace.memory_accesses.len(), 1);
+
+    // Hitting the cycle limit records only the steps that ran
+    let mut vm = VirtualMachine::with_config(VmConfig { max_cycles: 3, ..VmConfig::default() });
+    vm.load_asm(0x1000, "loop:\n j loop")?;
+    let (reason, trace) = vm.run_traced();
+    assert!(matches!(reason, ExitReason::CycleLimit));
+    assert_eq!(trace.pcs.len(), 3);
+    assert_eq!(trace.cycle_count, 3);
+    Ok(())
+}